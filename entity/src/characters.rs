@@ -2,8 +2,9 @@
 
 use super::sea_orm_active_enums::Class;
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "characters")]
 pub struct Model {
     #[sea_orm(column_type = "Text")]
@@ -2,13 +2,16 @@
 
 pub mod prelude;
 
+pub mod api_tokens;
 pub mod characters;
+pub mod custom_content;
 pub mod guildmates;
 pub mod lobby;
 pub mod lobby_player;
 pub mod sea_orm_active_enums;
 pub mod seaql_migrations;
 pub mod servers;
+pub mod webhooks;
 
 pub use sea_orm;
 
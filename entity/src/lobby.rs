@@ -1,9 +1,9 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.7.0
 
-use super::sea_orm_active_enums::Content;
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "lobby")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -16,10 +16,27 @@ pub struct Model {
     pub message_id: String,
     #[sea_orm(column_type = "Text")]
     pub lobby_master: String,
-    pub content: Content,
+    #[sea_orm(column_type = "Text")]
+    pub content: String,
     pub created: DateTimeUtc,
     pub scheduled: Option<DateTimeUtc>,
     pub active: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub difficulty: Option<String>,
+    pub gate_start: Option<i32>,
+    pub gate_end: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub note: Option<String>,
+    pub min_clears: Option<i32>,
+    /// Comma separated engravings.
+    #[sea_orm(column_type = "Text", nullable)]
+    pub engravings: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub voice: bool,
+    #[serde(default)]
+    pub gold_run: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
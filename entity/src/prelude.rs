@@ -1,8 +1,11 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.6.0
 
+pub use super::api_tokens::Entity as ApiTokens;
 pub use super::characters::Entity as Characters;
+pub use super::custom_content::Entity as CustomContent;
 pub use super::guildmates::Entity as Guildmates;
 pub use super::lobby::Entity as Lobby;
 pub use super::lobby_player::Entity as LobbyPlayer;
 pub use super::seaql_migrations::Entity as SeaqlMigrations;
 pub use super::servers::Entity as Servers;
+pub use super::webhooks::Entity as Webhooks;
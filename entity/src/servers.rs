@@ -1,8 +1,9 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.7.0
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "servers")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false, column_type = "Text")]
@@ -10,6 +11,28 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub guild_name: String,
     pub timezone: i32,
+    /// Missing in the archives made before the locales.
+    #[sea_orm(column_type = "Text")]
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// Users can join a lobby with only one of their characters.
+    #[serde(default = "default_single_character")]
+    pub single_character: bool,
+    /// Minutes between the lobbies of a character, 0 allows any.
+    #[serde(default = "default_conflict_minutes")]
+    pub conflict_minutes: i32,
+}
+
+fn default_locale() -> String {
+    "en".to_string()
+}
+
+fn default_single_character() -> bool {
+    true
+}
+
+fn default_conflict_minutes() -> i32 {
+    60
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -18,6 +41,12 @@ pub enum Relation {
     Lobby,
     #[sea_orm(has_many = "super::guildmates::Entity")]
     Guildmates,
+    #[sea_orm(has_many = "super::api_tokens::Entity")]
+    ApiTokens,
+    #[sea_orm(has_many = "super::webhooks::Entity")]
+    Webhooks,
+    #[sea_orm(has_many = "super::custom_content::Entity")]
+    CustomContent,
 }
 
 impl Related<super::lobby::Entity> for Entity {
@@ -32,4 +61,22 @@ impl Related<super::guildmates::Entity> for Entity {
     }
 }
 
+impl Related<super::api_tokens::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiTokens.def()
+    }
+}
+
+impl Related<super::webhooks::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Webhooks.def()
+    }
+}
+
+impl Related<super::custom_content::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CustomContent.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
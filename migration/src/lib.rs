@@ -1,7 +1,19 @@
 pub use sea_schema::migration::*;
 
+use sea_schema::migration::prelude::*;
+
 mod m20220101_000001_create_table;
 mod m20220319_000001_create_lobby_tables;
+mod m20220402_000001_create_api_tokens_table;
+mod m20220403_000001_create_webhooks_table;
+mod m20220601_000001_content_key;
+mod m20220602_000001_create_custom_content_table;
+mod m20220603_000001_lobby_content_options;
+mod m20220604_000001_server_locale;
+mod m20220605_000001_lobby_note;
+mod m20220606_000001_lobby_requirements;
+mod m20220607_000001_server_lobby_settings;
+mod m20220608_000001_lobby_player_slot;
 
 pub struct Migrator;
 
@@ -11,6 +23,37 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20220319_000001_create_lobby_tables::Migration),
+            Box::new(m20220402_000001_create_api_tokens_table::Migration),
+            Box::new(m20220403_000001_create_webhooks_table::Migration),
+            Box::new(m20220601_000001_content_key::Migration),
+            Box::new(m20220602_000001_create_custom_content_table::Migration),
+            Box::new(m20220603_000001_lobby_content_options::Migration),
+            Box::new(m20220604_000001_server_locale::Migration),
+            Box::new(m20220605_000001_lobby_note::Migration),
+            Box::new(m20220606_000001_lobby_requirements::Migration),
+            Box::new(m20220607_000001_server_lobby_settings::Migration),
+            Box::new(m20220608_000001_lobby_player_slot::Migration),
         ]
     }
 }
+
+/// Active enums are stored as enum types on PostgreSQL and as text on other backends.
+pub(crate) fn enum_column<C, N, S, V>(
+    backend: DbBackend,
+    column: C,
+    enum_name: N,
+    variants: V,
+) -> ColumnDef
+where
+    C: IntoIden,
+    N: IntoIden,
+    S: IntoIden,
+    V: IntoIterator<Item = S>,
+{
+    let mut column = ColumnDef::new(column);
+    match backend {
+        DbBackend::Postgres => column.enumeration(enum_name, variants),
+        _ => column.text(),
+    };
+    column.not_null().to_owned()
+}
@@ -4,6 +4,8 @@ use entity::{characters, guildmates, servers};
 use entity::{prelude::*, sea_orm_active_enums};
 use sea_schema::migration::prelude::*;
 
+use crate::enum_column;
+
 pub struct Migration;
 
 impl MigrationName for Migration {
@@ -29,36 +31,40 @@ impl Iden for IdenClass {
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        // Try to delete the types first.
-        if let Err(err) = manager
-            .drop_type(TypeDropStatement::new().name(IdenClass).to_owned())
-            .await
-        {
-            println!(
-                "Couldn't delete type {}: {} \nContinuing...",
-                IdenClass.quoted('"'),
-                &err
-            );
-        }
-        if let Err(err) = manager
-            .drop_type(TypeDropStatement::new().name(IdenRole).to_owned())
-            .await
-        {
-            println!(
-                "Couldn't delete type {}: {} \nContinuing...",
-                IdenRole.quoted('"'),
-                &err
-            );
-        }
-
         let db = manager.get_database_backend();
-        let schema = sea_orm::Schema::new(db);
-        manager
-            .create_type(schema.create_enum_from_active_enum::<sea_orm_active_enums::Class>())
-            .await?;
-        manager
-            .create_type(schema.create_enum_from_active_enum::<sea_orm_active_enums::Role>())
-            .await?;
+
+        // Only PostgreSQL has enum types
+        if db == DbBackend::Postgres {
+            // Try to delete the types first.
+            if let Err(err) = manager
+                .drop_type(TypeDropStatement::new().name(IdenClass).to_owned())
+                .await
+            {
+                println!(
+                    "Couldn't delete type {}: {} \nContinuing...",
+                    IdenClass.quoted('"'),
+                    &err
+                );
+            }
+            if let Err(err) = manager
+                .drop_type(TypeDropStatement::new().name(IdenRole).to_owned())
+                .await
+            {
+                println!(
+                    "Couldn't delete type {}: {} \nContinuing...",
+                    IdenRole.quoted('"'),
+                    &err
+                );
+            }
+
+            let schema = sea_orm::Schema::new(db);
+            manager
+                .create_type(schema.create_enum_from_active_enum::<sea_orm_active_enums::Class>())
+                .await?;
+            manager
+                .create_type(schema.create_enum_from_active_enum::<sea_orm_active_enums::Role>())
+                .await?;
+        }
 
         manager
             .create_table(
@@ -89,11 +95,12 @@ impl MigrationTrait for Migration {
                             .text()
                             .not_null(),
                     )
-                    .col(
-                        ColumnDef::new(guildmates::Column::Role)
-                            .enumeration("role", sea_orm_active_enums::Role::iter())
-                            .not_null(),
-                    )
+                    .col(&mut enum_column(
+                        db,
+                        guildmates::Column::Role,
+                        "role",
+                        sea_orm_active_enums::Role::iter(),
+                    ))
                     .primary_key(
                         Index::create()
                             .col(guildmates::Column::Id)
@@ -121,11 +128,12 @@ impl MigrationTrait for Migration {
                             .not_null(),
                     )
                     .col(ColumnDef::new(characters::Column::Name).text().not_null())
-                    .col(
-                        ColumnDef::new(characters::Column::Class)
-                            .enumeration("class", sea_orm_active_enums::Class::iter())
-                            .not_null(),
-                    )
+                    .col(&mut enum_column(
+                        db,
+                        characters::Column::Class,
+                        "class",
+                        sea_orm_active_enums::Class::iter(),
+                    ))
                     .col(
                         ColumnDef::new(characters::Column::ItemLevel)
                             .integer()
@@ -188,12 +196,14 @@ impl MigrationTrait for Migration {
             .await?;
 
         // Drop Enums
-        manager
-            .drop_type(TypeDropStatement::new().name(IdenClass).to_owned())
-            .await?;
-        manager
-            .drop_type(TypeDropStatement::new().name(IdenRole).to_owned())
-            .await?;
+        if manager.get_database_backend() == DbBackend::Postgres {
+            manager
+                .drop_type(TypeDropStatement::new().name(IdenClass).to_owned())
+                .await?;
+            manager
+                .drop_type(TypeDropStatement::new().name(IdenRole).to_owned())
+                .await?;
+        }
 
         Ok(())
     }
@@ -1,11 +1,13 @@
 use entity::lobby::Entity as Lobby;
 use entity::lobby_player::Entity as LobbyPlayer;
-use entity::sea_orm::sea_query::extension::postgres::TypeDropStatement;
-use entity::sea_orm::Iterable;
+use entity::sea_orm::sea_query::extension::postgres::{Type, TypeDropStatement};
+use entity::servers;
 use entity::{characters, guildmates, prelude::*};
 use entity::{lobby, lobby_player};
-use entity::{sea_orm_active_enums, servers};
 use sea_schema::migration::prelude::*;
+
+use crate::enum_column;
+
 pub struct Migration;
 
 impl MigrationName for Migration {
@@ -14,7 +16,46 @@ impl MigrationName for Migration {
     }
 }
 
-struct IdenContent;
+/// Contents known when the lobby tables were created. Contents are stored as text since
+/// `m20220601_000001_content_key`, new contents are added to the catalog files only.
+pub(crate) const LEGACY_CONTENTS: [&str; 34] = [
+    "Urnil",
+    "Lumerus",
+    "IcyLegoros",
+    "Vertus",
+    "Chromanium",
+    "Nacrasena",
+    "FlameFoxYoho",
+    "Tytalos",
+    "DarkLegoros",
+    "Helgaia",
+    "Calventus",
+    "Achates",
+    "FrostHelgaia",
+    "LavaChromanium",
+    "Levanos",
+    "Alberhastic",
+    "ArmoredNacrasena",
+    "Igrexion",
+    "NightFoxYoho",
+    "Velganos",
+    "DemonBeastCanyon",
+    "NecromancersOrigin",
+    "HallOfTheTwistedWarlord",
+    "HildebrandtPalace",
+    "RoadOfLament",
+    "ForgeOfFallenPride",
+    "SeaOfIndolence",
+    "TranquilKarkosa",
+    "AlaricsSanctuary",
+    "AirasOculus",
+    "OrehaPreveza",
+    "Argos1",
+    "Argos2",
+    "Argos3",
+];
+
+pub(crate) struct IdenContent;
 
 impl Iden for IdenContent {
     fn unquoted(&self, s: &mut dyn std::fmt::Write) {
@@ -25,21 +66,29 @@ impl Iden for IdenContent {
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        if let Err(err) = manager
-            .drop_type(TypeDropStatement::new().name(IdenContent).to_owned())
-            .await
-        {
-            println!(
-                "Couldn't delete type {}: {} \nContinuing...",
-                IdenContent.quoted('"'),
-                &err
-            );
-        }
         let db = manager.get_database_backend();
-        let schema = sea_orm::Schema::new(db);
-        manager
-            .create_type(schema.create_enum_from_active_enum::<sea_orm_active_enums::Content>())
-            .await?;
+
+        // Only PostgreSQL has enum types
+        if db == DbBackend::Postgres {
+            if let Err(err) = manager
+                .drop_type(TypeDropStatement::new().name(IdenContent).to_owned())
+                .await
+            {
+                println!(
+                    "Couldn't delete type {}: {} \nContinuing...",
+                    IdenContent.quoted('"'),
+                    &err
+                );
+            }
+            manager
+                .create_type(
+                    Type::create()
+                        .as_enum(IdenContent)
+                        .values(LEGACY_CONTENTS.map(Alias::new))
+                        .to_owned(),
+                )
+                .await?;
+        }
         // TODO!: Create Types
         manager
             .create_table(
@@ -56,11 +105,12 @@ impl MigrationTrait for Migration {
                     .col(ColumnDef::new(lobby::Column::ChannelId).text().not_null())
                     .col(ColumnDef::new(lobby::Column::MessageId).text().not_null())
                     .col(ColumnDef::new(lobby::Column::LobbyMaster).text().not_null())
-                    .col(
-                        ColumnDef::new(lobby::Column::Content)
-                            .enumeration("content", sea_orm_active_enums::Content::iter())
-                            .not_null(),
-                    )
+                    .col(&mut enum_column(
+                        db,
+                        lobby::Column::Content,
+                        "content",
+                        LEGACY_CONTENTS.map(Alias::new),
+                    ))
                     .col(
                         ColumnDef::new(lobby::Column::Created)
                             .timestamp_with_time_zone()
@@ -169,9 +219,11 @@ impl MigrationTrait for Migration {
         manager
             .drop_table(sea_query::Table::drop().if_exists().table(Lobby).to_owned())
             .await?;
-        manager
-            .drop_type(TypeDropStatement::new().name(IdenContent).to_owned())
-            .await?;
+        if manager.get_database_backend() == DbBackend::Postgres {
+            manager
+                .drop_type(TypeDropStatement::new().name(IdenContent).to_owned())
+                .await?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,61 @@
+use entity::api_tokens::{self, Entity as ApiTokens};
+use entity::{prelude::*, servers};
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220402_000001_create_api_tokens_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                sea_query::Table::create()
+                    .table(ApiTokens)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(api_tokens::Column::GuildId)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(api_tokens::Column::TokenHash)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(api_tokens::Column::Created)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-apitokens-servers")
+                            .from(ApiTokens, api_tokens::Column::GuildId)
+                            .to(Servers, servers::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                sea_query::Table::drop()
+                    .if_exists()
+                    .table(ApiTokens)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,58 @@
+use entity::webhooks::{self, Entity as Webhooks};
+use entity::{prelude::*, servers};
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220403_000001_create_webhooks_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                sea_query::Table::create()
+                    .table(Webhooks)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(webhooks::Column::WebhookId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(webhooks::Column::GuildId).text().not_null())
+                    .col(ColumnDef::new(webhooks::Column::Url).text().not_null())
+                    .col(ColumnDef::new(webhooks::Column::Secret).text().not_null())
+                    .col(
+                        ColumnDef::new(webhooks::Column::Created)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-webhooks-servers")
+                            .from(Webhooks, webhooks::Column::GuildId)
+                            .to(Servers, servers::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                sea_query::Table::drop()
+                    .if_exists()
+                    .table(Webhooks)
+                    .to_owned(),
+            )
+            .await
+    }
+}
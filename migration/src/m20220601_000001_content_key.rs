@@ -0,0 +1,64 @@
+use entity::sea_orm::sea_query::extension::postgres::{Type, TypeDropStatement};
+use entity::sea_orm::{ConnectionTrait, Statement};
+use sea_schema::migration::prelude::*;
+
+use crate::m20220319_000001_create_lobby_tables::{IdenContent, LEGACY_CONTENTS};
+
+/// Stores the contents of the lobbies by their catalog keys instead of an enum type.
+///
+/// Keys of the catalog are the old enum values, so only the PostgreSQL column needs a change.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220601_000001_content_key"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_database_backend();
+        if db != DbBackend::Postgres {
+            return Ok(());
+        }
+
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                db,
+                r#"ALTER TABLE "lobby" ALTER COLUMN "content" TYPE text USING "content"::text"#
+                    .to_string(),
+            ))
+            .await?;
+        manager
+            .drop_type(TypeDropStatement::new().name(IdenContent).to_owned())
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_database_backend();
+        if db != DbBackend::Postgres {
+            return Ok(());
+        }
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(IdenContent)
+                    .values(LEGACY_CONTENTS.map(Alias::new))
+                    .to_owned(),
+            )
+            .await?;
+        // Lobbies of the contents added after the enum can't be converted back
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                db,
+                r#"ALTER TABLE "lobby" ALTER COLUMN "content" TYPE "content" USING "content"::"content""#
+                    .to_string(),
+            ))
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use entity::custom_content::{self, Entity as CustomContent};
+use entity::{prelude::*, servers};
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220602_000001_create_custom_content_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                sea_query::Table::create()
+                    .table(CustomContent)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(custom_content::Column::ContentId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(custom_content::Column::GuildId)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(custom_content::Column::Name)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(custom_content::Column::ContentSize)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(custom_content::Column::IlvlReq)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(custom_content::Column::Description)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(custom_content::Column::Image).text())
+                    .col(
+                        ColumnDef::new(custom_content::Column::Created)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-custom_content-servers")
+                            .from(CustomContent, custom_content::Column::GuildId)
+                            .to(Servers, servers::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                sea_query::Index::create()
+                    .name("idx-custom_content-guild_id-name")
+                    .table(CustomContent)
+                    .col(custom_content::Column::GuildId)
+                    .col(custom_content::Column::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                sea_query::Table::drop()
+                    .if_exists()
+                    .table(CustomContent)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,101 @@
+use entity::lobby::{self, Entity as Lobby};
+use entity::sea_orm::ConnectionTrait;
+use sea_schema::migration::prelude::*;
+
+/// Adds the difficulty and the gates of the lobbies and merges the phases of Argos into a
+/// single content with three gates.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220603_000001_lobby_content_options"
+    }
+}
+
+const ARGOS_PHASES: [(&str, i32); 3] = [("Argos1", 1), ("Argos2", 2), ("Argos3", 3)];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can only add one column at a time
+        for mut column in [
+            ColumnDef::new(lobby::Column::Difficulty).text().to_owned(),
+            ColumnDef::new(lobby::Column::GateStart)
+                .integer()
+                .to_owned(),
+            ColumnDef::new(lobby::Column::GateEnd).integer().to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    sea_query::Table::alter()
+                        .table(Lobby)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        for (phase, gate) in ARGOS_PHASES {
+            update_lobbies(
+                manager,
+                sea_query::Query::update()
+                    .table(Lobby)
+                    .values(vec![
+                        (lobby::Column::Content, "Argos".into()),
+                        (lobby::Column::GateStart, gate.into()),
+                        (lobby::Column::GateEnd, gate.into()),
+                    ])
+                    .and_where(Expr::col(lobby::Column::Content).eq(phase))
+                    .to_owned(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Lobbies of more than one phase are kept as their first phase
+        for (phase, gate) in ARGOS_PHASES {
+            update_lobbies(
+                manager,
+                sea_query::Query::update()
+                    .table(Lobby)
+                    .values(vec![(lobby::Column::Content, phase.into())])
+                    .and_where(Expr::col(lobby::Column::Content).eq("Argos"))
+                    .and_where(Expr::col(lobby::Column::GateStart).eq(gate))
+                    .to_owned(),
+            )
+            .await?;
+        }
+
+        // Older SQLite versions can't drop columns, unused columns don't hurt there
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        for column in [
+            lobby::Column::Difficulty,
+            lobby::Column::GateStart,
+            lobby::Column::GateEnd,
+        ] {
+            manager
+                .alter_table(
+                    sea_query::Table::alter()
+                        .table(Lobby)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+async fn update_lobbies(
+    manager: &SchemaManager<'_>,
+    statement: sea_query::UpdateStatement,
+) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    db.execute(db.get_database_backend().build(&statement))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use entity::servers::{self, Entity as Servers};
+use sea_schema::migration::prelude::*;
+
+/// Adds the language of the messages of the servers.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220604_000001_server_locale"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                sea_query::Table::alter()
+                    .table(Servers)
+                    .add_column(
+                        &mut ColumnDef::new(servers::Column::Locale)
+                            .text()
+                            .not_null()
+                            .default("en"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Older SQLite versions can't drop columns, unused columns don't hurt there
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                sea_query::Table::alter()
+                    .table(Servers)
+                    .drop_column(servers::Column::Locale)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,40 @@
+use entity::lobby::{self, Entity as Lobby};
+use sea_schema::migration::prelude::*;
+
+/// Adds the note of the lobby master to the lobbies.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220605_000001_lobby_note"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                sea_query::Table::alter()
+                    .table(Lobby)
+                    .add_column(&mut ColumnDef::new(lobby::Column::Note).text())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Older SQLite versions can't drop columns, unused columns don't hurt there
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                sea_query::Table::alter()
+                    .table(Lobby)
+                    .drop_column(lobby::Column::Note)
+                    .to_owned(),
+            )
+            .await
+    }
+}
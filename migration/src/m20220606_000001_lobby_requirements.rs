@@ -0,0 +1,69 @@
+use entity::lobby::{self, Entity as Lobby};
+use sea_schema::migration::prelude::*;
+
+/// Adds the requirements of the lobby master for the players of the lobbies.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220606_000001_lobby_requirements"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can only add one column at a time
+        for mut column in [
+            ColumnDef::new(lobby::Column::MinClears)
+                .integer()
+                .to_owned(),
+            ColumnDef::new(lobby::Column::Engravings).text().to_owned(),
+            ColumnDef::new(lobby::Column::Language).text().to_owned(),
+            ColumnDef::new(lobby::Column::Voice)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+            ColumnDef::new(lobby::Column::GoldRun)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    sea_query::Table::alter()
+                        .table(Lobby)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Older SQLite versions can't drop columns, unused columns don't hurt there
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        for column in [
+            lobby::Column::MinClears,
+            lobby::Column::Engravings,
+            lobby::Column::Language,
+            lobby::Column::Voice,
+            lobby::Column::GoldRun,
+        ] {
+            manager
+                .alter_table(
+                    sea_query::Table::alter()
+                        .table(Lobby)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
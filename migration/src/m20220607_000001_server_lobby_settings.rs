@@ -0,0 +1,61 @@
+use entity::servers::{self, Entity as Servers};
+use sea_schema::migration::prelude::*;
+
+/// Adds the lobby settings of the servers.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220607_000001_server_lobby_settings"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can only add one column at a time
+        for mut column in [
+            ColumnDef::new(servers::Column::SingleCharacter)
+                .boolean()
+                .not_null()
+                .default(true)
+                .to_owned(),
+            ColumnDef::new(servers::Column::ConflictMinutes)
+                .integer()
+                .not_null()
+                .default(60)
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    sea_query::Table::alter()
+                        .table(Servers)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Older SQLite versions can't drop columns, unused columns don't hurt there
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        for column in [
+            servers::Column::SingleCharacter,
+            servers::Column::ConflictMinutes,
+        ] {
+            manager
+                .alter_table(
+                    sea_query::Table::alter()
+                        .table(Servers)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
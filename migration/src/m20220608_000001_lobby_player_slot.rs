@@ -0,0 +1,45 @@
+use entity::lobby_player::{self, Entity as LobbyPlayer};
+use sea_schema::migration::prelude::*;
+
+/// Adds the slot of the players in the lobby, so the order of the players survives restarts.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220608_000001_lobby_player_slot"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                sea_query::Table::alter()
+                    .table(LobbyPlayer)
+                    .add_column(
+                        &mut ColumnDef::new(lobby_player::Column::Slot)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Older SQLite versions can't drop columns, unused columns don't hurt there
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                sea_query::Table::alter()
+                    .table(LobbyPlayer)
+                    .drop_column(lobby_player::Column::Slot)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,154 @@
+//! Read-only HTTP API for guild websites.
+//!
+//! Every endpoint needs a `Authorization: Bearer <token>` header. Tokens are generated per
+//! guild with the `/api_token` command and only their SHA-256 hashes are stored in database.
+//! Guild scoped endpoints only accept the token of the guild in the path.
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{Extension, Path},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use entity::{characters, lobby, lobby_player};
+use sea_orm::DbErr;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    info::{contents, ContentInfo},
+    storage::Storage,
+};
+
+pub enum ApiError {
+    Unauthorized,
+    NotFound,
+    Database(DbErr),
+}
+
+impl From<DbErr> for ApiError {
+    fn from(err: DbErr) -> Self {
+        match err {
+            DbErr::RecordNotFound(_) => Self::NotFound,
+            err => Self::Database(err),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Invalid or missing API token."),
+            ApiError::NotFound => (StatusCode::NOT_FOUND, "Not found."),
+            ApiError::Database(err) => {
+                tracing::error!("API database error: {err}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Database error.")
+            }
+        };
+
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+/// Hashes the raw token the same way it is stored in database.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Returns the guild id the bearer token belongs to.
+async fn authorize(headers: &HeaderMap, db: &dyn Storage) -> Result<String, ApiError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(ApiError::Unauthorized)?;
+
+    match db.get_api_token_by_hash(&hash_token(token.trim())).await {
+        Ok(api_token) => Ok(api_token.guild_id),
+        Err(DbErr::RecordNotFound(_)) => Err(ApiError::Unauthorized),
+        Err(err) => Err(ApiError::Database(err)),
+    }
+}
+
+async fn authorize_guild(
+    headers: &HeaderMap,
+    guild_id: u64,
+    db: &dyn Storage,
+) -> Result<(), ApiError> {
+    if authorize(headers, db).await? == guild_id.to_string() {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized)
+    }
+}
+
+async fn contents(
+    headers: HeaderMap,
+    Extension(db): Extension<&'static dyn Storage>,
+) -> Result<Json<Vec<ContentInfo>>, ApiError> {
+    authorize(&headers, db).await?;
+
+    Ok(Json(
+        contents()
+            .values()
+            .map(|content_info| ContentInfo::clone(content_info))
+            .collect(),
+    ))
+}
+
+async fn lobbies(
+    headers: HeaderMap,
+    Path(guild_id): Path<u64>,
+    Extension(db): Extension<&'static dyn Storage>,
+) -> Result<Json<Vec<lobby::Model>>, ApiError> {
+    authorize_guild(&headers, guild_id, db).await?;
+
+    Ok(Json(db.get_active_guild_lobbies(guild_id).await?))
+}
+
+async fn lobby_players(
+    headers: HeaderMap,
+    Path((guild_id, lobby_id)): Path<(u64, Uuid)>,
+    Extension(db): Extension<&'static dyn Storage>,
+) -> Result<Json<Vec<lobby_player::Model>>, ApiError> {
+    authorize_guild(&headers, guild_id, db).await?;
+
+    // Don't leak lobbies of other guilds
+    if db.get_lobby(lobby_id).await?.guild_id != guild_id.to_string() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(db.get_lobby_players(lobby_id).await?))
+}
+
+async fn characters(
+    headers: HeaderMap,
+    Path(guild_id): Path<u64>,
+    Extension(db): Extension<&'static dyn Storage>,
+) -> Result<Json<Vec<characters::Model>>, ApiError> {
+    authorize_guild(&headers, guild_id, db).await?;
+
+    Ok(Json(db.get_guild_characters(guild_id).await?))
+}
+
+pub fn router(db: &'static dyn Storage) -> Router {
+    Router::new()
+        .route("/contents", get(contents))
+        .route("/guilds/:guild_id/lobbies", get(lobbies))
+        .route(
+            "/guilds/:guild_id/lobbies/:lobby_id/players",
+            get(lobby_players),
+        )
+        .route("/guilds/:guild_id/characters", get(characters))
+        .layer(Extension(db))
+}
+
+pub async fn serve(addr: SocketAddr, db: &'static dyn Storage) -> Result<(), hyper::Error> {
+    tracing::info!("API listening on {addr}");
+    axum::Server::bind(&addr)
+        .serve(router(db).into_make_service())
+        .await
+}
@@ -0,0 +1,152 @@
+//! JSON archives of a guild's data.
+//!
+//! An archive has every row of the guild except its API token, which can be generated again.
+//! Secrets of the webhooks are left out too, archives are shared in Discord. Importing an
+//! archive replaces the guild's current data in a single transaction.
+use chrono::{DateTime, Utc};
+use entity::{
+    characters, custom_content, guildmates, lobby, lobby_player, prelude::*, servers, webhooks,
+};
+use sea_orm::{
+    ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{database, webhook::generate_secret};
+
+/// Increased when the archive format changes.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct GuildArchive {
+    pub version: u32,
+    pub exported: DateTime<Utc>,
+    pub server: servers::Model,
+    pub guildmates: Vec<guildmates::Model>,
+    pub characters: Vec<characters::Model>,
+    pub lobbies: Vec<lobby::Model>,
+    pub lobby_players: Vec<lobby_player::Model>,
+    /// Secrets are empty.
+    pub webhooks: Vec<webhooks::Model>,
+    /// Missing in the archives made before custom contents.
+    #[serde(default)]
+    pub custom_contents: Vec<custom_content::Model>,
+}
+
+impl GuildArchive {
+    pub fn guild_id(&self) -> &str {
+        &self.server.id
+    }
+
+    /// Checks that the archive version is supported and the rows are consistent.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != ARCHIVE_VERSION {
+            return Err(format!(
+                "Unsupported archive version {}, expected {ARCHIVE_VERSION}.",
+                self.version
+            ));
+        }
+
+        let guild_id = self.guild_id();
+        if self.guildmates.iter().any(|g| g.server_id != guild_id)
+            || self.characters.iter().any(|c| c.guild_id != guild_id)
+            || self.lobbies.iter().any(|l| l.guild_id != guild_id)
+            || self.lobby_players.iter().any(|p| p.guild_id != guild_id)
+            || self.webhooks.iter().any(|w| w.guild_id != guild_id)
+            || self.custom_contents.iter().any(|c| c.guild_id != guild_id)
+        {
+            return Err("Archive contains data of another guild.".to_string());
+        }
+
+        let is_guildmate = |id: &str| self.guildmates.iter().any(|g| g.id == id);
+        if self.characters.iter().any(|c| !is_guildmate(&c.id))
+            || self.lobbies.iter().any(|l| !is_guildmate(&l.lobby_master))
+        {
+            return Err("Archive has characters or lobbies of unknown guildmates.".to_string());
+        }
+
+        if self.lobby_players.iter().any(|p| {
+            !self.lobbies.iter().any(|l| l.lobby_id == p.lobby_id)
+                || !self.characters.iter().any(|c| c.name == p.character_name)
+        }) {
+            return Err("Archive has players of unknown lobbies or characters.".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Fills the secrets left out of the archive. Webhooks the guild still has keep their
+    /// secrets, the others get new ones.
+    pub fn restore_secrets(&mut self, current: &[webhooks::Model]) {
+        for webhook in &mut self.webhooks {
+            webhook.secret = current
+                .iter()
+                .find(|w| w.url == webhook.url)
+                .map(|w| w.secret.clone())
+                .unwrap_or_else(generate_secret);
+        }
+    }
+}
+
+/// Webhooks as they are archived.
+pub fn without_secrets(webhooks: Vec<webhooks::Model>) -> Vec<webhooks::Model> {
+    webhooks
+        .into_iter()
+        .map(|webhook| webhooks::Model {
+            secret: String::new(),
+            ..webhook
+        })
+        .collect()
+}
+
+/// Returns [`DbErr::RecordNotFound`] if the guild is not registered.
+pub async fn export_guild(guild_id: u64, db: &DatabaseConnection) -> Result<GuildArchive, DbErr> {
+    Ok(GuildArchive {
+        version: ARCHIVE_VERSION,
+        exported: Utc::now(),
+        server: database::get_server(guild_id, db).await?,
+        guildmates: database::get_guildmates(guild_id, db).await?,
+        characters: database::get_guild_characters(guild_id, db).await?,
+        lobbies: database::get_guild_lobbies(guild_id, db).await?,
+        lobby_players: database::get_guild_lobby_players(guild_id, db).await?,
+        webhooks: without_secrets(database::get_webhooks(&guild_id.to_string(), db).await?),
+        custom_contents: database::get_custom_contents(guild_id, db).await?,
+    })
+}
+
+/// Replaces the data of the archive's guild with the archive.
+///
+/// Returns [`DbErr::Custom`] if the archive is not valid.
+pub async fn import_guild(mut archive: GuildArchive, db: &DatabaseConnection) -> Result<(), DbErr> {
+    archive.validate().map_err(DbErr::Custom)?;
+    archive.restore_secrets(&database::get_webhooks(&archive.server.id, db).await?);
+
+    let txn = db.begin().await?;
+
+    // Everything else of the guild is deleted by the cascading foreign keys
+    Servers::delete_by_id(archive.server.id.clone())
+        .exec(&txn)
+        .await?;
+
+    archive.server.into_active_model().insert(&txn).await?;
+    for guildmate in archive.guildmates {
+        guildmate.into_active_model().insert(&txn).await?;
+    }
+    for character in archive.characters {
+        character.into_active_model().insert(&txn).await?;
+    }
+    for lobby in archive.lobbies {
+        lobby.into_active_model().insert(&txn).await?;
+    }
+    for lobby_player in archive.lobby_players {
+        lobby_player.into_active_model().insert(&txn).await?;
+    }
+    for webhook in archive.webhooks {
+        webhook.into_active_model().insert(&txn).await?;
+    }
+    for custom_content in archive.custom_contents {
+        custom_content.into_active_model().insert(&txn).await?;
+    }
+
+    txn.commit().await
+}
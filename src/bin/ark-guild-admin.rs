@@ -0,0 +1,174 @@
+//! Maintenance tool working directly on the database of the bot.
+//!
+//! Changes to lobbies are picked up by the bot on its next start, stop the bot before closing
+//! or editing posted lobbies.
+use std::path::PathBuf;
+
+use ark_guild_bot::{backup, database};
+use clap::{Parser, Subcommand};
+use dotenv::dotenv;
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{Database, DatabaseConnection, DbErr};
+use uuid::Uuid;
+
+#[derive(Parser)]
+#[clap(
+    name = "ark-guild-admin",
+    about = "Offline maintenance for Ark Guild Helper"
+)]
+struct Cli {
+    /// Database of the bot
+    #[clap(long, env = "DATABASE_URL")]
+    database_url: String,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the registered servers
+    Servers,
+    /// List the lobbies of a server
+    Lobbies {
+        guild_id: u64,
+        /// Include the finished lobbies
+        #[clap(long)]
+        all: bool,
+    },
+    /// Show a lobby with its players
+    Lobby { lobby_id: Uuid },
+    /// Mark a lobby as finished
+    CloseLobby { lobby_id: Uuid },
+    /// Give a lobby to another guildmate
+    SetMaster { lobby_id: Uuid, user_id: u64 },
+    /// Deactivate the players left active in finished lobbies
+    Repair,
+    /// Write the data of a server to a JSON file
+    Export {
+        guild_id: u64,
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+    /// Replace the data of a server with a JSON file made by `export`
+    Import { input: PathBuf },
+    /// Manage the database migrations
+    Migrate {
+        #[clap(subcommand)]
+        action: MigrateAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrateAction {
+    /// Apply the pending migrations
+    Up,
+    /// Roll back the last migrations
+    Down {
+        #[clap(default_value = "1")]
+        steps: u32,
+    },
+    /// Show the applied and pending migrations
+    Status,
+}
+
+type CliResult = Result<(), Box<dyn std::error::Error>>;
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    let cli = Cli::parse();
+
+    let result = match Database::connect(&cli.database_url).await {
+        Ok(db) => run(cli.command, &db).await,
+        Err(err) => Err(err.into()),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(command: Command, db: &DatabaseConnection) -> CliResult {
+    match command {
+        Command::Servers => {
+            for server in database::get_servers(db).await? {
+                println!(
+                    "{}\t{}\tUTC{:+}",
+                    server.id, server.guild_name, server.timezone
+                );
+            }
+        }
+        Command::Lobbies { guild_id, all } => {
+            let lobbies = if all {
+                database::get_guild_lobbies(guild_id, db).await?
+            } else {
+                database::get_active_guild_lobbies(guild_id, db).await?
+            };
+
+            for lobby in lobbies {
+                println!(
+                    "{}\t{}\tmaster {}\t{}\t{}",
+                    lobby.lobby_id,
+                    lobby.content,
+                    lobby.lobby_master,
+                    lobby
+                        .scheduled
+                        .map(|time| time.to_rfc3339())
+                        .unwrap_or_else(|| "unscheduled".to_string()),
+                    if lobby.active { "active" } else { "finished" },
+                );
+            }
+        }
+        Command::Lobby { lobby_id } => {
+            let lobby = database::get_lobby(lobby_id, db).await?;
+            println!("{lobby:#?}");
+            for player in database::get_lobby_players(lobby_id, db).await? {
+                println!(
+                    "{}\t{}\t{}",
+                    player.player_id,
+                    player.character_name,
+                    if player.active { "active" } else { "inactive" },
+                );
+            }
+        }
+        Command::CloseLobby { lobby_id } => {
+            let lobby = database::get_lobby(lobby_id, db).await?;
+            if !lobby.active {
+                return Err(format!("Lobby {lobby_id} is already finished.").into());
+            }
+            database::disable_lobby(&lobby, db).await?;
+            println!("Closed lobby {lobby_id}.");
+        }
+        Command::SetMaster { lobby_id, user_id } => {
+            database::update_lobby_master(lobby_id, user_id, db).await?;
+            println!("{user_id} is the master of lobby {lobby_id} now.");
+        }
+        Command::Repair => {
+            let fixed = database::deactivate_orphaned_lobby_players(db).await?;
+            println!("Deactivated {fixed} lobby players of finished lobbies.");
+        }
+        Command::Export { guild_id, output } => {
+            let archive = backup::export_guild(guild_id, db).await?;
+            std::fs::write(&output, serde_json::to_vec_pretty(&archive)?)?;
+            println!("Exported guild {guild_id} to {}.", output.display());
+        }
+        Command::Import { input } => {
+            let archive: backup::GuildArchive = serde_json::from_slice(&std::fs::read(&input)?)?;
+            let guild_id = archive.guild_id().to_string();
+            backup::import_guild(archive, db).await?;
+            println!("Imported guild {guild_id} from {}.", input.display());
+        }
+        Command::Migrate { action } => migrate(action, db).await?,
+    }
+
+    Ok(())
+}
+
+async fn migrate(action: MigrateAction, db: &DatabaseConnection) -> Result<(), DbErr> {
+    match action {
+        MigrateAction::Up => Migrator::up(db, None).await,
+        MigrateAction::Down { steps } => Migrator::down(db, Some(steps)).await,
+        MigrateAction::Status => Migrator::status(db).await,
+    }
+}
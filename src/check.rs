@@ -1,17 +1,56 @@
-use crate::{database::get_server, Context, Error};
+use crate::{
+    locale::{tr, user_locale},
+    storage::Storage,
+    Context, Error, Role,
+};
 use sea_orm::DbErr;
 
+/// Returns the guild id of the context or an error for commands used in DMs.
+pub fn guild_id(ctx: Context<'_>) -> Result<u64, Error> {
+    ctx.guild_id()
+        .map(|guild_id| guild_id.0)
+        .ok_or_else(|| Error::permission("common.guild_only"))
+}
+
 pub async fn is_guild_init(ctx: Context<'_>) -> Result<bool, Error> {
-    match get_server(ctx.guild_id().unwrap().0, ctx.data().db).await {
+    match ctx.data().db.get_server(guild_id(ctx)?).await {
         Err(DbErr::RecordNotFound(_)) => {
-            ctx.say("Your server admin needs to register the server first")
+            ctx.say(tr(&user_locale(ctx).await, "common.server_not_registered"))
                 .await?;
             Ok(false)
         }
         Err(err) => {
-            ctx.say("Database Error, try again.").await?;
-            Err(Box::new(err))
+            ctx.say(tr(&user_locale(ctx).await, "common.database_error"))
+                .await?;
+            Err(err.into())
         }
         Ok(_) => Ok(true),
     }
 }
+
+/// Passes the guild master, deputies and officers of the guild.
+pub async fn is_guild_officer(ctx: Context<'_>) -> Result<bool, Error> {
+    match ctx
+        .data()
+        .db
+        .get_guildmate(ctx.author().id.0, guild_id(ctx)?)
+        .await
+    {
+        Ok(guildmate) if guildmate.role != Role::Guildmate => Ok(true),
+        Ok(_) => {
+            ctx.say(tr(&user_locale(ctx).await, "common.only_administration"))
+                .await?;
+            Ok(false)
+        }
+        Err(DbErr::RecordNotFound(_)) => {
+            ctx.say(tr(&user_locale(ctx).await, "common.not_registered"))
+                .await?;
+            Ok(false)
+        }
+        Err(err) => {
+            ctx.say(tr(&user_locale(ctx).await, "common.database_error"))
+                .await?;
+            Err(err.into())
+        }
+    }
+}
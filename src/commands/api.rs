@@ -0,0 +1,41 @@
+use crate::{
+    api::hash_token,
+    check::{guild_id, is_guild_init},
+    locale::{tr, user_locale},
+};
+
+use super::*;
+
+/// Generates a new API token for the guild website. The old token stops working.
+#[poise::command(
+    slash_command,
+    category = "Guild",
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    check = "is_guild_init"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn api_token(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    let db = ctx.data().db;
+    let locale = user_locale(ctx).await;
+
+    let token = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().to_simple(),
+        uuid::Uuid::new_v4().to_simple()
+    );
+    db.upsert_api_token(guild_id, &hash_token(&token)).await?;
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr(&locale, "api.token_title"))
+                .description(format!("```{token}```"))
+                .field("\0", tr(&locale, "api.token_note"), false)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::{borrow::Cow, sync::Arc};
+
+use parking_lot::RwLock;
+
+use crate::{
+    backup::GuildArchive,
+    check::{guild_id, is_guild_init},
+    commands::lobby::supervisor::load_lobby_context,
+    component_id::{ComponentId, CONFIRM},
+    locale::{tr, tr_with, user_locale},
+};
+
+use super::*;
+
+/// Discord doesn't allow bigger attachments for bots.
+const MAX_ARCHIVE_SIZE: u64 = 8 * 1024 * 1024;
+
+fn archive_attachment(archive: &GuildArchive) -> Result<serenity::AttachmentType<'static>, Error> {
+    let data = serde_json::to_vec_pretty(archive)
+        .map_err(|err| Error::parse(format!("Couldn't serialize archive: {err}")))?;
+
+    Ok(serenity::AttachmentType::Bytes {
+        data: Cow::Owned(data),
+        filename: format!(
+            "{}-{}.json",
+            archive.guild_id(),
+            archive.exported.format("%Y%m%d%H%M%S")
+        ),
+    })
+}
+
+/// Sends the prompt with the archive attached and waits for the author to confirm.
+///
+/// Prompt is only seen by the author. Returns false if the author cancels or doesn't answer
+/// in a minute.
+pub async fn confirm_with_archive(
+    ctx: Context<'_>,
+    prompt: &str,
+    archive: &GuildArchive,
+) -> Result<bool, Error> {
+    let attachment = archive_attachment(archive)?;
+    let locale = user_locale(ctx).await;
+    let confirm_id = ComponentId::new(CONFIRM, ctx.id(), "confirm").encode();
+    let cancel_id = ComponentId::new(CONFIRM, ctx.id(), "cancel").encode();
+
+    ctx.send(|m| {
+        m.content(format!("{prompt}\n{}", tr(&locale, "backup.attached")))
+            .attachment(attachment)
+            .ephemeral(true)
+            .components(|c| {
+                c.create_action_row(|r| {
+                    r.create_button(|b| {
+                        b.style(serenity::ButtonStyle::Danger)
+                            .custom_id(&confirm_id)
+                            .label(tr(&locale, "common.confirm"))
+                    });
+                    r.create_button(|b| {
+                        b.style(serenity::ButtonStyle::Secondary)
+                            .custom_id(&cancel_id)
+                            .label(tr(&locale, "common.cancel"))
+                    })
+                })
+            })
+    })
+    .await?;
+
+    let entity = ctx.id().to_string();
+    let mci = serenity::CollectComponentInteraction::new(ctx.discord())
+        .author_id(ctx.author().id)
+        .channel_id(ctx.channel_id())
+        .timeout(std::time::Duration::from_secs(60))
+        .filter(move |mci| {
+            ComponentId::decode(&mci.data.custom_id)
+                .map(|component_id| component_id.is_for(CONFIRM, &entity))
+                .unwrap_or(false)
+        })
+        .await;
+
+    // Ephemeral messages can only be edited by responding to their components
+    let mci = match mci {
+        Some(mci) => mci,
+        None => {
+            ctx.send(|m| m.content(tr(&locale, "common.cancelled")).ephemeral(true))
+                .await?;
+            return Ok(false);
+        }
+    };
+    let confirmed = mci.data.custom_id == confirm_id;
+    mci.create_interaction_response(ctx.discord(), |ir| {
+        ir.kind(serenity::InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| {
+                d.content(if confirmed {
+                    tr(&locale, "common.confirmed")
+                } else {
+                    tr(&locale, "common.cancelled")
+                })
+                .components(|c| c)
+            })
+    })
+    .await?;
+
+    Ok(confirmed)
+}
+
+#[poise::command(
+    slash_command,
+    category = "Guild",
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    check = "is_guild_init"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn backup(ctx: Context<'_>) -> Result<(), Error> {
+    let archive = ctx.data().db.export_guild(guild_id(ctx)?).await?;
+    let attachment = archive_attachment(&archive)?;
+    let locale = user_locale(ctx).await;
+
+    ctx.send(|m| {
+        m.content(tr_with(
+            &locale,
+            "backup.summary",
+            &[
+                ("guild", &archive.server.guild_name),
+                ("characters", &archive.characters.len()),
+                ("lobbies", &archive.lobbies.len()),
+            ],
+        ))
+        .attachment(attachment)
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    category = "Guild",
+    guild_only,
+    required_permissions = "ADMINISTRATOR"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn restore(
+    ctx: Context<'_>,
+    #[description = "Backup file made by /backup"] archive: serenity::Attachment,
+) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    let db = ctx.data().db;
+    let locale = user_locale(ctx).await;
+
+    if archive.size > MAX_ARCHIVE_SIZE {
+        return Err(Error::permission("backup.too_big"));
+    }
+
+    let archive = serde_json::from_slice::<GuildArchive>(&archive.download().await?)
+        .map_err(|err| err.to_string())
+        .and_then(|archive| archive.validate().map(|_| archive));
+    let archive = match archive {
+        Ok(archive) => archive,
+        Err(err) => {
+            ctx.send(|m| {
+                m.content(tr_with(&locale, "backup.invalid", &[("error", &err)]))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+    if archive.guild_id() != guild_id.to_string() {
+        return Err(Error::permission("backup.other_server"));
+    }
+
+    // Take a snapshot of the current data before replacing it
+    if let Ok(current) = db.export_guild(guild_id).await {
+        let time = archive.exported.format("%Y-%m-%d %H:%M UTC");
+        let prompt = tr_with(&locale, "backup.confirm", &[("time", &time)]);
+        if !confirm_with_archive(ctx, &prompt, &current).await? {
+            return Ok(());
+        }
+    }
+
+    let active_lobbies: Vec<_> = archive
+        .lobbies
+        .iter()
+        .filter(|lobby| lobby.active)
+        .cloned()
+        .collect();
+    db.import_guild(archive).await?;
+
+    // Lobby messages of the backup are still posted, start listening them again
+    let lobby_supervisor = &ctx.data().lobby_supervisor;
+    for lobby in active_lobbies {
+        if lobby_supervisor
+            .context(&lobby.lobby_id.to_hyphenated().to_string())
+            .is_none()
+        {
+            let lobby_context = load_lobby_context(&lobby, db).await?;
+            lobby_supervisor.spawn(Arc::new(RwLock::new(lobby_context)));
+        }
+    }
+
+    ctx.say(tr(&locale, "backup.restored")).await?;
+
+    Ok(())
+}
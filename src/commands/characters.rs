@@ -1,14 +1,22 @@
 use super::*;
-use crate::{*, check::is_guild_init};
+use crate::{
+    check::is_guild_init,
+    commands::lobby::helper::character_description,
+    component_id::{ComponentId, CHARACTER},
+    locale::{tr, tr_with, user_locale},
+    *,
+};
 use entity::characters;
 use sea_orm::DbErr;
 
-fn construct_character_list(characters: &Vec<characters::Model>) -> String {
+fn construct_character_list(characters: &Vec<characters::Model>, locale: &str) -> String {
     let mut character_list = String::new();
     character_list.push_str("```");
     character_list.push_str(&format!(
         "{:<15} {:<15} {}\n",
-        "Name", "Class", "Item Level"
+        tr(locale, "character.column_name"),
+        tr(locale, "character.column_class"),
+        tr(locale, "character.column_item_level")
     ));
     character_list.push_str(&format!("{:-<15} {:-<15} {:-<10}\n", "", "", ""));
     for character in characters {
@@ -22,6 +30,7 @@ fn construct_character_list(characters: &Vec<characters::Model>) -> String {
 }
 
 #[poise::command(slash_command, category = "Character", check = "is_guild_init")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
 pub async fn character(
     ctx: Context<'_>,
     #[description = "Name of the character"] character_name: String,
@@ -31,55 +40,68 @@ pub async fn character(
     #[max = 1490]
     item_level: i32,
 ) -> Result<(), Error> {
+    let locale = user_locale(ctx).await;
     let guild_id = if let Some(id) = ctx.guild_id() {
         id.0
     } else {
-        ctx.say("You must be in a guild to use this command")
-            .await?;
+        ctx.say(tr(&locale, "common.guild_only")).await?;
         return Ok(());
     };
 
     let db = &ctx.data().db;
 
-    match get_guildmate(ctx.author().id.0, guild_id, db).await {
+    match db.get_guildmate(ctx.author().id.0, guild_id).await {
         Ok(_) => {}
         Err(DbErr::RecordNotFound(_)) => {
-            ctx.say("Couldn't find guildmate record. Adding discord account as a guildmate first.")
+            ctx.say(tr(&locale, "character.guildmate_added")).await?;
+            db.insert_guildmate(guild_id, ctx.author().id.0, Role::Guildmate)
                 .await?;
-            insert_guildmate(guild_id, ctx.author().id.0, Role::Guildmate, db)
-                .await
-                .expect("Failed to insert guildmate");
         }
         Err(err) => {
-            ctx.say("Failed to acess guildmate.".to_string()).await?;
-            println!("{}", err);
+            ctx.say(tr(&locale, "character.guildmate_error")).await?;
+            tracing::error!("{}", err);
             return Ok(());
         }
     }
 
-    match get_single_character(&character_name, guild_id, db).await {
+    match db.get_single_character(&character_name, guild_id).await {
         Ok(_) => {
-            update_character(&character_name, guild_id, class, item_level, db)
-                .await
-                .expect("Error updating character");
-            ctx.say(format!("Updated character named **{character_name}** as *{class}* with __{item_level}__ Item Level")).await?;
+            db.update_character(&character_name, guild_id, class, item_level)
+                .await?;
+            ctx.say(tr_with(
+                &locale,
+                "character.updated",
+                &[
+                    ("name", &character_name),
+                    ("class", &class),
+                    ("ilvl", &item_level),
+                ],
+            ))
+            .await?;
         }
         Err(DbErr::RecordNotFound(_)) => {
-            insert_character(
+            db.insert_character(
                 ctx.author().id.0,
                 guild_id,
                 &character_name,
                 class,
                 item_level,
-                db,
             )
-            .await
-            .expect("Failed to insert character");
-            ctx.say(format!("Added **{character_name}** as *{class}* to your characters (__{item_level}__ Item Level)")).await?;
+            .await?;
+            ctx.say(tr_with(
+                &locale,
+                "character.added",
+                &[
+                    ("name", &character_name),
+                    ("class", &class),
+                    ("ilvl", &item_level),
+                ],
+            ))
+            .await?;
         }
         Err(err) => {
-            ctx.say("Failed to get character.".to_string()).await?;
-            println!("{}", err);
+            ctx.say(tr(&locale, "character.get_error")).await?;
+            tracing::error!("{}", err);
             return Ok(());
         }
     }
@@ -88,36 +110,41 @@ pub async fn character(
 }
 
 #[poise::command(slash_command, category = "Character", check = "is_guild_init")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
 pub async fn list_characters(ctx: Context<'_>) -> Result<(), Error> {
+    let locale = user_locale(ctx).await;
     let guild_id = if let Some(id) = ctx.guild_id() {
         id.0
     } else {
-        ctx.say("You must be in a guild to use this command")
-            .await?;
+        ctx.say(tr(&locale, "common.guild_only")).await?;
         return Ok(());
     };
 
     let db = &ctx.data().db;
 
-    match get_all_characters(ctx.author().id.0, guild_id, db).await {
+    match db.get_all_characters(ctx.author().id.0, guild_id).await {
         Ok(characters) => {
-            let character_list = construct_character_list(&characters);
+            let character_list = construct_character_list(&characters, &locale);
             ctx.send(|m| {
                 m.embed(|e| {
-                    e.title(format!("Characters of {}", ctx.author().name))
-                        .field("Characters:", character_list, false)
-                        .thumbnail(ctx.author().avatar_url().unwrap_or_default())
+                    e.title(tr_with(
+                        &locale,
+                        "character.list_title",
+                        &[("user", &ctx.author().name)],
+                    ))
+                    .field(tr(&locale, "character.list_field"), character_list, false)
+                    .thumbnail(ctx.author().avatar_url().unwrap_or_default())
                 })
             })
             .await?;
         }
         Err(DbErr::RecordNotFound(_)) => {
-            ctx.say("You have no characters.").await?;
+            ctx.say(tr(&locale, "character.none")).await?;
             return Ok(());
         }
         Err(err) => {
-            ctx.say("Error getting characters from database.").await?;
-            println!("{}", err);
+            ctx.say(tr(&locale, "common.database_error")).await?;
+            tracing::error!("{}", err);
         }
     }
 
@@ -125,47 +152,51 @@ pub async fn list_characters(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 #[poise::command(slash_command, track_edits, category = "Character", check = "is_guild_init")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
 pub async fn delete_character(ctx: Context<'_>) -> Result<(), Error> {
+    let locale = user_locale(ctx).await;
     let guild_id = if let Some(id) = ctx.guild_id() {
         id.0
     } else {
-        ctx.say("You must be in a guild to use this command")
-            .await?;
+        ctx.say(tr(&locale, "common.guild_only")).await?;
         return Ok(());
     };
 
     let db = &ctx.data().db;
 
-    match get_all_characters(ctx.author().id.0, guild_id, db).await {
+    match db.get_all_characters(ctx.author().id.0, guild_id).await {
         Ok(characters) => {
-            let custom_uuid = ctx.id();
+            let custom_id = ComponentId::new(CHARACTER, ctx.id(), "delete").encode();
 
-            let character_list = construct_character_list(&characters);
+            let character_list = construct_character_list(&characters, &locale);
             ctx.send(|m| {
                 m.embed(|e| {
-                    e.title(format!("Characters of {}", ctx.author().name))
-                        .field("Characters:", &character_list, false)
-                        .thumbnail(ctx.author().avatar_url().unwrap_or_default())
+                    e.title(tr_with(
+                        &locale,
+                        "character.list_title",
+                        &[("user", &ctx.author().name)],
+                    ))
+                    .field(tr(&locale, "character.list_field"), &character_list, false)
+                    .thumbnail(ctx.author().avatar_url().unwrap_or_default())
                 })
                 .components(|c| {
                     c.create_action_row(|r| {
                         r.create_select_menu(|m| {
-                            m.placeholder("Select a character to delete".to_string())
+                            m.placeholder(tr(&locale, "character.select_delete"))
                                 .options(|o| {
                                     for character in characters {
                                         o.create_option(|option| {
                                             option
                                                 .label(&character.name)
-                                                .description(format!(
-                                                    "{:<15} -> {:<5} ilvl",
-                                                    character.class, character.item_level
+                                                .description(character_description(
+                                                    &character, &locale,
                                                 ))
                                                 .value(&character.name)
                                         });
                                     }
                                     o
                                 })
-                                .custom_id(custom_uuid)
+                                .custom_id(&custom_id)
                         })
                     })
                 })
@@ -176,12 +207,11 @@ pub async fn delete_character(ctx: Context<'_>) -> Result<(), Error> {
                 .author_id(ctx.author().id)
                 .channel_id(ctx.channel_id())
                 .timeout(std::time::Duration::from_secs(60))
-                .filter(move |mci| mci.data.custom_id == custom_uuid.to_string())
+                .filter(move |mci| mci.data.custom_id == custom_id)
                 .await
             {
-                remove_character(&mci.data.values[0], guild_id, db)
-                    .await
-                    .expect("Failed to remove character");
+                let character_name = selected_value(&mci)?;
+                db.remove_character(character_name, guild_id).await?;
                 mci.create_interaction_response(ctx.discord(), |ir| {
                     ir.kind(serenity::model::interactions::InteractionResponseType::UpdateMessage)
                 })
@@ -190,10 +220,10 @@ pub async fn delete_character(ctx: Context<'_>) -> Result<(), Error> {
                 let mut msg = mci.message.clone();
                 msg.edit(ctx.discord(), |m| {
                     m.embed(|e| {
-                        e.title("Character deleted")
+                        e.title(tr(&locale, "character.deleted_title"))
                             .description(format!(
-                                "```Deleted {} from your character list.```",
-                                mci.data.values[0]
+                                "```{}```",
+                                tr_with(&locale, "character.deleted", &[("name", &character_name)])
                             ))
                             .thumbnail(ctx.author().avatar_url().unwrap_or_default())
                     })
@@ -203,11 +233,11 @@ pub async fn delete_character(ctx: Context<'_>) -> Result<(), Error> {
             }
         }
         Err(DbErr::RecordNotFound(_)) => {
-            ctx.say("You dont have any characters.").await?;
+            ctx.say(tr(&locale, "character.none")).await?;
         }
         Err(err) => {
-            ctx.say("Error accessing the database.").await?;
-            println!("{}", err);
+            ctx.say(tr(&locale, "common.database_error")).await?;
+            tracing::error!("{}", err);
         }
     }
 
@@ -215,6 +245,7 @@ pub async fn delete_character(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 #[poise::command(slash_command, category = "Character", check = "is_guild_init")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
 pub async fn edit_character_ilvl(
     ctx: Context<'_>,
     #[description = "Name of your character"] character_name: String,
@@ -223,30 +254,36 @@ pub async fn edit_character_ilvl(
     #[max = 1490]
     item_level: i32,
 ) -> Result<(), Error> {
+    let locale = user_locale(ctx).await;
     let guild_id = if let Some(id) = ctx.guild_id() {
         id.0
     } else {
-        ctx.say("You must be in a guild to use this command")
-            .await?;
+        ctx.say(tr(&locale, "common.guild_only")).await?;
         return Ok(());
     };
     let db = &ctx.data().db;
-    match update_ilvl(&character_name, guild_id, item_level, db).await {
+    match db.update_ilvl(&character_name, guild_id, item_level).await {
         Ok(()) => {
-            ctx.say(format!(
-                "Updated {}'s item level to {}",
-                character_name, item_level
+            ctx.say(tr_with(
+                &locale,
+                "character.ilvl_updated",
+                &[("name", &character_name), ("ilvl", &item_level)],
             ))
             .await?;
         }
         Err(DbErr::RecordNotFound(_)) => {
-            ctx.say(format!("No character named {} found", character_name))
-                .await?;
+            ctx.say(tr_with(
+                &locale,
+                "character.not_found",
+                &[("name", &character_name)],
+            ))
+            .await?;
         }
         Err(e) => {
-            ctx.say(format!(
-                "Error updating {}'s item level: {}",
-                character_name, e
+            ctx.say(tr_with(
+                &locale,
+                "character.ilvl_error",
+                &[("name", &character_name), ("error", &e)],
             ))
             .await?;
         }
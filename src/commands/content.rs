@@ -0,0 +1,448 @@
+use chrono::Utc;
+use entity::custom_content;
+use futures::Stream;
+
+use crate::{
+    check::{guild_id, is_guild_init, is_guild_officer},
+    info::{contents, reload_contents, ContentInfo},
+    locale::{tr, tr_with, user_locale},
+};
+
+use super::*;
+
+/// Keeps the select menu of the custom contents at a few pages.
+const MAX_CUSTOM_CONTENTS: usize = 100;
+
+/// Reloads the content files. Running lobbies keep their content until they finish.
+#[poise::command(prefix_command, slash_command, hide_in_help, owners_only)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn reload_content(ctx: Context<'_>) -> Result<(), Error> {
+    let locale = user_locale(ctx).await;
+    match reload_contents() {
+        Ok(len) => {
+            ctx.say(tr_with(&locale, "content.reloaded", &[("count", &len)]))
+                .await?;
+        }
+        Err(err) => {
+            ctx.say(tr_with(
+                &locale,
+                "content.reload_failed",
+                &[("error", &err)],
+            ))
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Contents of the guild that are not in the catalog. They are listed under "Custom" when
+/// creating a lobby.
+#[poise::command(
+    slash_command,
+    category = "Content",
+    guild_only,
+    subcommands("add", "edit", "remove")
+)]
+pub async fn content(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Returns the message key of the reason if the fields can't be used for a custom content.
+fn check_fields(name: &str, description: &str, image: Option<&str>) -> Result<(), &'static str> {
+    if name.trim().is_empty() || name.len() > 100 {
+        return Err("content.invalid_name");
+    }
+    if description.len() > 1000 {
+        return Err("content.invalid_description");
+    }
+    if let Some(image) = image {
+        match reqwest::Url::parse(image) {
+            Ok(parsed) if parsed.scheme() == "https" || parsed.scheme() == "http" => {}
+            _ => return Err("content.invalid_image"),
+        }
+    }
+    Ok(())
+}
+
+/// Finds the custom content of the guild by its name, ignoring the case.
+async fn find_custom_content(
+    ctx: Context<'_>,
+    name: &str,
+) -> Result<Option<custom_content::Model>, Error> {
+    Ok(ctx
+        .data()
+        .db
+        .get_custom_contents(guild_id(ctx)?)
+        .await?
+        .into_iter()
+        .find(|custom_content| custom_content.name.eq_ignore_ascii_case(name)))
+}
+
+/// Returns true if a lobby that is not finished yet uses the custom content.
+async fn is_in_use(
+    ctx: Context<'_>,
+    custom_content: &custom_content::Model,
+) -> Result<bool, Error> {
+    let key = ContentInfo::from_custom(custom_content).key;
+    Ok(ctx
+        .data()
+        .db
+        .get_active_guild_lobbies(guild_id(ctx)?)
+        .await?
+        .iter()
+        .any(|lobby| lobby.content == key))
+}
+
+async fn reply(ctx: Context<'_>, msg: impl Into<String>) -> Result<(), Error> {
+    let msg = msg.into();
+    ctx.send(|m| m.content(msg).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Add a content for the lobbies of the guild
+#[poise::command(slash_command, guild_only, check = "is_guild_officer")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Name of the content"] name: String,
+    #[description = "Number of players"]
+    #[min = 1]
+    #[max = 12]
+    size: i32,
+    #[description = "Minimum item level to join"]
+    #[min = 0]
+    #[max = 1490]
+    item_level: i32,
+    #[description = "Shown in the lobby message"] description: Option<String>,
+    #[description = "URL of an image shown in the lobby message"] image: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    let db = ctx.data().db;
+    let locale = user_locale(ctx).await;
+    let description = description.unwrap_or_default();
+
+    if let Err(reason) = check_fields(&name, &description, image.as_deref()) {
+        return reply(ctx, tr(&locale, reason)).await;
+    }
+    if find_custom_content(ctx, &name).await?.is_some() {
+        return reply(ctx, tr_with(&locale, "content.exists", &[("name", &name)])).await;
+    }
+    if db.get_custom_contents(guild_id).await?.len() >= MAX_CUSTOM_CONTENTS {
+        return reply(
+            ctx,
+            tr_with(&locale, "content.limit", &[("limit", &MAX_CUSTOM_CONTENTS)]),
+        )
+        .await;
+    }
+
+    db.insert_custom_content(custom_content::Model {
+        content_id: uuid::Uuid::new_v4(),
+        guild_id: guild_id.to_string(),
+        name: name.clone(),
+        content_size: size,
+        ilvl_req: item_level,
+        description,
+        image,
+        created: Utc::now(),
+    })
+    .await?;
+
+    ctx.say(tr_with(
+        &locale,
+        "content.added",
+        &[("name", &name), ("size", &size)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Change a custom content of the guild
+#[poise::command(slash_command, guild_only, check = "is_guild_officer")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn edit(
+    ctx: Context<'_>,
+    #[description = "Name of the content"] name: String,
+    #[description = "New name of the content"] new_name: Option<String>,
+    #[description = "Number of players"]
+    #[min = 1]
+    #[max = 12]
+    size: Option<i32>,
+    #[description = "Minimum item level to join"]
+    #[min = 0]
+    #[max = 1490]
+    item_level: Option<i32>,
+    #[description = "Shown in the lobby message"] description: Option<String>,
+    #[description = "URL of an image shown in the lobby message"] image: Option<String>,
+) -> Result<(), Error> {
+    let locale = user_locale(ctx).await;
+    let mut custom_content = match find_custom_content(ctx, &name).await? {
+        Some(custom_content) => custom_content,
+        None => {
+            return reply(
+                ctx,
+                tr_with(&locale, "content.not_found", &[("name", &name)]),
+            )
+            .await
+        }
+    };
+
+    if let Some(new_name) = new_name {
+        if let Some(other) = find_custom_content(ctx, &new_name).await? {
+            if other.content_id != custom_content.content_id {
+                return reply(
+                    ctx,
+                    tr_with(&locale, "content.exists", &[("name", &new_name)]),
+                )
+                .await;
+            }
+        }
+        custom_content.name = new_name;
+    }
+    if let Some(size) = size {
+        // Posted lobbies have a slot for every player
+        if size != custom_content.content_size && is_in_use(ctx, &custom_content).await? {
+            return reply(ctx, tr(&locale, "content.size_in_use")).await;
+        }
+        custom_content.content_size = size;
+    }
+    if let Some(item_level) = item_level {
+        custom_content.ilvl_req = item_level;
+    }
+    if let Some(description) = description {
+        custom_content.description = description;
+    }
+    if let Some(image) = image {
+        custom_content.image = Some(image);
+    }
+
+    if let Err(reason) = check_fields(
+        &custom_content.name,
+        &custom_content.description,
+        custom_content.image.as_deref(),
+    ) {
+        return reply(ctx, tr(&locale, reason)).await;
+    }
+
+    let new_name = custom_content.name.clone();
+    ctx.data().db.update_custom_content(custom_content).await?;
+
+    ctx.say(tr_with(&locale, "content.updated", &[("name", &new_name)]))
+        .await?;
+
+    Ok(())
+}
+
+/// Remove a custom content of the guild
+#[poise::command(slash_command, guild_only, check = "is_guild_officer")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Name of the content"] name: String,
+) -> Result<(), Error> {
+    let locale = user_locale(ctx).await;
+    let custom_content = match find_custom_content(ctx, &name).await? {
+        Some(custom_content) => custom_content,
+        None => {
+            return reply(
+                ctx,
+                tr_with(&locale, "content.not_found", &[("name", &name)]),
+            )
+            .await
+        }
+    };
+
+    if is_in_use(ctx, &custom_content).await? {
+        return reply(
+            ctx,
+            tr_with(&locale, "content.in_use", &[("name", &custom_content.name)]),
+        )
+        .await;
+    }
+
+    ctx.data()
+        .db
+        .remove_custom_content(custom_content.content_id)
+        .await?;
+
+    ctx.say(tr_with(
+        &locale,
+        "content.removed",
+        &[("name", &custom_content.name)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Discord doesn't show more autocomplete choices.
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+/// Catalog contents with the typed text in their names.
+async fn autocomplete_content(
+    _ctx: Context<'_>,
+    partial: String,
+) -> impl Stream<Item = poise::AutocompleteChoice<String>> {
+    let partial = partial.to_lowercase();
+    let mut choices: Vec<_> = contents()
+        .values()
+        .filter(|content_info| content_info.name.to_lowercase().contains(&partial))
+        .map(|content_info| poise::AutocompleteChoice {
+            name: content_info.name.clone(),
+            value: content_info.key.clone(),
+        })
+        .collect();
+    choices.sort_by(|a, b| a.name.cmp(&b.name));
+    choices.truncate(MAX_AUTOCOMPLETE_CHOICES);
+    futures::stream::iter(choices)
+}
+
+/// Item levels of the difficulties and the gates, one per line.
+fn describe_requirements(content_info: &ContentInfo, locale: &str) -> String {
+    let mut requirements = vec![tr_with(
+        locale,
+        "content.item_level",
+        &[("ilvl", &content_info.ilvl_req)],
+    )];
+    for difficulty in &content_info.difficulties {
+        requirements.push(format!("{}: {}", difficulty.name, difficulty.ilvl_req));
+    }
+    for gate in content_info.gates.iter().filter(|gate| gate.ilvl_req > 0) {
+        requirements.push(format!("{}: {}", gate.name, gate.ilvl_req));
+    }
+    requirements.join("\n")
+}
+
+/// Show the guides and requirements of a content
+#[poise::command(
+    slash_command,
+    category = "Content",
+    guild_only,
+    check = "is_guild_init"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn content_info(
+    ctx: Context<'_>,
+    #[description = "Name of the content"]
+    #[autocomplete = "autocomplete_content"]
+    content: String,
+) -> Result<(), Error> {
+    let locale = user_locale(ctx).await;
+    let content_info = match contents().get(&content) {
+        Some(content_info) => content_info,
+        None => {
+            return reply(
+                ctx,
+                tr_with(&locale, "content.unknown", &[("name", &content)]),
+            )
+            .await
+        }
+    };
+    let category = contents().category(&content_info.content_type);
+
+    let author_id = ctx.author().id.to_string();
+    let (my_characters, guild_characters): (Vec<_>, Vec<_>) = ctx
+        .data()
+        .db
+        .get_guild_characters(guild_id(ctx)?)
+        .await?
+        .into_iter()
+        .partition(|character| character.id == author_id);
+
+    let my_characters = if my_characters.is_empty() {
+        tr(&locale, "content.no_characters")
+    } else {
+        my_characters
+            .iter()
+            .map(|character| {
+                format!(
+                    "{} {} ({} {})",
+                    if character.item_level >= content_info.ilvl_req {
+                        "✅"
+                    } else {
+                        "❌"
+                    },
+                    character.name,
+                    character.class,
+                    character.item_level
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let eligible = guild_characters
+        .iter()
+        .filter(|character| character.item_level >= content_info.ilvl_req)
+        .count();
+
+    let mut links = vec![];
+    if !content_info.guide.is_empty() {
+        links.push(format!(
+            "[{}]({})",
+            tr(&locale, "content.guide"),
+            content_info.guide
+        ));
+    }
+    if !content_info.gameplay_video.is_empty() {
+        links.push(format!(
+            "[{}]({})",
+            tr(&locale, "content.gameplay_video"),
+            content_info.gameplay_video
+        ));
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(&content_info.name)
+                .description(content_info.introduction_for(&locale))
+                .field(
+                    tr(&locale, "content.category"),
+                    category
+                        .as_ref()
+                        .map_or("-", |category| category.name_for(&locale)),
+                    true,
+                )
+                .field(tr(&locale, "content.tier"), content_info.tier, true)
+                .field(
+                    tr(&locale, "content.size"),
+                    tr_with(
+                        &locale,
+                        "content.players",
+                        &[("size", &content_info.content_size)],
+                    ),
+                    true,
+                )
+                .field(
+                    tr(&locale, "content.minimum_item_level"),
+                    describe_requirements(&content_info, &locale),
+                    true,
+                )
+                .field(tr(&locale, "content.your_characters"), my_characters, false)
+                .field(
+                    tr(&locale, "content.guild"),
+                    tr_with(&locale, "content.guild_characters", &[("count", &eligible)]),
+                    false,
+                );
+            if !links.is_empty() {
+                e.field(tr(&locale, "content.links"), links.join(" | "), false);
+            }
+            if !content_info.guide.is_empty() {
+                e.url(&content_info.guide);
+            }
+            if !content_info.image.is_empty() {
+                e.thumbnail(&content_info.image);
+            }
+            if !content_info.banner.is_empty() {
+                e.image(&content_info.banner);
+            }
+            if let Some(colour) = category.as_ref().and_then(|category| category.colour()) {
+                e.colour(colour);
+            }
+            e
+        })
+    })
+    .await?;
+
+    Ok(())
+}
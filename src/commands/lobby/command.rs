@@ -1,21 +1,36 @@
-use crate::{commands::lobby::context::LobbyContext, info::*, check::is_guild_init};
+use crate::{
+    check::{guild_id, is_guild_init},
+    commands::lobby::context::LobbyContext,
+    component_id::{ComponentId, WIZARD},
+    locale::{interaction_locale, tr, tr_with, user_locale},
+    router::RoutedInteraction,
+};
 use helper::*;
 use parking_lot::RwLock;
 use poise::{
-    serenity_prelude::{CollectComponentInteraction, CreateComponents, Message},
+    serenity_prelude::{
+        CollectComponentInteraction, CollectModalInteraction, CreateComponents,
+        MessageComponentInteraction, ModalSubmitInteraction,
+    },
     Context,
 };
 use sea_orm::DbErr;
-use std::sync::Arc;
-use tokio::sync::mpsc::unbounded_channel;
+use settings::LobbySettings;
+use std::{sync::Arc, time::Duration};
+use wizard::{Transition, WizardEvent};
 
 use super::*;
 use crate::*;
 
+/// Lobby setup expires if the lobby master doesn't do anything for this long.
+const WIZARD_TIMEOUT: Duration = Duration::from_secs(600);
+
 #[derive(Debug, Clone, Copy)]
 pub enum State {
     ContentSelection,
     LobbyFirstPrompt,
+    /// Difficulty and gates of the content are selected.
+    OptionSelection,
     CollectPlayers,
     PrivateLobby,
     PublicLobby,
@@ -23,350 +38,250 @@ pub enum State {
 }
 
 #[poise::command(slash_command, category = "Lobby", guild_only, check = "is_guild_init")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
 pub async fn create_lobby(
     ctx: Context<'_, Data, Error>,
     #[description = "(Optional) Time of the lobby. You can choose another time later"]
     lobby_time: Option<String>,
 ) -> Result<(), Error> {
-    let guild_id = ctx.guild_id().unwrap().0;
+    let guild_id = guild_id(ctx)?;
     let db = ctx.data().db;
+    let server = db.get_server(guild_id).await?;
+    let settings = LobbySettings::from(&server);
+    // Lobby and its setup are seen by everyone in the channel
+    let locale = server.locale;
 
-    let lobby_master = match get_guildmate(ctx.author().id.0, guild_id, db).await {
+    let lobby_master = match db.get_guildmate(ctx.author().id.0, guild_id).await {
         Ok(guildmate) => {
             if guildmate.role == Role::Guildmate {
-                ctx.say("Only guild administration can create lobbies.")
-                    .await?;
+                ctx.say(tr(&locale, "lobby.only_administration")).await?;
                 return Ok(());
             }
             ctx.author()
         }
         Err(DbErr::RecordNotFound(_)) => {
-            ctx.say("You are not registered in guild.").await?;
+            ctx.say(tr(&locale, "common.not_registered")).await?;
             return Ok(());
         }
         Err(_) => {
-            ctx.say("Error getting guildmate record from database.")
-                .await?;
+            ctx.say(tr(&locale, "lobby.guildmate_error")).await?;
             return Ok(());
         }
     };
 
-    let lobby_time = if let Some(lobby_time) = lobby_time {
-        let offset = chrono::offset::FixedOffset::east(
-            get_server(guild_id, db).await?.timezone as i32 * 3600,
-        );
-        dateparser::parse_with_timezone(&lobby_time, &offset).ok()
-    } else {
-        None
-    };
-
-    let lobby_id = uuid::Uuid::new_v4();
-    let lobby_id_string = lobby_id.to_hyphenated().to_string();
-
-    // Check if lobby time is valid
+    // Check if lobby time is valid. Time can also be set from the details of the lobby later
     let lobby_time = match lobby_time {
-        None => {
-            ctx.send(|m| {
-                m.embed(|e| {
-                    e.title("No Time Specified")
-                    .description("Couldn't set lobby time. Either you did not specify a lobby time or the time format is false")
-                    .field("Example usage", "`/create_lobby <lobby time>`\n`/create_lobby 6:00pm`\n`/create_lobby May 02, 2021 15:51 UTC+2`", false)
-                    .field("\0", "If no time zone is specified the guild time zone is used.", false)
-                })
-            }).await?;
-            None
-        }
-        Some(lobby_time) => {
-            if lobby_time <= (chrono::Utc::now() + chrono::Duration::minutes(15)) {
-                ctx.send(|m| {
-                    m.embed(|e| {
-                        e.title("Lobby time cannot be within 15 minutes")
-                        .description(format!("Couldn't set lobby time. Got time: {}",lobby_time))
-                        .field("Example usage", "`/create_lobby <lobby time>`\n`/create_lobby 6:00pm`\n`/create_lobby May 02, 2021 15:51 UTC+2`", false)
-                        .field("\0", "If no time zone is specified the guild time zone is used.", false)
-                    })
-                }).await?;
-                None
-            } else if lobby_time >= (chrono::Utc::now() + chrono::Duration::weeks(2)) {
-                ctx.send(|m| {
-                    m.embed(|e| {
-                        e.title("Lobby time must be within 2 weeks.")
-                        .description(format!("Couldn't set lobby time. Got time: {}",lobby_time))
-                        .field("Example usage", "`/create_lobby <lobby time>`\n`/create_lobby 6:00pm`\n`/create_lobby May 02, 2021 15:51 UTC+2`", false)
-                        .field("\0", "If no time zone is specified the guild time zone is used.", false)
-                    })
-                }).await?;
+        None => None,
+        Some(lobby_time) => match parse_lobby_time(&lobby_time, server.timezone) {
+            None => {
+                ctx.say(tr(&locale, "lobby.time_parse_error")).await?;
                 None
-            } else {
-                Some(lobby_time)
             }
-        }
+            Some(lobby_time) => match check_lobby_time(lobby_time) {
+                Err(title) => {
+                    ctx.send(|m| {
+                        m.embed(|e| {
+                            e.title(tr(&locale, title))
+                            .description(tr_with(&locale, "lobby.invalid_time", &[("time", &lobby_time)]))
+                            .field(tr(&locale, "lobby.example_usage"), tr(&locale, "lobby.time_examples"), false)
+                            .field("\0", tr(&locale, "lobby.time_zone_note"), false)
+                        })
+                    }).await?;
+                    None
+                }
+                Ok(()) => Some(lobby_time),
+            },
+        },
     };
 
-    let reply_handle = ctx
-        .send(|m| {
-            m.embed(|e| {
-                e.title("Please select the content.")
-                //TODO!: Add fields for each content
-            })
-            .components(|c| {
-                c.create_action_row(|r| {
-                    r.create_button(|b| {
-                        b.style(serenity::ButtonStyle::Danger)
-                            .custom_id(lobby_id_string.clone() + "guardian-raid")
-                            .label("Guardian Raid")
-                    });
-                    r.create_button(|b| {
-                        b.style(serenity::ButtonStyle::Primary)
-                            .custom_id(lobby_id_string.clone() + "abyss-dungeon")
-                            .label("Abyss Dungeon")
-                    });
-                    r.create_button(|b| {
-                        b.style(serenity::ButtonStyle::Success)
-                            .custom_id(lobby_id_string.clone() + "abyss-raid")
-                            .label("Abyss Raid")
-                    })
-                })
-            })
-        })
-        .await?;
-
-    let message_id = reply_handle.message().await?.id.0;
+    let lobby_id = uuid::Uuid::new_v4();
+    let lobby_id_string = lobby_id.to_hyphenated().to_string();
+    let channel_id = ctx.channel_id();
 
-    let lobby_context_locked = Arc::new(RwLock::new(LobbyContext {
+    let mut lobby_context = LobbyContext {
         id: lobby_id,
         id_as_string: lobby_id_string.clone(),
         guild_id,
-        channel_id: ctx.channel_id().0,
-        message_id,
+        channel_id: channel_id.0,
+        // Set after the message is sent
+        message_id: 0,
         lobby_master: lobby_master.id.0,
+        locale: locale.clone(),
         state: State::ContentSelection,
         content: None,
         content_info: None,
+        options: Default::default(),
         lobby_time,
+        note: None,
+        requirements: Default::default(),
+        settings,
         players: vec![],
         active_players: vec![],
         player_list: vec![],
-    }));
-
-    while let Some(mci) = CollectComponentInteraction::new(ctx.discord())
-        .author_id(ctx.author().id)
-        .channel_id(ctx.channel_id())
-        .timeout(std::time::Duration::from_secs(600))
-        .filter({
-            let slice = lobby_id_string.clone();
-            move |mci| mci.data.custom_id.starts_with(slice.as_str())
-        })
-        .await
-    {
-        let mut lobby_context = lobby_context_locked.write();
-        match lobby_context.state {
-            State::ContentSelection => {
-                lobby_context.set_content(Some(LobbyContent::from(
-                    mci.data
-                        .custom_id
-                        .get(lobby_id_string.len()..mci.data.custom_id.len())
-                        .unwrap(),
-                )));
-
-                mci.create_interaction_response(ctx.discord(), |ir| {
-                    ir.kind(serenity::model::interactions::InteractionResponseType::UpdateMessage)
-                })
-                .await?;
-
-                create_select_content_message(
-                    mci.message.clone(),
-                    ctx,
-                    lobby_context.lobby_content(),
-                    &lobby_id_string,
-                )
-                .await?;
-                lobby_context.state = State::LobbyFirstPrompt;
-            }
-
-            State::LobbyFirstPrompt => {
-                mci.create_interaction_response(ctx.discord(), |ir| {
-                    ir.kind(serenity::model::interactions::InteractionResponseType::UpdateMessage)
-                })
-                .await?;
-
-                lobby_context
-                    .set_content_info(Some(CONTENT_DATA.get(&mci.data.values[0]).unwrap()));
-
-                let characters =
-                    get_guildmates_by_min_ilvl(guild_id, lobby_context.content_info().ilvl_req, db)
-                        .await;
-
-                lobby_context.player_list = vec![
-                    "\n*This slot is empty*".to_string();
-                    lobby_context.content_info().content_size as usize
-                ];
-
-                // I hate this fucking design. Just spent the last hour trying to move the embed and component creation
-                // logic to lobby_context struct just to see E0521 which makes PERFECT sense because the `EditMessage`
-                // is OUTSIDE of the closure not the INSIDE and don't get me started with the nested closures like I am some
-                // kind of fucking JS developer holy shit this is an ugly and unreadable mess. Like seriously just let me
-                // pass a struct with a Default implementation and save me from this atrocity so i can move my logic PLUS
-                // it wouldn't look as bad as this. Maybe I don't know or couldn't find it but all of Embed, Menu, Message
-                // types are either Vecs or HashMaps because (i think) they are converted to JSON but I can't explain
-                // myself enough how mad I am because I had to write this paragraph HOLYSHITAHSUGDFUAYFSDYATSD
-                //
-                // Edit from the future: Turns out I can kind of modularize the embed and action rows but not the message
-                // because it assigns stored data to builder type at background and its kind of pointless to do the same
-                // myself but I am ok with what I have now. Not deleting the original rant so I can remember the tough times
-                // our humankind went through. My ancestors would be proud.
-                mci.message
-                    .clone()
-                    .edit(ctx.discord(), |m| {
-                        m.embed(|e| {
-                            *e = lobby_context.create_embed();
-                            e
-                        })
-                        .components(|c| {
-                            c.create_action_row(|r| {
-                                r.create_select_menu(|m| {
-                                    match characters {
-                                        Err(DbErr::RecordNotFound(_)) => {
-                                            m.disabled(true)
-                                            .placeholder("There are no characters who can participate to this lobby :(")
-                                            .options(|o| o.create_option(|option|
-                                                option.value("0").label("None")))
-                                            .custom_id(&lobby_id_string)
-
-                                        }
-                                        Err(_) => {
-                                            m.disabled(true)
-                                            .placeholder("Error getting characters from database!")
-                                            .options(|o| o.create_option(|option|
-                                                option.value("0").label("None")))
-                                            .custom_id(&lobby_id_string)
-
-                                        }
-                                        _ => {
-                                            lobby_context.players = characters.unwrap();
-                                            *m = lobby_context.players_as_add_options();
-                                            m
-                                        }
-                                    }
-                                })
-                            }).create_action_row(|r| {
-                                r.create_select_menu(|m| {
-                                    *m = lobby_context.active_players_as_remove_options();
-                                    m
-                                })
-                            }).create_action_row(|r| {
-                                *r = lobby_context.create_lobby_buttons();
-                                r
-                            })
-                        })
-                    })
-                    .await?;
-                lobby_context.state = State::CollectPlayers;
-            }
+    };
 
-            State::CollectPlayers => {
-                mci.create_interaction_response(ctx.discord(), |ir| {
-                    ir.kind(serenity::model::interactions::InteractionResponseType::UpdateMessage)
-                })
-                .await?;
-
-                // Extract the str payload
-                match mci
-                    .data
-                    .custom_id
-                    .get(lobby_id_string.len()..mci.data.custom_id.len())
-                    .unwrap()
-                {
-                    "add" => {
-                        lobby_context
-                            .add_active_player(mci.data.values[0].parse::<usize>().unwrap(), db)
-                            .await;
-                    }
-                    "remove" => {
-                        lobby_context
-                            .remove_active_player(mci.data.values[0].parse::<usize>().unwrap(), db)
-                            .await;
-                    }
-                    "post-lobby" => {
-                        lobby_context.state = State::PrivateLobby;
-
-                        mci.message
-                            .clone()
-                            .edit(ctx.discord(), |m| {
-                                m.embed(|e| {
-                                    *e = lobby_context.create_embed();
-                                    e
-                                })
-                                .set_components(CreateComponents::default())
-                            })
-                            .await?;
-
-                        break;
-                    }
-                    "open-lobby" => {
-                        lobby_context.state = State::PublicLobby;
-
-                        mci.message
-                            .clone()
-                            .edit(ctx.discord(), |m| {
-                                m.embed(|e| {
-                                    *e = lobby_context.create_embed();
-                                    e
-                                })
-                                .components(|c| {
-                                    c.set_action_row(lobby_context.create_user_buttons())
-                                })
-                            })
-                            .await?;
+    let (embed, rows) = wizard::render(&lobby_context, 0, db).await?;
+    let reply_handle = ctx
+        .send(|m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+            .components(|c| c.set_action_rows(rows))
+        })
+        .await?;
+    lobby_context.message_id = reply_handle.message().await?.id.0;
+
+    match run_wizard(ctx, &mut lobby_context).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(()),
+        Err(err) => {
+            // Buttons of the wizard stop working once the command returns
+            show_ended(ctx, &lobby_context, "lobby.cancelled").await;
+            return Err(err);
+        }
+    }
 
-                        break;
-                    }
-                    "close-lobby" => {
-                        mci.message.clone().delete(ctx.discord()).await?;
-                        ctx.say("Lobby is closed").await?;
+    // End the command context here and let the supervisor run the lobby task
+    ctx.data()
+        .lobby_supervisor
+        .spawn(Arc::new(RwLock::new(lobby_context)));
 
-                        return Ok(());
-                    }
-                    _ => unreachable!(),
-                }
+    Ok(())
+}
 
-                mci.message
-                    .clone()
-                    .edit(ctx.discord(), |m| {
-                        m.embed(|e| {
-                            *e = lobby_context.create_embed();
-                            e
-                        })
-                        .components(|c| {
-                            c.create_action_row(|r| {
-                                r.create_select_menu(|m| {
-                                    *m = lobby_context.players_as_add_options();
-                                    m
-                                })
-                            })
-                            .create_action_row(|r| {
-                                r.create_select_menu(|m| {
-                                    *m = lobby_context.active_players_as_remove_options();
-                                    m
-                                })
-                            })
-                            .create_action_row(|r| {
-                                *r = lobby_context.create_lobby_buttons();
-                                r
+/// Runs the setup of the lobby until it is posted and inserted to the database.
+///
+/// Returns `false` if the setup is cancelled or expired.
+async fn run_wizard(
+    ctx: Context<'_, Data, Error>,
+    lobby_context: &mut LobbyContext,
+) -> Result<bool, Error> {
+    let db = ctx.data().db;
+    let locale = lobby_context.locale.clone();
+    let channel_id = serenity::ChannelId(lobby_context.channel_id);
+    let lobby_id_string = lobby_context.id_as_string.clone();
+
+    loop {
+        let interaction = tokio::select! {
+            mci = CollectComponentInteraction::new(ctx.discord())
+                .author_id(ctx.author().id)
+                .channel_id(channel_id)
+                .timeout(WIZARD_TIMEOUT)
+                .filter({
+                    let lobby_id_string = lobby_id_string.clone();
+                    move |mci| is_wizard_component(&mci.data.custom_id, &lobby_id_string)
+                }) => mci.map(WizardInteraction::Component),
+            msi = CollectModalInteraction::new(ctx.discord())
+                .author_id(ctx.author().id)
+                .channel_id(channel_id)
+                .timeout(WIZARD_TIMEOUT)
+                .filter({
+                    let lobby_id_string = lobby_id_string.clone();
+                    move |msi| is_wizard_component(&msi.data.custom_id, &lobby_id_string)
+                }) => msi.map(WizardInteraction::Modal),
+        };
+
+        let interaction = match interaction {
+            Some(interaction) => interaction,
+            None => {
+                show_ended(ctx, lobby_context, "lobby.expired").await;
+                return Ok(false);
+            }
+        };
+
+        let transition = match interaction.event() {
+            Ok(event) => wizard::apply(lobby_context, event, db).await,
+            Err(err) => Err(err),
+        };
+        let transition = match transition {
+            Ok(transition) => transition,
+            Err(err) => {
+                // Setup stays at its current step, the user can try again
+                err.report_interaction(interaction.routed(), &ctx.discord().http)
+                    .await;
+                continue;
+            }
+        };
+
+        let page = match transition {
+            Transition::Render { page } => page,
+            Transition::EditDetails => {
+                if let WizardInteraction::Component(mci) = &interaction {
+                    mci.create_interaction_response(ctx.discord(), |ir| {
+                        ir.kind(serenity::InteractionResponseType::Modal)
+                            .interaction_response_data(|d| {
+                                d.custom_id(lobby_context.wizard_id("details"))
+                                    .title(tr(&locale, "lobby.details_title"))
+                                    .set_components(wizard::details_inputs(lobby_context))
                             })
-                        })
                     })
                     .await?;
+                }
+                continue;
             }
-            _ => unreachable!(),
-        }
+            Transition::Rejected(reason) => {
+                let routed = interaction.routed();
+                let user_locale = interaction_locale(routed.locale(), &locale);
+                routed
+                    .respond_ephemeral(&ctx.discord().http, &tr(&user_locale, reason))
+                    .await?;
+                continue;
+            }
+            Transition::Cancel => {
+                interaction.acknowledge(&ctx.discord().http).await?;
+                show_ended(ctx, lobby_context, "lobby.cancelled").await;
+                return Ok(false);
+            }
+            Transition::Post => {
+                interaction.acknowledge(&ctx.discord().http).await?;
+                break;
+            }
+        };
+
+        interaction.acknowledge(&ctx.discord().http).await?;
+
+        // I hate this fucking design. Just spent the last hour trying to move the embed and component creation
+        // logic to lobby_context struct just to see E0521 which makes PERFECT sense because the `EditMessage`
+        // is OUTSIDE of the closure not the INSIDE and don't get me started with the nested closures like I am some
+        // kind of fucking JS developer holy shit this is an ugly and unreadable mess. Like seriously just let me
+        // pass a struct with a Default implementation and save me from this atrocity so i can move my logic PLUS
+        // it wouldn't look as bad as this. Maybe I don't know or couldn't find it but all of Embed, Menu, Message
+        // types are either Vecs or HashMaps because (i think) they are converted to JSON but I can't explain
+        // myself enough how mad I am because I had to write this paragraph HOLYSHITAHSUGDFUAYFSDYATSD
+        //
+        // Edit from the future: Turns out I can kind of modularize the embed and action rows but not the message
+        // because it assigns stored data to builder type at background and its kind of pointless to do the same
+        // myself but I am ok with what I have now. Not deleting the original rant so I can remember the tough times
+        // our humankind went through. My ancestors would be proud.
+        let (embed, rows) = wizard::render(lobby_context, page, db).await?;
+        channel_id
+            .edit_message(ctx.discord(), lobby_context.message_id, |m| {
+                m.embed(|e| {
+                    *e = embed;
+                    e
+                })
+                .components(|c| c.set_action_rows(rows))
+            })
+            .await?;
     }
 
-    {
-        let mut lobby_context = lobby_context_locked.write();
-        // We do not need players after lobby is posted.
-        lobby_context.players = vec![];
+    let mut components = CreateComponents::default();
+    if let State::PublicLobby = lobby_context.state {
+        components.set_action_row(lobby_context.create_user_buttons());
     }
+    channel_id
+        .edit_message(ctx.discord(), lobby_context.message_id, |m| {
+            m.embed(|e| {
+                *e = lobby_context.create_embed();
+                e
+            })
+            .set_components(components)
+        })
+        .await?;
+
+    // We do not need players after lobby is posted.
+    lobby_context.players = vec![];
 
     // From this point on lobby is created and inserted to database
     // Further component interactions will be collected from event listener
@@ -375,62 +290,177 @@ pub async fn create_lobby(
     //
     // I spent too much time thinking about this and i am not proud of it.
 
-    insert_lobby(&lobby_context_locked.read(), db).await?;
-    let (sender, mut reciever) = unbounded_channel::<EventComponent>();
-    ctx.data()
-        .active_lobbies
-        .write()
-        .insert(lobby_context_locked.read().id_as_string.clone(), sender);
-
-    println!(
-        "Inserted lobby id: {}",
-        lobby_context_locked.read().id_as_string
-    );
-
-    // End the command context here and spawn a background task
-    tokio::spawn({
-        let db = ctx.data().db.clone();
-        async move {
-            while let Some(event_c) = reciever.recv().await {
-                match process_lobby_event(event_c, lobby_context_locked.clone(), &db).await {
-                    Ok(_) => {}
-                    Err(err) => {
-                        println!("Error processing event: {err}")
-                    }
-                }
+    db.insert_lobby(lobby_context).await?;
+
+    tracing::info!(lobby_id = %lobby_context.id_as_string, "Inserted lobby");
+
+    Ok(true)
+}
+
+/// Replaces the wizard message with the message of the key.
+async fn show_ended(ctx: Context<'_, Data, Error>, lobby_context: &LobbyContext, key: &str) {
+    let result = serenity::ChannelId(lobby_context.channel_id)
+        .edit_message(ctx.discord(), lobby_context.message_id, |m| {
+            m.content(tr(&lobby_context.locale, key))
+                .set_embeds(vec![])
+                .set_components(CreateComponents::default())
+        })
+        .await;
+
+    if let Err(err) = result {
+        tracing::warn!(lobby_id = %lobby_context.id_as_string, "Couldn't end the lobby setup: {err}");
+    }
+}
+
+/// Component or details modal of the lobby setup.
+enum WizardInteraction {
+    Component(Arc<MessageComponentInteraction>),
+    Modal(Arc<ModalSubmitInteraction>),
+}
+
+impl WizardInteraction {
+    fn routed(&self) -> RoutedInteraction<'_> {
+        match self {
+            WizardInteraction::Component(mci) => RoutedInteraction::Component(mci),
+            WizardInteraction::Modal(msi) => RoutedInteraction::Modal(msi),
+        }
+    }
+
+    /// Event of the wizard sent by the interaction.
+    fn event(&self) -> Result<WizardEvent, Error> {
+        match self {
+            WizardInteraction::Component(mci) => {
+                WizardEvent::parse(&ComponentId::decode(&mci.data.custom_id)?, &mci.data.values)
             }
+            WizardInteraction::Modal(msi) => Ok(WizardEvent::from_modal(&msi.data.components)),
         }
-    });
+    }
 
-    Ok(())
+    /// Acknowledges the interaction, the wizard message is edited afterwards.
+    async fn acknowledge(
+        &self,
+        http: &serenity::http::client::Http,
+    ) -> Result<(), serenity::Error> {
+        match self {
+            WizardInteraction::Component(mci) => {
+                mci.create_interaction_response(http, |ir| {
+                    ir.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+                })
+                .await
+            }
+            WizardInteraction::Modal(msi) => {
+                msi.create_interaction_response(http, |ir| {
+                    ir.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+                })
+                .await
+            }
+        }
+    }
+}
+
+fn is_wizard_component(custom_id: &str, lobby_id: &str) -> bool {
+    ComponentId::decode(custom_id)
+        .map(|component_id| component_id.is_for(WIZARD, lobby_id))
+        .unwrap_or(false)
 }
 
-async fn create_select_content_message(
-    mut message: Message,
+#[poise::command(slash_command, category = "Lobby", guild_only, check = "is_guild_init")]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn reschedule_lobby(
     ctx: Context<'_, Data, Error>,
-    content: LobbyContent,
-    custom_id: &str,
+    #[description = "Id of the lobby, shown at the bottom of the lobby"] lobby_id: String,
+    #[description = "New time of the lobby"] lobby_time: String,
 ) -> Result<(), Error> {
-    message
-        .edit(ctx.discord(), |m| {
-            m.embed(|e| e.title(format!("Select the {}", content)))
-                .components(|c| {
-                    c.create_action_row(|r| {
-                        r.create_select_menu(|m| {
-                            m.placeholder(format!("Please select the {}", content))
-                                .options(|o| {
-                                    for instance in content.get_content_list() {
-                                        o.create_option(|option| {
-                                            option.label(instance).value(instance)
-                                        });
-                                    }
-                                    o
-                                })
-                                .custom_id(custom_id)
-                        })
-                    })
-                })
+    let guild_id = guild_id(ctx)?;
+    let db = ctx.data().db;
+    let locale = user_locale(ctx).await;
+
+    let lobby_context_locked = ctx.data().lobby_supervisor.context(lobby_id.trim());
+
+    // Lobbies of the other guilds are not found
+    let lobby_context_locked = match lobby_context_locked {
+        Some(lobby_context_locked) if lobby_context_locked.read().guild_id == guild_id => {
+            lobby_context_locked
+        }
+        _ => {
+            ctx.say(tr(&locale, "lobby.not_found")).await?;
+            return Ok(());
+        }
+    };
+
+    let lobby_master = lobby_context_locked.read().lobby_master;
+    if lobby_master != ctx.author().id.0 {
+        match db.get_guildmate(ctx.author().id.0, guild_id).await {
+            Ok(guildmate) if guildmate.role != Role::Guildmate => {}
+            Ok(_) | Err(DbErr::RecordNotFound(_)) => {
+                ctx.say(tr(&locale, "lobby.reschedule_not_allowed")).await?;
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    let timezone = db.get_server(guild_id).await?.timezone;
+    let new_time = match parse_lobby_time(&lobby_time, timezone) {
+        Some(new_time) => new_time,
+        None => {
+            ctx.say(tr(&locale, "lobby.time_parse_error")).await?;
+            return Ok(());
+        }
+    };
+
+    if let Err(title) = check_lobby_time(new_time) {
+        ctx.say(tr(&locale, title)).await?;
+        return Ok(());
+    }
+
+    let (lobby_uuid, channel, message_id, settings, players) = {
+        let lobby_context = lobby_context_locked.read();
+        (
+            lobby_context.id,
+            serenity::ChannelId(lobby_context.channel_id),
+            lobby_context.message_id,
+            lobby_context.settings,
+            lobby_context
+                .active_players
+                .iter()
+                .map(|player| player.name.clone())
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    // New time may be too close to the other lobbies of the players
+    let busy = settings
+        .busy_characters(guild_id, lobby_uuid, Some(new_time), db)
+        .await?;
+    if players.iter().any(|name| busy.contains(name)) {
+        ctx.say(tr(&locale, "lobby.reschedule_conflict")).await?;
+        return Ok(());
+    }
+
+    db.update_lobby_schedule(lobby_uuid, Some(new_time)).await?;
+
+    let embed = {
+        let mut lobby_context = lobby_context_locked.write();
+        lobby_context.lobby_time = Some(new_time);
+        lobby_context.create_embed()
+    };
+
+    channel
+        .edit_message(ctx.discord(), message_id, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
         })
         .await?;
+
+    ctx.say(tr_with(
+        &locale,
+        "lobby.rescheduled",
+        &[("timestamp", &new_time.timestamp())],
+    ))
+    .await?;
+
     Ok(())
 }
@@ -1,21 +1,27 @@
+use std::sync::Arc;
+
 use chrono::{DateTime, Utc};
+use hashbrown::HashSet;
 use poise::serenity_prelude::{self as serenity, CreateSelectMenu};
 use poise::serenity_prelude::{CreateActionRow, CreateEmbed};
-use sea_orm::DatabaseConnection;
+use sea_orm::DbErr;
 
 use super::command::State;
 use super::helper::*;
-use crate::database::get_guildmates_by_min_ilvl_filter_out;
+use super::requirements::{LobbyRequirements, GOLD_RUN_RULE, VOICE_RULE};
+use super::settings::LobbySettings;
+use crate::component_id::{ComponentId, LOBBY, WIZARD};
 use crate::info::*;
+use crate::locale::{tr, tr_with};
+use crate::storage::Storage;
 
 /// Represents the lobby.
 ///
 /// Important thing about the component creations is discord only allows [String] values
 /// to pass as custom_ids or [serenity::builder::create_components::CreateSelectMenuOptions] values.
-/// So as a workaround to filter the component interactions we check if custom id starts with our command's
-/// custom id and then extract the last part of the custom id and match it.
-/// This requires some string operations every time bot recieves a component interaction thus an allocation.
-/// I currently have no idea how much does it cost but I doubt there is a better way since we can only send strings to discord.
+/// So every component of the lobby has a [ComponentId] with the lobby id as the entity and
+/// the interactions are matched by decoding the custom id.
+#[derive(Clone)]
 pub struct LobbyContext {
     pub id: uuid::Uuid,
     pub id_as_string: String,
@@ -23,10 +29,19 @@ pub struct LobbyContext {
     pub channel_id: u64,
     pub message_id: u64,
     pub lobby_master: u64,
+    /// Locale of the server, used for the messages seen by everyone.
+    pub locale: String,
     pub state: State,
     pub content: Option<LobbyContent>,
-    pub content_info: Option<&'static ContentInfo>,
+    /// Kept by the lobby so reloading the contents doesn't change running lobbies.
+    pub content_info: Option<Arc<ContentInfo>>,
+    pub options: ContentOptions,
     pub lobby_time: Option<DateTime<Utc>>,
+    /// Note of the lobby master shown under the lobby.
+    pub note: Option<String>,
+    pub requirements: LobbyRequirements,
+    /// Settings of the server when the lobby was created or loaded.
+    pub settings: LobbySettings,
     pub players: Vec<entity::characters::Model>,
     pub active_players: Vec<entity::characters::Model>,
     pub player_list: Vec<String>,
@@ -35,14 +50,14 @@ pub struct LobbyContext {
 impl LobbyContext {
     /// Get a reference to the lobby context's lobby content.
     #[must_use]
-    pub fn lobby_content(&self) -> LobbyContent {
-        self.content.unwrap()
+    pub fn lobby_content(&self) -> &LobbyContent {
+        self.content.as_ref().unwrap()
     }
 
     /// Get a reference to the lobby context's content info.
     #[must_use]
     pub fn content_info(&self) -> &ContentInfo {
-        self.content_info.as_ref().unwrap()
+        self.content_info.as_deref().unwrap()
     }
 
     /// Set the lobby context's content.
@@ -50,76 +65,252 @@ impl LobbyContext {
         self.content = content;
     }
 
-    /// Set the lobby context's content info.
-    pub fn set_content_info(&mut self, content_info: Option<&'static ContentInfo>) {
+    /// Set the lobby context's content info. Options are reset to the defaults of the content.
+    pub fn set_content_info(&mut self, content_info: Option<Arc<ContentInfo>>) {
+        self.options = content_info
+            .as_deref()
+            .map(ContentInfo::default_options)
+            .unwrap_or_default();
         self.content_info = content_info;
     }
 
+    /// Minimum item level of the content with the selected difficulty and gates.
+    pub fn ilvl_req(&self) -> i32 {
+        self.content_info().ilvl_req_for(&self.options)
+    }
+
     pub fn create_embed(&self) -> CreateEmbed {
+        let content_info = self.content_info();
+        let locale = &self.locale;
+        let category = self.lobby_content().name(locale);
+        let requirement = if content_info.is_custom() {
+            tr_with(
+                locale,
+                "lobby.minimum_item_level",
+                &[("ilvl", &self.ilvl_req())],
+            )
+        } else {
+            tr_with(
+                locale,
+                "lobby.requirement",
+                &[
+                    ("tier", &content_info.tier),
+                    ("category", &category),
+                    ("ilvl", &self.ilvl_req()),
+                ],
+            )
+        };
+
+        // This will be a discord timestamp
+        let scheduled_time = match self.lobby_time {
+            Some(time) => format!("<t:{0}:R>  (<t:{0}:F>)", time.timestamp()),
+            None => tr(locale, "lobby.not_set"),
+        };
+
         let mut embed = CreateEmbed::default();
+        // Custom contents may not have an image or a guide
+        if !content_info.banner.is_empty() {
+            embed.image(&content_info.banner);
+        }
+        if !content_info.guide.is_empty() {
+            embed.url(&content_info.guide);
+        }
+        if let Some(colour) = self.lobby_content().category.colour() {
+            embed.colour(colour);
+        }
         embed
-            .title(format!(
-                "{}: {}",
-                self.lobby_content(),
-                self.content_info().name
-            ))
-            .description(&self.content_info().introduction)
-            .image(&self.content_info().banner)
-            .url(&self.content_info().guide)
+            .title(match content_info.describe_options(&self.options) {
+                Some(options) => format!("{category}: {} ({options})", content_info.name),
+                None => format!("{category}: {}", content_info.name),
+            })
+            .description(content_info.introduction_for(locale))
             .field(
-                format!(
-                    "Tier {} {} (Minimum Item Level) => {}",
-                    self.content_info().tier,
-                    self.lobby_content(),
-                    self.content_info().ilvl_req
-                ),
-                format!("Scheduled time: {}", {
-                    match self.lobby_time {
-                        Some(time) => format!("<t:{0}:R>  (<t:{0}:F>)", time.timestamp()),
-                        None => "Not Set".to_owned() + "",
-                    }
-                }), // This will be a discord timestamp
+                requirement,
+                tr_with(locale, "lobby.scheduled_time", &[("time", &scheduled_time)]),
                 true,
+            );
+        if let Some(note) = &self.note {
+            embed.field(tr(locale, "lobby.note"), note, false);
+        }
+        if !self.requirements.is_empty() {
+            embed.field(
+                tr(locale, "lobby.requirements"),
+                self.requirements.describe(locale),
+                false,
+            );
+        }
+        embed
+            .field(
+                tr(locale, "lobby.participating_players"),
+                self.player_list.concat(),
+                false,
             )
-            .field("Participating Players:", self.player_list.concat(), false)
-            .footer(|foo| foo.text(format!("Lobby id: {}", self.id_as_string)));
+            .footer(|foo| {
+                foo.text(tr_with(
+                    locale,
+                    "lobby.footer",
+                    &[("id", &self.id_as_string)],
+                ))
+            });
         embed
     }
 
+    /// Returns the encoded custom id of a lobby component with given action.
+    pub fn component_id(&self, action: &str) -> String {
+        ComponentId::new(LOBBY, &self.id_as_string, action).encode()
+    }
+
+    /// Returns the encoded custom id of a component of the lobby setup with given action.
+    pub fn wizard_id(&self, action: &str) -> String {
+        ComponentId::new(WIZARD, &self.id_as_string, action).encode()
+    }
+
     pub fn create_lobby_buttons(&self) -> CreateActionRow {
         let mut buttons = CreateActionRow::default();
 
         buttons.create_button(|b| {
-            b.label("Post Lobby")
+            b.label(tr(&self.locale, "lobby.post"))
                 .style(serenity::ButtonStyle::Success)
-                .custom_id(self.id_as_string.clone() + "post-lobby")
+                .custom_id(self.wizard_id("post-lobby"))
         });
         buttons.create_button(|b| {
-            b.label("Close Lobby")
-                .style(serenity::ButtonStyle::Danger)
-                .custom_id(self.id_as_string.clone() + "close-lobby")
+            b.label(tr(&self.locale, "lobby.open"))
+                .style(serenity::ButtonStyle::Primary)
+                .custom_id(self.wizard_id("open-lobby"))
         });
+
+        buttons
+    }
+
+    /// Embed of the difficulty and gate selection with the resulting item level.
+    pub fn create_options_embed(&self) -> CreateEmbed {
+        let content_info = self.content_info();
+        let mut embed = CreateEmbed::default();
+        embed.title(tr_with(
+            &self.locale,
+            "lobby.select_options_title",
+            &[("content", &content_info.name)],
+        ));
+        match content_info.check_options(&self.options) {
+            Ok(()) => embed.description(format!(
+                "{}\n{}",
+                content_info
+                    .describe_options(&self.options)
+                    .unwrap_or_default(),
+                tr_with(
+                    &self.locale,
+                    "lobby.minimum_item_level",
+                    &[("ilvl", &self.ilvl_req())]
+                )
+            )),
+            Err(_) => embed.description(tr(&self.locale, "lobby.select_options")),
+        };
+        embed
+    }
+
+    pub fn difficulties_as_options(&self) -> CreateSelectMenu {
+        let mut menu = CreateSelectMenu::default();
+        menu.custom_id(self.wizard_id("difficulty"))
+            .placeholder(tr(&self.locale, "lobby.select_difficulty"))
+            .options(|o| {
+                for difficulty in &self.content_info().difficulties {
+                    o.create_option(|o| {
+                        o.label(&difficulty.name)
+                            .value(&difficulty.name)
+                            .description(tr_with(
+                                &self.locale,
+                                "lobby.item_level",
+                                &[("ilvl", &difficulty.ilvl_req)],
+                            ))
+                            .default_selection(
+                                self.options.difficulty.as_ref() == Some(&difficulty.name),
+                            )
+                    });
+                }
+                o
+            });
+        menu
+    }
+
+    /// Select menu of the first (`gate-start`) or the last (`gate-end`) gate of the lobby.
+    pub fn gates_as_options(&self, action: &str) -> CreateSelectMenu {
+        let selected = match action {
+            "gate-start" => self.options.gate_start,
+            _ => self.options.gate_end,
+        };
+
+        let mut menu = CreateSelectMenu::default();
+        menu.custom_id(self.wizard_id(action))
+            .placeholder(match action {
+                "gate-start" => tr(&self.locale, "lobby.select_first_gate"),
+                _ => tr(&self.locale, "lobby.select_last_gate"),
+            })
+            .options(|o| {
+                for (index, gate) in self.content_info().gates.iter().enumerate() {
+                    let number = index as i32 + 1;
+                    o.create_option(|o| {
+                        o.label(&gate.name)
+                            .value(number)
+                            .default_selection(selected == Some(number))
+                    });
+                }
+                o
+            });
+        menu
+    }
+
+    pub fn create_option_buttons(&self) -> CreateActionRow {
+        let mut buttons = CreateActionRow::default();
+
         buttons.create_button(|b| {
-            b.label("Open Lobby")
-                .style(serenity::ButtonStyle::Primary)
-                .custom_id(self.id_as_string.clone() + "open-lobby")
+            b.label(tr(&self.locale, "lobby.continue"))
+                .style(serenity::ButtonStyle::Success)
+                .custom_id(self.wizard_id("continue"))
+                .disabled(self.content_info().check_options(&self.options).is_err())
         });
 
         buttons
     }
 
+    /// Select menu of the rules which are either followed or not.
+    pub fn rules_as_options(&self) -> CreateSelectMenu {
+        let mut menu = CreateSelectMenu::default();
+        menu.custom_id(self.wizard_id("rules"))
+            .placeholder(tr(&self.locale, "lobby.select_rules"))
+            .min_values(0)
+            .max_values(2)
+            .options(|o| {
+                o.create_option(|o| {
+                    o.label(tr(&self.locale, "lobby.rule_voice"))
+                        .value(VOICE_RULE)
+                        .default_selection(self.requirements.voice)
+                })
+                .create_option(|o| {
+                    o.label(tr(&self.locale, "lobby.rule_gold_run"))
+                        .value(GOLD_RUN_RULE)
+                        .default_selection(self.requirements.gold_run)
+                })
+            });
+        menu
+    }
+
     pub fn create_user_buttons(&self) -> CreateActionRow {
         let mut buttons = CreateActionRow::default();
 
         buttons.create_button(|b| {
-            b.label("Join Lobby!")
+            b.label(tr(&self.locale, "lobby.join"))
                 .style(serenity::ButtonStyle::Success)
-                .custom_id(self.id_as_string.clone() + "lobby-join")
+                .custom_id(self.component_id("lobby-join"))
         });
         buttons.create_button(|b| {
-            b.label("Leave Lobby!")
+            b.label(tr(&self.locale, "lobby.swap"))
+                .style(serenity::ButtonStyle::Secondary)
+                .custom_id(self.component_id("lobby-swap"))
+        });
+        buttons.create_button(|b| {
+            b.label(tr(&self.locale, "lobby.leave"))
                 .style(serenity::ButtonStyle::Danger)
-                .custom_id(self.id_as_string.clone() + "lobby-leave")
+                .custom_id(self.component_id("lobby-leave"))
         });
 
         buttons
@@ -127,24 +318,28 @@ impl LobbyContext {
 
     pub fn players_as_add_options(&self) -> CreateSelectMenu {
         let mut menu = CreateSelectMenu::default();
-        menu.custom_id(self.id_as_string.clone() + "add");
+        menu.custom_id(self.wizard_id("add"));
 
         if self.players.is_empty() {
             menu.disabled(true)
-                .placeholder("No characters available!")
-                .options(|o| o.create_option(|o| o.label("Empty").value("0")));
+                .placeholder(tr(&self.locale, "lobby.no_characters"))
+                .options(|o| {
+                    o.create_option(|o| o.label(tr(&self.locale, "lobby.empty")).value("0"))
+                });
         } else if self.active_players.len() == self.content_info().content_size as usize {
             menu.disabled(true)
-                .placeholder("Content size limit reached.")
-                .options(|o| o.create_option(|o| o.label("empty").value("0")));
+                .placeholder(tr(&self.locale, "lobby.size_limit"))
+                .options(|o| {
+                    o.create_option(|o| o.label(tr(&self.locale, "lobby.empty")).value("0"))
+                });
         } else {
             menu.options(|o| {
                 for (index, player) in self.players.iter().enumerate() {
-                    o.add_option(player.option(index));
+                    o.add_option(player.option(index, &self.locale));
                 }
                 o
             })
-            .placeholder("Select a player to add.");
+            .placeholder(tr(&self.locale, "lobby.select_player_add"));
         }
 
         menu
@@ -153,76 +348,144 @@ impl LobbyContext {
     pub fn active_players_as_remove_options(&self) -> CreateSelectMenu {
         let mut menu = CreateSelectMenu::default();
 
-        menu.custom_id(self.id_as_string.clone() + "remove");
+        menu.custom_id(self.wizard_id("remove"));
 
         if self.active_players.is_empty() {
             menu.disabled(true)
-                .placeholder("No active characters available!")
-                .options(|o| o.create_option(|o| o.label("Empty").value("0")));
+                .placeholder(tr(&self.locale, "lobby.no_active_characters"))
+                .options(|o| {
+                    o.create_option(|o| o.label(tr(&self.locale, "lobby.empty")).value("0"))
+                });
         } else {
             menu.options(|o| {
                 for (index, player) in self.active_players.iter().enumerate() {
-                    o.add_option(player.option(index));
+                    o.add_option(player.option(index, &self.locale));
                 }
                 o
             })
-            .placeholder("Select a player to remove.");
+            .placeholder(tr(&self.locale, "lobby.select_player_remove"));
         }
 
         menu
     }
 
-    // TODO!: Need to remove all affiliated guildmate characters
-    pub async fn add_active_player(&mut self, idx: usize, db: &DatabaseConnection) {
+    /// Characters the lobby master can add. They are eligible for the content, not in the
+    /// lobby and not in another lobby scheduled too close.
+    pub async fn available_players(
+        &self,
+        db: &dyn Storage,
+    ) -> Result<Vec<entity::characters::Model>, DbErr> {
+        let characters = if self.settings.single_character {
+            // Every character of the users in the lobby is filtered out
+            db.get_guildmates_by_min_ilvl_filter_out(
+                self.guild_id,
+                self.ilvl_req(),
+                &self.active_players,
+            )
+            .await
+        } else {
+            db.get_guildmates_by_min_ilvl(self.guild_id, self.ilvl_req())
+                .await
+        };
+        let characters = match characters {
+            Ok(characters) => characters,
+            Err(DbErr::RecordNotFound(_)) => vec![],
+            Err(err) => return Err(err),
+        };
+
+        let busy = self.busy_characters(db).await?;
+        Ok(characters
+            .into_iter()
+            .filter(|character| {
+                !busy.contains(&character.name) && !self.is_active_character(&character.name)
+            })
+            .collect())
+    }
+
+    /// Names of the characters in the other lobbies scheduled too close to the lobby.
+    pub async fn busy_characters(&self, db: &dyn Storage) -> Result<HashSet<String>, DbErr> {
+        self.settings
+            .busy_characters(self.guild_id, self.id, self.lobby_time, db)
+            .await
+    }
+
+    /// Returns true if a character in the lobby is in another lobby scheduled too close.
+    pub async fn has_busy_players(&self, db: &dyn Storage) -> Result<bool, DbErr> {
+        let busy = self.busy_characters(db).await?;
+        Ok(self
+            .active_players
+            .iter()
+            .any(|player| busy.contains(&player.name)))
+    }
+
+    pub async fn refresh_players(&mut self, db: &dyn Storage) {
+        self.players = self.available_players(db).await.unwrap_or_default();
+    }
+
+    pub async fn add_active_player(&mut self, idx: usize, db: &dyn Storage) {
         let player = self.players[idx].clone();
 
-        self.player_list[self.active_players.len()] = format!(
-            "\n**{}** ({}) => __**{}** Item Level__ | <@{}>",
-            player.name, player.class, player.item_level, player.id
-        );
+        self.player_list[self.active_players.len()] = self.player_line(&player);
 
         self.active_players.push(player);
 
-        self.players = get_guildmates_by_min_ilvl_filter_out(
-            self.guild_id,
-            self.content_info().ilvl_req,
-            &self.active_players,
-            db,
-        )
-        .await
-        .unwrap_or_default();
+        self.refresh_players(db).await;
     }
 
     // This function is only called when lobby is posted and when lobby is posted we empty
     // the player list in lobby_context so we dont need to update player list
     pub fn add_active_player_by_model(&mut self, player: entity::characters::Model) {
-        self.player_list[self.active_players.len()] = format!(
-            "\n**{}** ({}) => __**{}** Item Level__ | <@{}>",
-            player.name, player.class, player.item_level, player.id
-        );
+        self.player_list[self.active_players.len()] = self.player_line(&player);
 
         self.active_players.push(player);
     }
 
-    pub async fn remove_active_player(&mut self, idx: usize, db: &DatabaseConnection) {
+    pub async fn remove_active_player(&mut self, idx: usize, db: &dyn Storage) {
         let _player = self.active_players.remove(idx);
         self.player_list.remove(idx);
-        self.player_list.push("\n*This slot is empty*".to_string());
+        self.player_list.push(self.empty_slot());
 
-        self.players = get_guildmates_by_min_ilvl_filter_out(
-            self.guild_id,
-            self.content_info().ilvl_req,
-            &self.active_players,
-            db,
-        )
-        .await
-        .unwrap_or_default();
+        self.refresh_players(db).await;
+    }
+
+    /// Replaces a character in the lobby with another one, keeping its slot. Returns false if
+    /// the character isn't in the lobby.
+    pub fn swap_active_player(&mut self, name: &str, player: entity::characters::Model) -> bool {
+        match self.active_players.iter().position(|p| p.name == name) {
+            Some(idx) => {
+                self.player_list[idx] = self.player_line(&player);
+                self.active_players[idx] = player;
+                true
+            }
+            None => false,
+        }
     }
 
     pub fn remove_active_player_without_filter(&mut self, idx: usize) {
         let _player = self.active_players.remove(idx);
         self.player_list.remove(idx);
-        self.player_list.push("\n*This slot is empty*".to_string());
+        self.player_list.push(self.empty_slot());
+    }
+
+    /// Line of an empty slot in the player list.
+    pub fn empty_slot(&self) -> String {
+        format!("\n{}", tr(&self.locale, "lobby.empty_slot"))
+    }
+
+    fn player_line(&self, player: &entity::characters::Model) -> String {
+        format!(
+            "\n{}",
+            tr_with(
+                &self.locale,
+                "lobby.player",
+                &[
+                    ("name", &player.name),
+                    ("class", &player.class),
+                    ("ilvl", &player.item_level),
+                    ("user", &player.id),
+                ],
+            )
+        )
     }
 
     pub fn is_active_player(&self, id: u64) -> bool {
@@ -234,4 +497,8 @@ impl LobbyContext {
         }
         false
     }
+
+    pub fn is_active_character(&self, name: &str) -> bool {
+        self.active_players.iter().any(|player| player.name == name)
+    }
 }
@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use poise::serenity_prelude as serenity;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::{
+    component_id::ComponentId,
+    locale::{interaction_locale, tr, DEFAULT_LOCALE},
+    router::{InteractionHandler, Outcome, RoutedInteraction},
+    Data, Error, EventComponent,
+};
+
+/// Sends the interactions of the posted lobbies to their tasks.
+pub struct LobbyHandler;
+
+#[async_trait]
+impl InteractionHandler for LobbyHandler {
+    async fn check(
+        &self,
+        _ctx: &serenity::Context,
+        _data: &Data,
+        _component_id: &ComponentId,
+        interaction: RoutedInteraction<'_>,
+    ) -> Result<(), Error> {
+        if interaction.guild_id().is_none() {
+            return Err(Error::permission("lobby.only_in_server"));
+        }
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        ctx: &serenity::Context,
+        data: &Data,
+        component_id: ComponentId,
+        interaction: RoutedInteraction<'_>,
+    ) -> Result<Outcome, Error> {
+        let mci = match interaction {
+            RoutedInteraction::Component(mci) => mci,
+            RoutedInteraction::Modal(_) => return Ok(Outcome::Expired),
+        };
+
+        let event_c = match EventComponent::new(mci.clone(), ctx.http.clone(), &component_id.action)
+        {
+            Ok(event_c) => event_c,
+            // Buttons removed from the lobbies may still be on the older lobby messages
+            Err(_) => return Ok(Outcome::Expired),
+        };
+
+        match data.lobby_supervisor.send(&component_id.entity, event_c) {
+            Ok(()) => Ok(Outcome::Handled),
+            Err(TrySendError::Full(_)) => {
+                let locale = interaction_locale(interaction.locale(), DEFAULT_LOCALE);
+                interaction
+                    .respond_ephemeral(&ctx.http, &tr(&locale, "lobby.busy"))
+                    .await?;
+                Ok(Outcome::Handled)
+            }
+            // Lobby is finished or its task is being restarted
+            Err(TrySendError::Closed(_)) => Ok(Outcome::Expired),
+        }
+    }
+}
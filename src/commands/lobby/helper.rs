@@ -1,71 +1,175 @@
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
-use entity::sea_orm_active_enums::Content;
+use chrono::{DateTime, Utc};
 use parking_lot::RwLock;
 use parse_display::Display;
 use poise::serenity_prelude as serenity;
 use poise::serenity_prelude::CreateSelectMenuOption;
-use sea_orm::{DatabaseConnection, DbErr};
+use sea_orm::DbErr;
 
 use crate::{
-    database::{
-        get_all_character_by_ilvl, get_single_character, insert_lobby_player, remove_lobby_player,
-    },
+    commands::selected_value,
     info::*,
+    locale::{interaction_locale, tr, tr_with},
+    metrics,
+    router::RoutedInteraction,
+    storage::Storage,
     Error, EventComponent,
 };
 
 use super::context::LobbyContext;
 
+/// Discord doesn't allow more options in a select menu.
+pub const SELECT_MENU_LIMIT: usize = 25;
+
 pub trait AddOption {
-    fn option<V: ToString>(&self, val: V) -> CreateSelectMenuOption;
+    fn option<V: ToString>(&self, val: V, locale: &str) -> CreateSelectMenuOption;
 }
 
 impl AddOption for entity::characters::Model {
     // TODO! Add user name to description
-    fn option<V: ToString>(&self, val: V) -> CreateSelectMenuOption {
+    fn option<V: ToString>(&self, val: V, locale: &str) -> CreateSelectMenuOption {
         let mut option = CreateSelectMenuOption::default();
         option
             .label(&self.name)
-            .description(format!("{:<15} -> {:<5} ilvl", self.class, self.item_level))
+            .description(character_description(self, locale))
             .value(val);
         option
     }
 }
 
-#[derive(Display, Clone, Copy)]
-pub enum LobbyContent {
-    #[display("Guardian Raid")]
-    GuardianRaid,
-    #[display("Abyss Dungeon")]
-    AbyssDungeon,
-    #[display("Abyss Raid")]
-    AbyssRaid,
+/// Class and item level of the character, aligned for select menus.
+pub fn character_description(character: &entity::characters::Model, locale: &str) -> String {
+    tr_with(
+        locale,
+        "character.option",
+        &[
+            ("class", &format!("{:<15}", character.class)),
+            ("ilvl", &format!("{:<5}", character.item_level)),
+        ],
+    )
 }
 
-impl From<&str> for LobbyContent {
-    fn from(val: &str) -> Self {
-        match val {
-            "guardian-raid" => Self::GuardianRaid,
-            "abyss-dungeon" => Self::AbyssDungeon,
-            "abyss-raid" => Self::AbyssRaid,
-            _ => unreachable!(),
-        }
+/// Category of the lobby content, read from the category file of the catalog.
+#[derive(Clone)]
+pub struct LobbyContent {
+    pub category: Arc<Category>,
+}
+
+impl fmt::Display for LobbyContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.category.name)
+    }
+}
+
+impl TryFrom<&str> for LobbyContent {
+    type Error = Error;
+
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        let category = if val == CUSTOM_CONTENT_TYPE {
+            Some(Arc::new(Category::custom()))
+        } else {
+            contents().category(val)
+        };
+
+        category
+            .map(|category| Self { category })
+            .ok_or_else(|| Error::parse(format!("Unknown content type: {val}")))
     }
 }
 
 impl LobbyContent {
-    pub fn get_content_list(&self) -> &Vec<Content> {
-        match self {
-            LobbyContent::GuardianRaid => &*GUARDIAN_RAIDS,
-            LobbyContent::AbyssDungeon => &*ABYSS_DUNGEONS,
-            LobbyContent::AbyssRaid => &*ABYSS_RAIDS,
+    /// Name of the category in the locale.
+    pub fn name(&self, locale: &str) -> &str {
+        self.category.name_for(locale)
+    }
+
+    /// `type` of the content files in this category.
+    pub fn content_type(&self) -> &str {
+        &self.category.content_type
+    }
+
+    pub fn button_style(&self) -> serenity::ButtonStyle {
+        match self.category.button.as_str() {
+            "secondary" => serenity::ButtonStyle::Secondary,
+            "success" => serenity::ButtonStyle::Success,
+            "danger" => serenity::ButtonStyle::Danger,
+            _ => serenity::ButtonStyle::Primary,
+        }
+    }
+
+    /// Categories a lobby can be created for in the guild. Custom contents are the last one.
+    pub async fn available(guild_id: u64, db: &dyn Storage) -> Result<Vec<Self>, DbErr> {
+        let mut categories: Vec<_> = contents()
+            .categories()
+            .into_iter()
+            .map(|category| Self { category })
+            .collect();
+        if !db.get_custom_contents(guild_id).await?.is_empty() {
+            categories.push(Self {
+                category: Arc::new(Category::custom()),
+            });
+        }
+        Ok(categories)
+    }
+
+    /// Contents of the category, custom contents are the ones of the guild.
+    pub async fn contents(
+        &self,
+        guild_id: u64,
+        db: &dyn Storage,
+    ) -> Result<Vec<Arc<ContentInfo>>, DbErr> {
+        if self.content_type() == CUSTOM_CONTENT_TYPE {
+            Ok(db
+                .get_custom_contents(guild_id)
+                .await?
+                .iter()
+                .map(|custom_content| Arc::new(ContentInfo::from_custom(custom_content)))
+                .collect())
+        } else {
+            Ok(contents().of_type(self.content_type()))
+        }
+    }
+}
+
+/// Finds the content of a lobby in the catalog or in the custom contents of the guilds.
+pub async fn find_content(key: &str, db: &dyn Storage) -> Result<Arc<ContentInfo>, Error> {
+    match custom_content_id(key) {
+        Some(content_id) => {
+            let custom_content = db.get_custom_content(content_id).await?;
+            Ok(Arc::new(ContentInfo::from_custom(&custom_content)))
         }
+        None => contents()
+            .get(key)
+            .ok_or_else(|| Error::parse(format!("Unknown content: {key}"))),
+    }
+}
+
+/// Parses the lobby time using the guild time zone if no time zone is specified.
+pub fn parse_lobby_time(lobby_time: &str, timezone: i32) -> Option<DateTime<Utc>> {
+    let offset = chrono::offset::FixedOffset::east(timezone * 3600);
+    dateparser::parse_with_timezone(lobby_time, &offset).ok()
+}
+
+/// Returns the message key of the reason if the lobby time can't be used.
+pub fn check_lobby_time(lobby_time: DateTime<Utc>) -> Result<(), &'static str> {
+    if lobby_time <= (Utc::now() + chrono::Duration::minutes(15)) {
+        Err("lobby.time_too_soon")
+    } else if lobby_time >= (Utc::now() + chrono::Duration::weeks(2)) {
+        Err("lobby.time_too_late")
+    } else {
+        Ok(())
     }
 }
+
+#[derive(Debug)]
 pub enum LobbyEvent {
     LobbyJoin,
     PlayerJoin,
+    LobbySwap,
+    /// Character of the user to swap is selected.
+    SwapFrom,
+    PlayerSwap,
     LobbyLeave,
 }
 
@@ -75,6 +179,19 @@ pub struct EventParseError {}
 
 impl std::error::Error for EventParseError {}
 
+impl LobbyEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::LobbyJoin => "lobby-join",
+            Self::PlayerJoin => "player-join",
+            Self::LobbySwap => "lobby-swap",
+            Self::SwapFrom => "swap-from",
+            Self::PlayerSwap => "player-swap",
+            Self::LobbyLeave => "lobby-leave",
+        }
+    }
+}
+
 impl TryFrom<&str> for LobbyEvent {
     type Error = EventParseError;
 
@@ -82,65 +199,178 @@ impl TryFrom<&str> for LobbyEvent {
         match value {
             "lobby-join" => Ok(Self::LobbyJoin),
             "player-join" => Ok(Self::PlayerJoin),
+            "lobby-swap" => Ok(Self::LobbySwap),
+            "swap-from" => Ok(Self::SwapFrom),
+            "player-swap" => Ok(Self::PlayerSwap),
             "lobby-leave" => Ok(Self::LobbyLeave),
             _ => Err(EventParseError {}),
         }
     }
 }
 
+/// Value of a character swap option. Character names can't have colons.
+fn swap_value(from: &str, to: &str) -> String {
+    format!("{from}:{to}")
+}
+
+/// Returns the names of the character in the lobby and the one replacing it.
+fn parse_swap_value(value: &str) -> Result<(&str, &str), Error> {
+    value
+        .split_once(':')
+        .ok_or_else(|| Error::parse(format!("Invalid character swap: {value}")))
+}
+
+/// Responds with the select menu of the characters of the user that can replace `from`.
+async fn respond_swap_menu(
+    mci: &serenity::MessageComponentInteraction,
+    http_client: &serenity::http::client::Http,
+    kind: serenity::InteractionResponseType,
+    lobby_context: &LobbyContext,
+    locale: &str,
+    from: &str,
+    db: &dyn Storage,
+) -> Result<(), Error> {
+    // Other characters of the user that could join the lobby
+    let user_chars = match db
+        .get_all_character_by_ilvl(
+            mci.user.id.0,
+            lobby_context.guild_id,
+            lobby_context.ilvl_req(),
+        )
+        .await
+    {
+        Ok(user_chars) => user_chars,
+        Err(DbErr::RecordNotFound(_)) => vec![],
+        Err(err) => return Err(err.into()),
+    };
+    let user_chars = lobby_context
+        .requirements
+        .with_enough_clears(
+            user_chars,
+            lobby_context.guild_id,
+            &lobby_context.content_info().key,
+            db,
+        )
+        .await?;
+    let busy = lobby_context.busy_characters(db).await?;
+    let user_chars: Vec<_> = user_chars
+        .into_iter()
+        .filter(|char| !busy.contains(&char.name) && !lobby_context.is_active_character(&char.name))
+        .collect();
+
+    if user_chars.is_empty() {
+        mci.create_interaction_response(http_client, |r| {
+            r.kind(kind).interaction_response_data(|d| {
+                d.flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                    .embed(|e| e.description(tr(locale, "lobby.no_swappable_characters")))
+                    .components(|c| c)
+            })
+        })
+        .await?;
+        return Ok(());
+    }
+
+    mci.create_interaction_response(http_client, |r| {
+        r.kind(kind).interaction_response_data(|d| {
+            d.flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                .embed(|e| {
+                    e.description(tr_with(locale, "lobby.select_swap", &[("name", &from)]))
+                        .title(&lobby_context.content_info().name)
+                })
+                .components(|c| {
+                    c.create_action_row(|r| {
+                        r.create_select_menu(|m| {
+                            m.custom_id(lobby_context.component_id("player-swap"))
+                                .options(|o| {
+                                    // Characters are sorted by item level, the best ones are kept
+                                    for char in user_chars.iter().take(SELECT_MENU_LIMIT) {
+                                        o.add_option(char.option(swap_value(from, &char.name)));
+                                    }
+                                    o
+                                })
+                        })
+                    })
+                })
+        })
+    })
+    .await?;
+    Ok(())
+}
+
+/// Processes the event and reports any error to the user so the lobby task keeps running.
+#[tracing::instrument(
+    skip_all,
+    fields(
+        lobby_id = %lobby_context_locked.read().id,
+        guild_id = ?event_c.message_component_interaction.guild_id,
+        user_id = %event_c.message_component_interaction.user.id,
+        event = ?event_c.event,
+    )
+)]
+pub async fn handle_lobby_event(
+    event_c: EventComponent,
+    lobby_context_locked: Arc<RwLock<LobbyContext>>,
+    db: &dyn Storage,
+) {
+    let mci = event_c.message_component_interaction.clone();
+    let http_client = event_c.http_client.clone();
+    metrics::lobby_event_processed(event_c.event.name());
+
+    if let Err(err) = process_lobby_event(event_c, lobby_context_locked, db).await {
+        err.report_interaction(RoutedInteraction::Component(&mci), &http_client)
+            .await;
+    }
+}
+
 pub async fn process_lobby_event(
     event_c: EventComponent,
     lobby_context_locked: Arc<RwLock<LobbyContext>>,
-    db: &DatabaseConnection,
+    db: &dyn Storage,
 ) -> Result<(), Error> {
     match event_c.event {
         LobbyEvent::LobbyJoin => {
             let lobby_context = lobby_context_locked.read();
             let mci = event_c.message_component_interaction;
             let http_client = event_c.http_client;
+            let locale = interaction_locale(&mci.locale, &lobby_context.locale);
 
             // Check if lobby is full
             if lobby_context.content_info().content_size == lobby_context.active_players.len() {
                 mci.create_interaction_response(&http_client, |m| {
                     m.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
                         .interaction_response_data(|d| {
-                            d.embed(|e| {
-                                e.description(
-                                    "Lobby is already full. Wait for someone else to leave.",
-                                )
-                            })
-                            .flags(
+                            d.embed(|e| e.description(tr(&locale, "lobby.full"))).flags(
                                 serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
                             )
                         })
                 })
-                .await
-                .expect("Couldn't send message");
+                .await?;
                 return Ok(());
             }
 
-            // If user is already is an active player
-            if lobby_context.is_active_player(mci.user.id.0) {
+            // If user is already is an active player and can't join with another character
+            if lobby_context.settings.single_character
+                && lobby_context.is_active_player(mci.user.id.0)
+            {
                 mci.create_interaction_response(&http_client, |m| {
                     m.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
                         .interaction_response_data(|d| {
-                            d.embed(|e| e.description("You are already in the lobby dumbass."))
+                            d.embed(|e| e.description(tr(&locale, "lobby.already_joined")))
                                 .flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
                         })
                 })
-                .await
-                .expect("Couldn't send message");
+                .await?;
                 return Ok(());
             }
 
             // Get current user chars
-            let user_chars = get_all_character_by_ilvl(
-                mci.user.id.0,
-                lobby_context.guild_id,
-                lobby_context.content_info().ilvl_req,
-                db,
-            )
-            .await;
+            let user_chars = db
+                .get_all_character_by_ilvl(
+                    mci.user.id.0,
+                    lobby_context.guild_id,
+                    lobby_context.ilvl_req(),
+                )
+                .await;
 
             match user_chars {
                 Err(DbErr::RecordNotFound(_)) => {
@@ -148,32 +378,78 @@ pub async fn process_lobby_event(
                         m.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
                         .interaction_response_data(|d| {
                             d.embed(|e| {
-                                e.description("You currently don't have any characters that can join this lobby.")
+                                e.description(tr(&locale, "lobby.no_joinable_characters"))
                             })
                             .flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
                         })
-                    }).await.expect("Couldn't create a response.")
-
+                    }).await?;
                 }
                 Ok(user_chars) => {
+                    let user_chars = lobby_context
+                        .requirements
+                        .with_enough_clears(
+                            user_chars,
+                            lobby_context.guild_id,
+                            &lobby_context.content_info().key,
+                            db,
+                        )
+                        .await?;
+                    if user_chars.is_empty() {
+                        let min_clears = lobby_context.requirements.min_clears.unwrap_or_default();
+                        mci.create_interaction_response(&http_client, |m| {
+                            m.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                                .interaction_response_data(|d| {
+                                    d.embed(|e| {
+                                        e.description(tr_with(
+                                            &locale,
+                                            "lobby.not_enough_clears",
+                                            &[("clears", &min_clears)],
+                                        ))
+                                    })
+                                    .flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                                })
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+
+                    // Characters in the lobby or in another lobby at the same time can't join
+                    let busy = lobby_context.busy_characters(db).await?;
+                    let user_chars: Vec<_> = user_chars
+                        .into_iter()
+                        .filter(|char| {
+                            !busy.contains(&char.name)
+                                && !lobby_context.is_active_character(&char.name)
+                        })
+                        .collect();
+                    if user_chars.is_empty() {
+                        mci.create_interaction_response(&http_client, |m| {
+                            m.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                                .interaction_response_data(|d| {
+                                    d.embed(|e| e.description(tr(&locale, "lobby.characters_busy")))
+                                        .flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                                })
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+
                     mci.create_interaction_response(&http_client, |m| {
                     m.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
                         .interaction_response_data(|d| {
                             d.flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
                             .embed(|e| {
-                                e.description(
-                                    "Please select a character to join the lobby.",
-                                )
+                                e.description(tr(&locale, "lobby.select_character"))
                                 .title(&lobby_context.content_info().name)
                             })
                             .components(|c| {
                                 c.create_action_row(|r| {
                                     r.create_select_menu(|m| {
                                         m.custom_id(
-                                            lobby_context.id_as_string.clone() + "player-join",
+                                            lobby_context.component_id("player-join"),
                                         ).options(|o| {
                                             for char in user_chars {
-                                                o.add_option(char.option(&char.name));
+                                                o.add_option(char.option(&char.name, &locale));
                                             }
                                             o
                                         })
@@ -182,76 +458,352 @@ pub async fn process_lobby_event(
                             })
                         })
                     })
-                    .await
-                    .expect("Couldn't create a response.");
+                    .await?;
                 }
 
-                Err(_) => {
-                    mci.create_interaction_response(&http_client, |r| {
-                        r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
-                        .interaction_response_data(|d| {
-                            d.embed(|e| {
-                                e.description("Database Error! :(")
-                            })
-                        })
-                    }).await.expect("Couldn't create a response.")
-                }
+                Err(err) => return Err(err.into()),
             };
             Ok(())
         }
         LobbyEvent::PlayerJoin => {
             let mci = event_c.message_component_interaction;
             let http_client = event_c.http_client;
-            //
-            let (channel, message_id) = {
+            // Guard is dropped before awaiting the database
+            let (
+                channel,
+                message_id,
+                guild_id,
+                lobby_id,
+                lobby_time,
+                locale,
+                requirements,
+                content,
+                settings,
+            ) = {
                 let lobby_context = lobby_context_locked.read();
                 (
                     serenity::ChannelId(lobby_context.channel_id),
                     lobby_context.message_id,
+                    lobby_context.guild_id,
+                    lobby_context.id,
+                    lobby_context.lobby_time,
+                    interaction_locale(&mci.locale, &lobby_context.locale),
+                    lobby_context.requirements.clone(),
+                    lobby_context.content_info().key.clone(),
+                    lobby_context.settings,
                 )
             };
+            let busy = settings
+                .busy_characters(guild_id, lobby_id, lobby_time, db)
+                .await?;
+
+            let player = db
+                .get_single_character(selected_value(&mci)?, guild_id)
+                .await?;
+
+            // Select menu may be older than the clears and the lobbies of the character
+            let eligible = requirements
+                .with_enough_clears(vec![player.clone()], guild_id, &content, db)
+                .await?;
+            let joinable = {
+                let lobby_context = lobby_context_locked.read();
+                !busy.contains(&player.name)
+                    && !lobby_context.is_active_character(&player.name)
+                    && !(lobby_context.settings.single_character
+                        && lobby_context.is_active_player(mci.user.id.0))
+            };
+            let rejection = if eligible.is_empty() {
+                let min_clears = requirements.min_clears.unwrap_or_default();
+                Some(tr_with(
+                    &locale,
+                    "lobby.not_enough_clears",
+                    &[("clears", &min_clears)],
+                ))
+            } else if !joinable {
+                Some(tr(&locale, "lobby.characters_busy"))
+            } else {
+                None
+            };
+            if let Some(rejection) = rejection {
+                mci.create_interaction_response(&http_client, |r| {
+                    r.kind(serenity::InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.embed(|e| e.description(rejection)).components(|c| c)
+                        })
+                })
+                .await?;
+                return Ok(());
+            }
 
-            let player =
-                get_single_character(mci.data.values[0].as_str(), mci.guild_id.unwrap().0, db)
-                    .await
-                    .expect("Database Error");
+            // Lobby may be filled since the select menu was sent
+            let full = {
+                let lobby_context = lobby_context_locked.read();
+                lobby_context.content_info().content_size == lobby_context.active_players.len()
+            };
+            if full {
+                mci.create_interaction_response(&http_client, |r| {
+                    r.kind(serenity::InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.embed(|e| e.description(tr(&locale, "lobby.full")))
+                                .components(|c| c)
+                        })
+                })
+                .await?;
+                return Ok(());
+            }
 
             // Add character to lobby
-            {
-                insert_lobby_player(&lobby_context_locked.read(), &player, db).await?;
+            let lobby_context = lobby_context_locked.read().clone();
+            db.insert_lobby_player(&lobby_context, &player).await?;
+            let (lobby_embed, lobby_buttons) = {
                 let mut lobby_context = lobby_context_locked.write();
+                lobby_context.add_active_player_by_model(player);
+                (
+                    lobby_context.create_embed(),
+                    lobby_context.create_user_buttons(),
+                )
+            };
+
+            metrics::discord_request(
+                "edit_lobby_message",
+                channel.edit_message(&http_client, message_id, |m| {
+                    m.embed(|e| {
+                        *e = lobby_embed;
+                        e
+                    })
+                    .components(|c| c.set_action_row(lobby_buttons))
+                }),
+            )
+            .await?;
 
-                if lobby_context.content_info().content_size == lobby_context.active_players.len() {
-                    return Ok(());
+            mci.create_interaction_response(&http_client, |r| {
+                r.kind(serenity::InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|d| {
+                        d.flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                            .embed(|e| e.description(tr(&locale, "lobby.character_added")))
+                            .components(|c| c)
+                    })
+            })
+            .await?;
+
+            Ok(())
+        }
+        LobbyEvent::LobbySwap => {
+            // Guard isn't held while awaiting the database
+            let lobby_context = lobby_context_locked.read().clone();
+            let mci = event_c.message_component_interaction;
+            let http_client = event_c.http_client;
+            let locale = interaction_locale(&mci.locale, &lobby_context.locale);
+
+            let user_id = mci.user.id.0.to_string();
+            let joined: Vec<_> = lobby_context
+                .active_players
+                .iter()
+                .filter(|player| player.id == user_id)
+                .cloned()
+                .collect();
+            match joined.as_slice() {
+                [] => {
+                    mci.create_interaction_response(&http_client, |r| {
+                        r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|d| {
+                                d.flags(
+                                    serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
+                                )
+                                .embed(|e| e.description(tr(&locale, "lobby.not_in_lobby")))
+                            })
+                    })
+                    .await?;
+                }
+                [from] => {
+                    respond_swap_menu(
+                        &mci,
+                        &http_client,
+                        serenity::InteractionResponseType::ChannelMessageWithSource,
+                        &lobby_context,
+                        &locale,
+                        &from.name,
+                        db,
+                    )
+                    .await?;
                 }
+                // Users in the lobby with more than one character first choose which one to swap
+                joined => {
+                    mci.create_interaction_response(&http_client, |r| {
+                        r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|d| {
+                                d.flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                                    .embed(|e| {
+                                        e.description(tr(&locale, "lobby.select_swap_from"))
+                                            .title(&lobby_context.content_info().name)
+                                    })
+                                    .components(|c| {
+                                        c.create_action_row(|r| {
+                                            r.create_select_menu(|m| {
+                                                m.custom_id(lobby_context.component_id("swap-from"))
+                                                    .options(|o| {
+                                                        for char in joined {
+                                                            o.add_option(char.option(&char.name, &locale));
+                                                        }
+                                                        o
+                                                    })
+                                            })
+                                        })
+                                    })
+                            })
+                    })
+                    .await?;
+                }
+            }
+            Ok(())
+        }
+        LobbyEvent::SwapFrom => {
+            let lobby_context = lobby_context_locked.read().clone();
+            let mci = event_c.message_component_interaction;
+            let http_client = event_c.http_client;
+            let locale = interaction_locale(&mci.locale, &lobby_context.locale);
+            let from = selected_value(&mci)?;
+
+            // Select menu may be older than the lobby
+            let user_id = mci.user.id.0.to_string();
+            if !lobby_context
+                .active_players
+                .iter()
+                .any(|p| p.name == from && p.id == user_id)
+            {
+                mci.create_interaction_response(&http_client, |r| {
+                    r.kind(serenity::InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.embed(|e| e.description(tr(&locale, "lobby.not_in_lobby")))
+                                .components(|c| c)
+                        })
+                })
+                .await?;
+                return Ok(());
+            }
 
-                lobby_context.add_active_player_by_model(player);
+            respond_swap_menu(
+                &mci,
+                &http_client,
+                serenity::InteractionResponseType::UpdateMessage,
+                &lobby_context,
+                &locale,
+                from,
+                db,
+            )
+            .await?;
+            Ok(())
+        }
+        LobbyEvent::PlayerSwap => {
+            let mci = event_c.message_component_interaction;
+            let http_client = event_c.http_client;
+            let (from, to) = parse_swap_value(selected_value(&mci)?)?;
+
+            // Guard is dropped before awaiting the database
+            let (
+                channel,
+                message_id,
+                guild_id,
+                lobby_id,
+                lobby_time,
+                locale,
+                requirements,
+                content,
+                settings,
+            ) = {
+                let lobby_context = lobby_context_locked.read();
+                (
+                    serenity::ChannelId(lobby_context.channel_id),
+                    lobby_context.message_id,
+                    lobby_context.guild_id,
+                    lobby_context.id,
+                    lobby_context.lobby_time,
+                    interaction_locale(&mci.locale, &lobby_context.locale),
+                    lobby_context.requirements.clone(),
+                    lobby_context.content_info().key.clone(),
+                    lobby_context.settings,
+                )
+            };
+            let busy = settings
+                .busy_characters(guild_id, lobby_id, lobby_time, db)
+                .await?;
 
-                let lobby_embed = lobby_context.create_embed();
-                let lobby_buttons = lobby_context.create_user_buttons();
+            let player = db.get_single_character(to, guild_id).await?;
 
-                channel
-                    .edit_message(&http_client, message_id, |m| {
-                        m.embed(|e| {
-                            *e = lobby_embed;
-                            e
+            // Select menu may be older than the lobby and the clears of the character
+            let eligible = requirements
+                .with_enough_clears(vec![player.clone()], guild_id, &content, db)
+                .await?;
+            let (in_lobby, swappable) = {
+                let lobby_context = lobby_context_locked.read();
+                let user_id = mci.user.id.0.to_string();
+                (
+                    lobby_context
+                        .active_players
+                        .iter()
+                        .any(|p| p.name == from && p.id == user_id),
+                    player.id == user_id
+                        && !busy.contains(&player.name)
+                        && !lobby_context.is_active_character(&player.name),
+                )
+            };
+            let rejection = if !in_lobby {
+                Some(tr(&locale, "lobby.not_in_lobby"))
+            } else if eligible.is_empty() {
+                let min_clears = requirements.min_clears.unwrap_or_default();
+                Some(tr_with(
+                    &locale,
+                    "lobby.not_enough_clears",
+                    &[("clears", &min_clears)],
+                ))
+            } else if !swappable {
+                Some(tr(&locale, "lobby.characters_busy"))
+            } else {
+                None
+            };
+            if let Some(rejection) = rejection {
+                mci.create_interaction_response(&http_client, |r| {
+                    r.kind(serenity::InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.embed(|e| e.description(rejection)).components(|c| c)
                         })
-                        .components(|c| c.set_action_row(lobby_buttons))
-                    })
-                    .await
-                    .expect("Couldn't edit the message");
+                })
+                .await?;
+                return Ok(());
             }
 
+            // Swap the character in its slot
+            let lobby_context = lobby_context_locked.read().clone();
+            db.swap_lobby_player(&lobby_context, from, &player).await?;
+            let (lobby_embed, lobby_buttons) = {
+                let mut lobby_context = lobby_context_locked.write();
+                lobby_context.swap_active_player(from, player);
+                (
+                    lobby_context.create_embed(),
+                    lobby_context.create_user_buttons(),
+                )
+            };
+
+            metrics::discord_request(
+                "edit_lobby_message",
+                channel.edit_message(&http_client, message_id, |m| {
+                    m.embed(|e| {
+                        *e = lobby_embed;
+                        e
+                    })
+                    .components(|c| c.set_action_row(lobby_buttons))
+                }),
+            )
+            .await?;
+
             mci.create_interaction_response(&http_client, |r| {
                 r.kind(serenity::InteractionResponseType::UpdateMessage)
                     .interaction_response_data(|d| {
-                        d.flags(serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
-                            .embed(|e| e.description("Added your character!"))
+                        d.embed(|e| e.description(tr(&locale, "lobby.character_swapped")))
                             .components(|c| c)
                     })
             })
-            .await
-            .expect("Couldn't generate response");
+            .await?;
 
             Ok(())
         }
@@ -259,29 +811,38 @@ pub async fn process_lobby_event(
             let mci = event_c.message_component_interaction;
             let http_client = event_c.http_client;
 
-            let (channel, message_id) = {
+            let (channel, message_id, locale) = {
                 let lobby_context = lobby_context_locked.read();
                 (
                     serenity::ChannelId(lobby_context.channel_id),
                     lobby_context.message_id,
+                    interaction_locale(&mci.locale, &lobby_context.locale),
                 )
             };
 
-            let mut deleted = false;
-
-            let (embed, buttons) = {
-                let mut lobby_context = lobby_context_locked.write();
+            let user_id = mci.user.id.0.to_string();
+            let (lobby_id, joined) = {
+                let lobby_context = lobby_context_locked.read();
+                let joined = lobby_context
+                    .active_players
+                    .iter()
+                    .enumerate()
+                    .find(|(_, char)| char.id == user_id)
+                    .map(|(index, char)| (index, char.name.clone()));
+                (lobby_context.id, joined)
+            };
 
-                let user_id = mci.user.id.0.to_string();
+            let deleted = joined.is_some();
+            if let Some((index, name)) = joined {
+                // Guard isn't held while the player is removed from the database
+                db.remove_lobby_player(lobby_id, &name).await?;
+                lobby_context_locked
+                    .write()
+                    .remove_active_player_without_filter(index);
+            }
 
-                for (index, char) in lobby_context.active_players.iter().enumerate() {
-                    if char.id == user_id {
-                        remove_lobby_player(lobby_context.id, &char.name, db).await?;
-                        lobby_context.remove_active_player_without_filter(index);
-                        deleted = true;
-                        break;
-                    }
-                }
+            let (embed, buttons) = {
+                let lobby_context = lobby_context_locked.read();
                 (
                     lobby_context.create_embed(),
                     lobby_context.create_user_buttons(),
@@ -289,16 +850,17 @@ pub async fn process_lobby_event(
             };
 
             if deleted {
-                channel
-                    .edit_message(&http_client, message_id, |m| {
+                metrics::discord_request(
+                    "edit_lobby_message",
+                    channel.edit_message(&http_client, message_id, |m| {
                         m.embed(|e| {
                             *e = embed;
                             e
                         })
                         .components(|c| c.set_action_row(buttons))
-                    })
-                    .await
-                    .expect("Couldn't edit the message.");
+                    }),
+                )
+                .await?;
 
                 mci.create_interaction_response(&http_client, |r| {
                     r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
@@ -306,11 +868,10 @@ pub async fn process_lobby_event(
                             d.flags(
                                 serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
                             )
-                            .embed(|e| e.description("Removed you from the lobby."))
+                            .embed(|e| e.description(tr(&locale, "lobby.left")))
                         })
                 })
-                .await
-                .expect("Couldn't create response");
+                .await?;
             } else {
                 mci.create_interaction_response(&http_client, |r| {
                     r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
@@ -318,11 +879,10 @@ pub async fn process_lobby_event(
                             d.flags(
                                 serenity::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
                             )
-                            .embed(|e| e.description("You are not in the lobby."))
+                            .embed(|e| e.description(tr(&locale, "lobby.not_in_lobby")))
                         })
                 })
-                .await
-                .expect("Couldn't create response");
+                .await?;
             }
 
             Ok(())
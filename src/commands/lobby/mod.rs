@@ -1,6 +1,11 @@
 pub mod command;
 
 pub mod context;
+pub mod handler;
 pub mod helper;
+pub mod requirements;
+pub mod settings;
+pub mod supervisor;
+pub mod wizard;
 
 use super::*;
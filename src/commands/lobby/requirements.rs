@@ -0,0 +1,132 @@
+//! Requirements of the lobby master for the players of a lobby.
+//!
+//! Minimum clears are checked when a player joins the lobby, the other requirements are
+//! shown on the lobby for the players to follow. Lobby master can add anyone to the lobby.
+use entity::{characters, lobby};
+use sea_orm::DbErr;
+
+use crate::locale::{tr, tr_with};
+use crate::storage::Storage;
+
+/// Engravings more than this are left out.
+pub const MAX_ENGRAVINGS: usize = 5;
+
+/// Values of the rules select menu.
+pub const VOICE_RULE: &str = "voice";
+pub const GOLD_RUN_RULE: &str = "gold-run";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LobbyRequirements {
+    /// Finished lobbies of the same content a character needs to join the lobby.
+    pub min_clears: Option<u32>,
+    pub engravings: Vec<String>,
+    /// Language spoken in the lobby.
+    pub language: Option<String>,
+    /// Players need to join the voice chat.
+    pub voice: bool,
+    /// Only the characters still earning gold from the content should join.
+    pub gold_run: bool,
+}
+
+impl From<&lobby::Model> for LobbyRequirements {
+    fn from(lobby: &lobby::Model) -> Self {
+        Self {
+            min_clears: lobby.min_clears.map(|clears| clears as u32),
+            engravings: lobby
+                .engravings
+                .as_deref()
+                .map(Self::parse_engravings)
+                .unwrap_or_default(),
+            language: lobby.language.clone(),
+            voice: lobby.voice,
+            gold_run: lobby.gold_run,
+        }
+    }
+}
+
+impl LobbyRequirements {
+    /// Engravings separated by commas, empty ones are skipped.
+    pub fn parse_engravings(engravings: &str) -> Vec<String> {
+        engravings
+            .split(',')
+            .map(str::trim)
+            .filter(|engraving| !engraving.is_empty())
+            .take(MAX_ENGRAVINGS)
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Engravings as they are stored in the lobby table.
+    pub fn engravings_text(&self) -> Option<String> {
+        if self.engravings.is_empty() {
+            None
+        } else {
+            Some(self.engravings.join(", "))
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns true if a character with the clears can join the lobby.
+    pub fn allows_clears(&self, clears: usize) -> bool {
+        self.min_clears
+            .map_or(true, |min_clears| clears >= min_clears as usize)
+    }
+
+    /// Characters of the guild with enough clears of the content to join the lobby.
+    pub async fn with_enough_clears(
+        &self,
+        characters: Vec<characters::Model>,
+        guild_id: u64,
+        content: &str,
+        db: &dyn Storage,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        if self.min_clears.is_none() {
+            return Ok(characters);
+        }
+
+        let mut eligible = vec![];
+        for character in characters {
+            let clears = db.count_clears(guild_id, &character.name, content).await?;
+            if self.allows_clears(clears) {
+                eligible.push(character);
+            }
+        }
+        Ok(eligible)
+    }
+
+    /// Lines of the requirements shown on the lobby.
+    pub fn describe(&self, locale: &str) -> String {
+        let mut lines = vec![];
+        if let Some(clears) = self.min_clears {
+            lines.push(tr_with(
+                locale,
+                "lobby.rule_min_clears",
+                &[("clears", &clears)],
+            ));
+        }
+        if let Some(engravings) = self.engravings_text() {
+            lines.push(tr_with(
+                locale,
+                "lobby.rule_engravings",
+                &[("engravings", &engravings)],
+            ));
+        }
+        if let Some(language) = &self.language {
+            lines.push(tr_with(
+                locale,
+                "lobby.rule_language",
+                &[("language", language)],
+            ));
+        }
+        if self.voice {
+            lines.push(tr(locale, "lobby.rule_voice"));
+        }
+        if self.gold_run {
+            lines.push(tr(locale, "lobby.rule_gold_run"));
+        }
+        lines.join("\n")
+    }
+}
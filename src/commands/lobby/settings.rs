@@ -0,0 +1,68 @@
+//! Settings of the server for the characters of the players in the lobbies.
+//!
+//! Lobbies read the settings when they are created or loaded, so running lobbies keep
+//! their settings until the bot restarts.
+use chrono::{DateTime, Utc};
+use entity::servers;
+use hashbrown::HashSet;
+use sea_orm::DbErr;
+use uuid::Uuid;
+
+use crate::storage::Storage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LobbySettings {
+    /// Users can join a lobby with only one of their characters.
+    pub single_character: bool,
+    /// A character can't be in two lobbies scheduled closer than this, 0 allows any.
+    pub conflict_minutes: i32,
+}
+
+impl Default for LobbySettings {
+    fn default() -> Self {
+        Self {
+            single_character: true,
+            conflict_minutes: 60,
+        }
+    }
+}
+
+impl From<&servers::Model> for LobbySettings {
+    fn from(server: &servers::Model) -> Self {
+        Self {
+            single_character: server.single_character,
+            conflict_minutes: server.conflict_minutes,
+        }
+    }
+}
+
+impl LobbySettings {
+    /// Names of the characters in the other lobbies of the guild scheduled too close to the
+    /// time. Lobbies without a time never conflict.
+    pub async fn busy_characters(
+        &self,
+        guild_id: u64,
+        lobby_id: Uuid,
+        lobby_time: Option<DateTime<Utc>>,
+        db: &dyn Storage,
+    ) -> Result<HashSet<String>, DbErr> {
+        let lobby_time = match lobby_time {
+            Some(lobby_time) if self.conflict_minutes > 0 => lobby_time,
+            _ => return Ok(HashSet::new()),
+        };
+
+        let mut busy = HashSet::new();
+        for lobby in db.get_active_guild_lobbies(guild_id).await? {
+            let conflicts = lobby.scheduled.map_or(false, |scheduled| {
+                (scheduled - lobby_time).num_minutes().abs() < i64::from(self.conflict_minutes)
+            });
+            if lobby.lobby_id == lobby_id || !conflicts {
+                continue;
+            }
+            for player in db.get_lobby_players(lobby.lobby_id).await? {
+                busy.insert(player.character_name);
+            }
+        }
+        Ok(busy)
+    }
+}
@@ -0,0 +1,301 @@
+//! Owns the tasks of the posted lobbies.
+//!
+//! Every lobby has a task processing its events from a bounded channel. The supervisor watches
+//! the tasks and if one of them panics, rebuilds the lobby context from the database and starts
+//! a new task in its place.
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::Utc;
+use entity::lobby;
+use hashbrown::HashMap;
+use parking_lot::RwLock;
+use tokio::{
+    sync::mpsc::{channel, error::TrySendError, Receiver, Sender},
+    task::JoinHandle,
+};
+
+use super::{
+    command::State,
+    context::LobbyContext,
+    helper::{find_content, handle_lobby_event},
+    requirements::LobbyRequirements,
+    settings::LobbySettings,
+};
+use crate::{info::ContentOptions, storage::Storage, Error, EventComponent};
+
+/// Number of events that can wait for a lobby task before new ones are rejected.
+pub const LOBBY_CHANNEL_SIZE: usize = 16;
+/// A lobby that keeps crashing is dropped after this many restarts.
+pub const MAX_RESTARTS: u32 = 5;
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+pub struct LobbyHandle {
+    pub sender: Sender<EventComponent>,
+    pub context: Arc<RwLock<LobbyContext>>,
+    // Watcher of the lobby task, finishes after the task
+    task: JoinHandle<()>,
+}
+
+/// Posted message of a lobby.
+pub struct LobbyMessage {
+    pub channel_id: u64,
+    pub message_id: u64,
+    /// Locale of the server the lobby is posted in.
+    pub locale: String,
+}
+
+pub struct LobbySupervisor {
+    db: &'static dyn Storage,
+    // Hashmap to store lobby ids with their task's channel handle
+    lobbies: RwLock<HashMap<String, LobbyHandle>>,
+    draining: AtomicBool,
+}
+
+impl LobbySupervisor {
+    /// Starts the tasks of the active lobbies in the database.
+    pub async fn start(db: &'static dyn Storage) -> Result<Arc<Self>, Error> {
+        let supervisor = Arc::new(Self {
+            db,
+            lobbies: RwLock::new(HashMap::new()),
+            draining: AtomicBool::new(false),
+        });
+
+        let active_lobbies = db.get_active_lobbies().await?;
+        for lobby in active_lobbies {
+            if let Some(time) = lobby.scheduled {
+                if time <= Utc::now() {
+                    db.disable_lobby(&lobby).await?;
+                    continue;
+                } else if time <= Utc::now() + chrono::Duration::minutes(10) {
+                    // Lobby is still joinable until its time, players see the time on the lobby
+                    tracing::info!(lobby_id = %lobby.lobby_id, %time, "Lobby starts soon");
+                }
+            }
+
+            let lobby_context = match load_lobby_context(&lobby, db).await {
+                Ok(lobby_context) => lobby_context,
+                Err(err) => {
+                    // Content of the lobby may be removed from the catalog since it was posted
+                    tracing::error!(lobby_id = %lobby.lobby_id, "Couldn't load lobby, disabling it: {err}");
+                    if let Err(err) = db.disable_lobby(&lobby).await {
+                        tracing::error!(lobby_id = %lobby.lobby_id, "Couldn't disable lobby: {err}");
+                    }
+                    continue;
+                }
+            };
+            tracing::info!(lobby_id = %lobby.lobby_id, "Started listening lobby");
+            supervisor.spawn(Arc::new(RwLock::new(lobby_context)));
+        }
+
+        Ok(supervisor)
+    }
+
+    /// Starts the task of a posted lobby.
+    pub fn spawn(self: &Arc<Self>, lobby_context_locked: Arc<RwLock<LobbyContext>>) {
+        self.spawn_with_restarts(lobby_context_locked, 0);
+    }
+
+    /// Queues the event to the task of the lobby.
+    ///
+    /// Returns the event back if the lobby is not active or its queue is full.
+    pub fn send(
+        &self,
+        lobby_id: &str,
+        event_c: EventComponent,
+    ) -> Result<(), TrySendError<EventComponent>> {
+        match self.lobbies.read().get(lobby_id) {
+            Some(handle) => handle.sender.try_send(event_c),
+            None => Err(TrySendError::Closed(event_c)),
+        }
+    }
+
+    pub fn context(&self, lobby_id: &str) -> Option<Arc<RwLock<LobbyContext>>> {
+        self.lobbies
+            .read()
+            .get(lobby_id)
+            .map(|handle| handle.context.clone())
+    }
+
+    pub fn active_lobbies(&self) -> usize {
+        self.lobbies.read().len()
+    }
+
+    /// Number of events waiting in the channels of the lobby tasks.
+    pub fn queue_depth(&self) -> usize {
+        self.lobbies
+            .read()
+            .values()
+            .map(|handle| LOBBY_CHANNEL_SIZE - handle.sender.capacity())
+            .sum()
+    }
+
+    /// Messages of the posted lobbies.
+    pub fn lobby_messages(&self) -> Vec<LobbyMessage> {
+        self.lobbies
+            .read()
+            .values()
+            .map(|handle| {
+                let lobby_context = handle.context.read();
+                LobbyMessage {
+                    channel_id: lobby_context.channel_id,
+                    message_id: lobby_context.message_id,
+                    locale: lobby_context.locale.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Closes the lobby channels and waits until the queued events are processed.
+    ///
+    /// New events are rejected and crashed tasks are not restarted after this is called.
+    pub async fn drain(&self, timeout: Duration) {
+        self.draining.store(true, Ordering::SeqCst);
+
+        let handles: Vec<LobbyHandle> = self.lobbies.write().drain().map(|(_, h)| h).collect();
+        let tasks: Vec<JoinHandle<()>> = handles.into_iter().map(|handle| handle.task).collect();
+
+        let drained = tokio::time::timeout(timeout, async {
+            for task in tasks {
+                let _ = task.await;
+            }
+        })
+        .await;
+
+        if drained.is_err() {
+            tracing::warn!("Some lobbies couldn't finish their events in {timeout:?}.");
+        }
+    }
+
+    fn spawn_with_restarts(
+        self: &Arc<Self>,
+        lobby_context_locked: Arc<RwLock<LobbyContext>>,
+        restarts: u32,
+    ) {
+        if self.draining.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let (sender, reciever) = channel(LOBBY_CHANNEL_SIZE);
+        let (lobby_id, lobby_id_string) = {
+            let lobby_context = lobby_context_locked.read();
+            (lobby_context.id, lobby_context.id_as_string.clone())
+        };
+
+        let task = tokio::spawn(run_lobby(reciever, lobby_context_locked.clone(), self.db));
+
+        let supervisor = self.clone();
+        let task = tokio::spawn(async move {
+            // Task returns normally when the lobby is removed and its channel is closed
+            if let Err(err) = task.await {
+                if err.is_panic() && !supervisor.draining.load(Ordering::SeqCst) {
+                    tracing::error!(%lobby_id, "Lobby task panicked, restarting.");
+                    supervisor.restart(lobby_id, restarts + 1).await;
+                }
+            }
+        });
+
+        self.lobbies.write().insert(
+            lobby_id_string,
+            LobbyHandle {
+                sender,
+                context: lobby_context_locked,
+                task,
+            },
+        );
+    }
+
+    async fn restart(self: Arc<Self>, lobby_id: uuid::Uuid, restarts: u32) {
+        let lobby_id_string = lobby_id.to_hyphenated().to_string();
+
+        if restarts > MAX_RESTARTS {
+            tracing::error!(%lobby_id, "Lobby crashed too many times, dropping it.");
+            self.lobbies.write().remove(&lobby_id_string);
+            return;
+        }
+
+        tokio::time::sleep(RESTART_DELAY * restarts).await;
+
+        let lobby = match self.db.get_lobby(lobby_id).await {
+            Ok(lobby) if lobby.active => lobby,
+            Ok(_) => {
+                self.lobbies.write().remove(&lobby_id_string);
+                return;
+            }
+            Err(err) => {
+                tracing::error!(%lobby_id, "Couldn't reload lobby: {err}");
+                self.lobbies.write().remove(&lobby_id_string);
+                return;
+            }
+        };
+
+        match load_lobby_context(&lobby, self.db).await {
+            Ok(lobby_context) => {
+                self.spawn_with_restarts(Arc::new(RwLock::new(lobby_context)), restarts)
+            }
+            Err(err) => {
+                tracing::error!(%lobby_id, "Couldn't reload lobby: {err}");
+                self.lobbies.write().remove(&lobby_id_string);
+            }
+        }
+    }
+}
+
+async fn run_lobby(
+    mut reciever: Receiver<EventComponent>,
+    lobby_context_locked: Arc<RwLock<LobbyContext>>,
+    db: &'static dyn Storage,
+) {
+    while let Some(event_c) = reciever.recv().await {
+        handle_lobby_event(event_c, lobby_context_locked.clone(), db).await;
+    }
+}
+
+/// Builds the context of a posted lobby from its database records.
+pub async fn load_lobby_context(
+    lobby: &lobby::Model,
+    db: &dyn Storage,
+) -> Result<LobbyContext, Error> {
+    let active_players = db.get_active_characters_joined(lobby.lobby_id).await?;
+    let content_info = find_content(&lobby.content, db).await?;
+    let guild_id = lobby.guild_id.parse()?;
+    let content_size = content_info.content_size;
+    let server = db.get_server(guild_id).await?;
+
+    let mut lobby_context = LobbyContext {
+        id: lobby.lobby_id,
+        id_as_string: lobby.lobby_id.to_hyphenated().to_string(),
+        guild_id,
+        channel_id: lobby.channel_id.parse()?,
+        message_id: lobby.message_id.parse()?,
+        lobby_master: lobby.lobby_master.parse()?,
+        locale: server.locale.clone(),
+        state: State::Generated,
+        content: Some(content_info.content_type.as_str().try_into()?),
+        player_list: vec![],
+        content_info: Some(content_info),
+        options: ContentOptions {
+            difficulty: lobby.difficulty.clone(),
+            gate_start: lobby.gate_start,
+            gate_end: lobby.gate_end,
+        },
+        lobby_time: lobby.scheduled,
+        note: lobby.note.clone(),
+        requirements: LobbyRequirements::from(&lobby),
+        settings: LobbySettings::from(&server),
+        players: vec![],
+        active_players: vec![],
+    };
+
+    lobby_context.player_list = vec![lobby_context.empty_slot(); content_size];
+    for char_model in active_players {
+        lobby_context.add_active_player_by_model(char_model);
+    }
+
+    Ok(lobby_context)
+}
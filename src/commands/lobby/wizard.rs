@@ -0,0 +1,559 @@
+//! Setup of a lobby before it is posted.
+//!
+//! `/create_lobby` shows the steps of the setup in a single message. Every step has Back,
+//! Details and Cancel buttons and Details opens a modal to edit the time, the note and the
+//! requirements of the lobby. [`apply`] moves the lobby to the next step of an event and [`render`] shows the
+//! step, so the setup can be tested without Discord.
+use std::sync::Arc;
+
+use poise::serenity_prelude::{
+    self as serenity, CreateActionRow, CreateComponents, CreateEmbed, CreateSelectMenu,
+};
+
+use super::command::State;
+use super::context::LobbyContext;
+use super::helper::*;
+use super::requirements::{LobbyRequirements, GOLD_RUN_RULE, VOICE_RULE};
+use crate::component_id::{ComponentId, WIZARD};
+use crate::info::ContentInfo;
+use crate::locale::{tr, tr_with};
+use crate::storage::Storage;
+use crate::Error;
+
+/// Discord doesn't allow more buttons in an action row.
+const BUTTONS_PER_ROW: usize = 5;
+
+/// Keeps the lobby embed readable.
+pub const MAX_NOTE_LENGTH: u64 = 300;
+const MAX_ENGRAVINGS_LENGTH: u64 = 100;
+const MAX_LANGUAGE_LENGTH: u64 = 30;
+
+/// Custom ids of the inputs of the details modal.
+const TIME_INPUT: &str = "time";
+const NOTE_INPUT: &str = "note";
+const MIN_CLEARS_INPUT: &str = "min-clears";
+const ENGRAVINGS_INPUT: &str = "engravings";
+const LANGUAGE_INPUT: &str = "language";
+
+/// Inputs of the submitted details modal. Empty inputs clear their values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DetailsInput {
+    pub time: String,
+    pub note: String,
+    pub min_clears: String,
+    pub engravings: String,
+    pub language: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WizardEvent {
+    /// Category with the `type` of its contents is selected.
+    Category(String),
+    ContentPage(usize),
+    /// Content with the key is selected.
+    Content(String),
+    Difficulty(String),
+    GateStart(i32),
+    GateEnd(i32),
+    /// Selected difficulty and gates are confirmed.
+    Continue,
+    /// Index of the character in the players to add.
+    AddPlayer(usize),
+    /// Index of the character in the active players to remove.
+    RemovePlayer(usize),
+    /// Lobby is posted without the join buttons.
+    Post,
+    /// Lobby is posted with the join buttons.
+    Open,
+    Back,
+    Cancel,
+    /// Details button is pressed.
+    EditDetails,
+    /// Details modal is submitted.
+    Details(DetailsInput),
+    /// Values of the selected rules.
+    Rules(Vec<String>),
+}
+
+impl WizardEvent {
+    /// Event of a component of the wizard with the selected values of the select menus.
+    pub fn parse(component_id: &ComponentId, values: &[String]) -> Result<Self, Error> {
+        let arg = component_id.arg.as_deref().unwrap_or_default();
+        let event = match component_id.action.as_str() {
+            "category" => Self::Category(arg.to_string()),
+            "content-page" => Self::ContentPage(arg.parse()?),
+            "select-content" => Self::Content(first_value(values)?.to_string()),
+            "difficulty" => Self::Difficulty(first_value(values)?.to_string()),
+            "gate-start" => Self::GateStart(first_value(values)?.parse()?),
+            "gate-end" => Self::GateEnd(first_value(values)?.parse()?),
+            "continue" => Self::Continue,
+            "add" => Self::AddPlayer(first_value(values)?.parse()?),
+            "remove" => Self::RemovePlayer(first_value(values)?.parse()?),
+            "post-lobby" => Self::Post,
+            "open-lobby" => Self::Open,
+            "back" => Self::Back,
+            "cancel" => Self::Cancel,
+            "details" => Self::EditDetails,
+            "rules" => Self::Rules(values.to_vec()),
+            action => return Err(Error::parse(format!("Unknown lobby action: {action}"))),
+        };
+        Ok(event)
+    }
+
+    /// Event of the submitted details modal.
+    pub fn from_modal(rows: &[serenity::ActionRow]) -> Self {
+        let input = |custom_id: &str| {
+            rows.iter()
+                .flat_map(|row| &row.components)
+                .find_map(|component| match component {
+                    serenity::ActionRowComponent::InputText(input)
+                        if input.custom_id == custom_id =>
+                    {
+                        Some(input.value.trim().to_string())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_default()
+        };
+
+        Self::Details(DetailsInput {
+            time: input(TIME_INPUT),
+            note: input(NOTE_INPUT),
+            min_clears: input(MIN_CLEARS_INPUT),
+            engravings: input(ENGRAVINGS_INPUT),
+            language: input(LANGUAGE_INPUT),
+        })
+    }
+}
+
+fn first_value(values: &[String]) -> Result<&str, Error> {
+    values
+        .first()
+        .map(String::as_str)
+        .ok_or_else(|| Error::parse("Select menu interaction has no values"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Message shows the current step, `page` is the page of the content list.
+    Render { page: usize },
+    /// Details modal is opened.
+    EditDetails,
+    /// Details are not changed, the user is told the reason with the message key.
+    Rejected(&'static str),
+    /// Lobby is set up and posted.
+    Post,
+    /// Lobby is cancelled.
+    Cancel,
+}
+
+/// Applies the event to the lobby and returns what the wizard message should do next.
+///
+/// Events of the components of an older step, like a double click on a button, show the
+/// current step again.
+pub async fn apply(
+    lobby_context: &mut LobbyContext,
+    event: WizardEvent,
+    db: &dyn Storage,
+) -> Result<Transition, Error> {
+    match (lobby_context.state, event) {
+        (_, WizardEvent::Cancel) => return Ok(Transition::Cancel),
+        (_, WizardEvent::EditDetails) => return Ok(Transition::EditDetails),
+        (_, WizardEvent::Details(details)) => {
+            return update_details(lobby_context, details, db).await;
+        }
+        (_, WizardEvent::Back) => go_back(lobby_context),
+
+        (State::ContentSelection, WizardEvent::Category(content_type)) => {
+            lobby_context.set_content(Some(LobbyContent::try_from(content_type.as_str())?));
+            lobby_context.state = State::LobbyFirstPrompt;
+        }
+
+        (State::LobbyFirstPrompt, WizardEvent::ContentPage(page)) => {
+            return Ok(Transition::Render { page });
+        }
+        (State::LobbyFirstPrompt, WizardEvent::Content(key)) => {
+            let content_info = find_content(&key, db).await?;
+            lobby_context.set_content_info(Some(content_info));
+
+            if lobby_context.content_info().has_options() {
+                lobby_context.state = State::OptionSelection;
+            } else {
+                collect_players(lobby_context, db).await?;
+            }
+        }
+
+        (State::OptionSelection, WizardEvent::Difficulty(difficulty)) => {
+            lobby_context.options.difficulty = Some(difficulty);
+        }
+        (State::OptionSelection, WizardEvent::GateStart(gate)) => {
+            lobby_context.options.gate_start = Some(gate);
+        }
+        (State::OptionSelection, WizardEvent::GateEnd(gate)) => {
+            lobby_context.options.gate_end = Some(gate);
+        }
+        (State::OptionSelection, WizardEvent::Continue) => {
+            if lobby_context
+                .content_info()
+                .check_options(&lobby_context.options)
+                .is_ok()
+            {
+                collect_players(lobby_context, db).await?;
+            }
+        }
+
+        (State::CollectPlayers, WizardEvent::AddPlayer(idx)) => {
+            if idx < lobby_context.players.len() {
+                lobby_context.add_active_player(idx, db).await;
+            }
+        }
+        (State::CollectPlayers, WizardEvent::RemovePlayer(idx)) => {
+            if idx < lobby_context.active_players.len() {
+                lobby_context.remove_active_player(idx, db).await;
+            }
+        }
+        (State::CollectPlayers, WizardEvent::Rules(rules)) => {
+            lobby_context.requirements.voice = rules.iter().any(|rule| rule == VOICE_RULE);
+            lobby_context.requirements.gold_run = rules.iter().any(|rule| rule == GOLD_RUN_RULE);
+        }
+        (State::CollectPlayers, event @ (WizardEvent::Post | WizardEvent::Open)) => {
+            // Players may have joined another lobby at the same time since they were added
+            if lobby_context.has_busy_players(db).await? {
+                return Ok(Transition::Rejected("lobby.players_busy"));
+            }
+            lobby_context.state = match event {
+                WizardEvent::Post => State::PrivateLobby,
+                _ => State::PublicLobby,
+            };
+            return Ok(Transition::Post);
+        }
+
+        (state, event) => {
+            tracing::debug!(?state, ?event, "Ignored lobby setup event of another step");
+        }
+    }
+
+    Ok(Transition::Render { page: 0 })
+}
+
+/// Moves the lobby to the previous step and forgets what was selected in the current one.
+fn go_back(lobby_context: &mut LobbyContext) {
+    match lobby_context.state {
+        State::LobbyFirstPrompt => {
+            lobby_context.set_content(None);
+            lobby_context.state = State::ContentSelection;
+        }
+        State::OptionSelection => {
+            lobby_context.set_content_info(None);
+            lobby_context.state = State::LobbyFirstPrompt;
+        }
+        State::CollectPlayers => {
+            lobby_context.players = vec![];
+            lobby_context.active_players = vec![];
+            lobby_context.player_list = vec![];
+            if lobby_context.content_info().has_options() {
+                lobby_context.state = State::OptionSelection;
+            } else {
+                lobby_context.set_content_info(None);
+                lobby_context.state = State::LobbyFirstPrompt;
+            }
+        }
+        // First step and the posted lobbies have nothing to go back to
+        _ => {}
+    }
+}
+
+/// Moves the lobby to the player selection with the characters eligible for the content.
+async fn collect_players(lobby_context: &mut LobbyContext, db: &dyn Storage) -> Result<(), Error> {
+    lobby_context.active_players = vec![];
+    lobby_context.players = lobby_context.available_players(db).await?;
+    lobby_context.player_list =
+        vec![lobby_context.empty_slot(); lobby_context.content_info().content_size as usize];
+    lobby_context.state = State::CollectPlayers;
+    Ok(())
+}
+
+async fn update_details(
+    lobby_context: &mut LobbyContext,
+    details: DetailsInput,
+    db: &dyn Storage,
+) -> Result<Transition, Error> {
+    let lobby_time = if details.time.is_empty() {
+        None
+    } else {
+        let timezone = db.get_server(lobby_context.guild_id).await?.timezone;
+        let lobby_time = match parse_lobby_time(&details.time, timezone) {
+            Some(lobby_time) => lobby_time,
+            None => return Ok(Transition::Rejected("lobby.time_parse_error")),
+        };
+        if let Err(reason) = check_lobby_time(lobby_time) {
+            return Ok(Transition::Rejected(reason));
+        }
+        Some(lobby_time)
+    };
+
+    let min_clears = if details.min_clears.is_empty() {
+        None
+    } else {
+        match details.min_clears.parse::<u32>() {
+            Ok(0) => None,
+            Ok(min_clears) => Some(min_clears),
+            Err(_) => return Ok(Transition::Rejected("lobby.invalid_min_clears")),
+        }
+    };
+
+    lobby_context.lobby_time = lobby_time;
+    lobby_context.note = non_empty(details.note);
+    lobby_context.requirements.min_clears = min_clears;
+    lobby_context.requirements.engravings =
+        LobbyRequirements::parse_engravings(&details.engravings);
+    lobby_context.requirements.language = non_empty(details.language);
+    // Characters busy at the new time can't be added anymore
+    if let State::CollectPlayers = lobby_context.state {
+        lobby_context.refresh_players(db).await;
+    }
+    Ok(Transition::Render { page: 0 })
+}
+
+fn non_empty(input: String) -> Option<String> {
+    if input.is_empty() {
+        None
+    } else {
+        Some(input)
+    }
+}
+
+/// Embed and action rows of the current step.
+pub async fn render(
+    lobby_context: &LobbyContext,
+    page: usize,
+    db: &dyn Storage,
+) -> Result<(CreateEmbed, Vec<CreateActionRow>), Error> {
+    let locale = &lobby_context.locale;
+    let mut embed = CreateEmbed::default();
+    let mut rows = vec![];
+    let mut last_row = CreateActionRow::default();
+
+    match lobby_context.state {
+        State::ContentSelection => {
+            embed.title(tr(locale, "lobby.select_category"));
+
+            let categories = LobbyContent::available(lobby_context.guild_id, db).await?;
+            let category_id = ComponentId::new(WIZARD, &lobby_context.id_as_string, "category");
+            // Category file can't have more categories than the rows can fit, see MAX_CATEGORIES
+            for chunk in categories.chunks(BUTTONS_PER_ROW) {
+                let mut row = CreateActionRow::default();
+                for category in chunk {
+                    row.create_button(|b| {
+                        b.style(category.button_style())
+                            .custom_id(category_id.clone().with_arg(category.content_type()))
+                            .label(category.name(locale))
+                    });
+                }
+                rows.push(row);
+            }
+        }
+        State::LobbyFirstPrompt => {
+            let content_list = lobby_context
+                .lobby_content()
+                .contents(lobby_context.guild_id, db)
+                .await?;
+            let category = lobby_context.lobby_content().name(locale);
+            embed.title(tr_with(
+                locale,
+                "lobby.select_content_title",
+                &[("category", &category)],
+            ));
+
+            let pages = (content_list.len() + SELECT_MENU_LIMIT - 1) / SELECT_MENU_LIMIT;
+            let page = page.min(pages.saturating_sub(1));
+            let mut row = CreateActionRow::default();
+            row.add_select_menu(content_menu(lobby_context, &content_list, page));
+            rows.push(row);
+
+            if pages > 1 {
+                let page_id = ComponentId::new(WIZARD, &lobby_context.id_as_string, "content-page");
+                last_row.create_button(|b| {
+                    b.style(serenity::ButtonStyle::Secondary)
+                        .custom_id(page_id.clone().with_arg(page.saturating_sub(1)))
+                        .label(tr(locale, "lobby.previous"))
+                        .disabled(page == 0)
+                });
+                last_row.create_button(|b| {
+                    b.style(serenity::ButtonStyle::Secondary)
+                        .custom_id(page_id.clone().with_arg(page + 1))
+                        .label(tr_with(
+                            locale,
+                            "lobby.next",
+                            &[("page", &(page + 1)), ("pages", &pages)],
+                        ))
+                        .disabled(page + 1 == pages)
+                });
+            }
+        }
+        State::OptionSelection => {
+            embed = lobby_context.create_options_embed();
+
+            let content_info = lobby_context.content_info();
+            if content_info.difficulties.len() > 1 {
+                let mut row = CreateActionRow::default();
+                row.add_select_menu(lobby_context.difficulties_as_options());
+                rows.push(row);
+            }
+            if content_info.gates.len() > 1 {
+                for action in ["gate-start", "gate-end"] {
+                    let mut row = CreateActionRow::default();
+                    row.add_select_menu(lobby_context.gates_as_options(action));
+                    rows.push(row);
+                }
+            }
+            last_row = lobby_context.create_option_buttons();
+        }
+        _ => {
+            embed = lobby_context.create_embed();
+
+            let mut row = CreateActionRow::default();
+            row.add_select_menu(lobby_context.players_as_add_options());
+            rows.push(row);
+            let mut row = CreateActionRow::default();
+            row.add_select_menu(lobby_context.active_players_as_remove_options());
+            rows.push(row);
+            let mut row = CreateActionRow::default();
+            row.add_select_menu(lobby_context.rules_as_options());
+            rows.push(row);
+            last_row = lobby_context.create_lobby_buttons();
+        }
+    }
+
+    add_navigation(lobby_context, &mut last_row);
+    rows.push(last_row);
+    Ok((embed, rows))
+}
+
+fn content_menu(
+    lobby_context: &LobbyContext,
+    content_list: &[Arc<ContentInfo>],
+    page: usize,
+) -> CreateSelectMenu {
+    let locale = &lobby_context.locale;
+    let category = lobby_context.lobby_content().name(locale);
+
+    let mut menu = CreateSelectMenu::default();
+    menu.placeholder(tr_with(
+        locale,
+        "lobby.select_content",
+        &[("category", &category)],
+    ))
+    .options(|o| {
+        for content_info in content_list
+            .iter()
+            .skip(page * SELECT_MENU_LIMIT)
+            .take(SELECT_MENU_LIMIT)
+        {
+            o.create_option(|option| option.label(&content_info.name).value(&content_info.key));
+        }
+        o
+    })
+    .custom_id(lobby_context.wizard_id("select-content"));
+    menu
+}
+
+/// Adds the Back, Details and Cancel buttons of every step to the row.
+fn add_navigation(lobby_context: &LobbyContext, row: &mut CreateActionRow) {
+    let locale = &lobby_context.locale;
+
+    row.create_button(|b| {
+        b.label(tr(locale, "lobby.back"))
+            .style(serenity::ButtonStyle::Secondary)
+            .custom_id(lobby_context.wizard_id("back"))
+            .disabled(matches!(lobby_context.state, State::ContentSelection))
+    });
+    row.create_button(|b| {
+        b.label(tr(locale, "lobby.details"))
+            .style(serenity::ButtonStyle::Secondary)
+            .custom_id(lobby_context.wizard_id("details"))
+    });
+    row.create_button(|b| {
+        b.label(tr(locale, "lobby.cancel"))
+            .style(serenity::ButtonStyle::Danger)
+            .custom_id(lobby_context.wizard_id("cancel"))
+    });
+}
+
+/// Inputs of the details modal, filled with the current details of the lobby.
+pub fn details_inputs(lobby_context: &LobbyContext) -> CreateComponents {
+    let locale = &lobby_context.locale;
+    let requirements = &lobby_context.requirements;
+
+    let mut components = CreateComponents::default();
+    components
+        .create_action_row(|r| {
+            r.create_input_text(|t| {
+                t.custom_id(TIME_INPUT)
+                    .style(serenity::InputTextStyle::Short)
+                    .label(tr(locale, "lobby.details_time"))
+                    .placeholder(tr(locale, "lobby.details_time_example"))
+                    .required(false);
+                if let Some(lobby_time) = lobby_context.lobby_time {
+                    t.value(lobby_time.to_rfc3339());
+                }
+                t
+            })
+        })
+        .create_action_row(|r| {
+            r.create_input_text(|t| {
+                t.custom_id(NOTE_INPUT)
+                    .style(serenity::InputTextStyle::Paragraph)
+                    .label(tr(locale, "lobby.note"))
+                    .placeholder(tr(locale, "lobby.details_note"))
+                    .max_length(MAX_NOTE_LENGTH)
+                    .required(false);
+                if let Some(note) = &lobby_context.note {
+                    t.value(note);
+                }
+                t
+            })
+        })
+        .create_action_row(|r| {
+            r.create_input_text(|t| {
+                t.custom_id(MIN_CLEARS_INPUT)
+                    .style(serenity::InputTextStyle::Short)
+                    .label(tr(locale, "lobby.details_min_clears"))
+                    .placeholder("3")
+                    .max_length(3)
+                    .required(false);
+                if let Some(min_clears) = requirements.min_clears {
+                    t.value(min_clears);
+                }
+                t
+            })
+        })
+        .create_action_row(|r| {
+            r.create_input_text(|t| {
+                t.custom_id(ENGRAVINGS_INPUT)
+                    .style(serenity::InputTextStyle::Short)
+                    .label(tr(locale, "lobby.details_engravings"))
+                    .placeholder(tr(locale, "lobby.details_engravings_example"))
+                    .max_length(MAX_ENGRAVINGS_LENGTH)
+                    .required(false);
+                if let Some(engravings) = requirements.engravings_text() {
+                    t.value(engravings);
+                }
+                t
+            })
+        })
+        .create_action_row(|r| {
+            r.create_input_text(|t| {
+                t.custom_id(LANGUAGE_INPUT)
+                    .style(serenity::InputTextStyle::Short)
+                    .label(tr(locale, "lobby.details_language"))
+                    .max_length(MAX_LANGUAGE_LENGTH)
+                    .required(false);
+                if let Some(language) = &requirements.language {
+                    t.value(language);
+                }
+                t
+            })
+        });
+    components
+}
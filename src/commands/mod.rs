@@ -1,9 +1,24 @@
 use crate::database::*;
+use crate::storage::Storage;
 use poise::serenity_prelude as serenity;
+#[cfg(feature = "api")]
+pub mod api;
+pub mod backup;
 pub mod characters;
+pub mod content;
 pub mod lobby;
 pub mod register;
+pub mod webhook;
 
 pub use crate::Context;
 pub use crate::Data;
 pub use crate::Error;
+
+/// Returns the first selected value of a select menu interaction.
+pub fn selected_value(mci: &serenity::MessageComponentInteraction) -> Result<&str, Error> {
+    mci.data
+        .values
+        .first()
+        .map(String::as_str)
+        .ok_or_else(|| Error::parse("Select menu interaction has no values"))
+}
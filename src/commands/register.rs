@@ -1,6 +1,13 @@
 use entity::sea_orm_active_enums::Role;
+use futures::Stream;
 
-use super::*;
+use crate::{
+    check::{guild_id, is_guild_init},
+    commands::lobby::settings::LobbySettings,
+    locale::{tr, tr_with, user_locale, LOCALES},
+};
+
+use super::{backup::confirm_with_archive, *};
 
 #[poise::command(
     prefix_command,
@@ -8,42 +15,40 @@ use super::*;
     hide_in_help,
     required_permissions = "ADMINISTRATOR"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
 pub async fn register_guild(
     ctx: Context<'_>,
     #[description = "Name of the guild"] name: String,
     #[description = "Timezone of the guild"]
     #[min = -23]
     #[max = 23]
-    utc_timezone: i32,
+    utc_timezone: Option<i32>,
 ) -> Result<(), Error> {
     let db = &ctx.data().db;
+    let guild_id = guild_id(ctx)?;
+    let locale = user_locale(ctx).await;
+    let utc_timezone = utc_timezone.unwrap_or(ctx.data().config.defaults.timezone);
 
-    if (get_server(ctx.guild_id().expect("No guild id").0, db).await).is_ok() {
-        remove_server(ctx.guild_id().expect("No guild id").0, db)
-            .await
-            .expect("Failed to remove server");
+    // Registering again deletes every guildmate, character and lobby of the server
+    if (db.get_server(guild_id).await).is_ok() {
+        let snapshot = db.export_guild(guild_id).await?;
+        let prompt = tr(&locale, "register.confirm");
+        if !confirm_with_archive(ctx, &prompt, &snapshot).await? {
+            return Ok(());
+        }
+        db.remove_server(guild_id).await?;
     }
 
-    insert_server(
-        ctx.guild_id().expect("No guild id").0,
-        &name,
-        utc_timezone,
-        db,
-    )
-    .await?;
-    insert_guildmate(
-        ctx.guild_id().expect("No guild id").0,
-        ctx.author().id.0,
-        Role::GuildMaster,
-        db,
-    )
-    .await?;
-    ctx.say("Added you as a guildmaster!").await?;
+    db.insert_server(guild_id, &name, utc_timezone).await?;
+    db.insert_guildmate(guild_id, ctx.author().id.0, Role::GuildMaster)
+        .await?;
+    ctx.say(tr(&locale, "register.guild_master")).await?;
 
     Ok(())
 }
 
 #[poise::command(prefix_command, hide_in_help, owners_only)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
 pub async fn register_commands(
     ctx: Context<'_>,
     #[description = "Will be removed"]
@@ -51,9 +56,123 @@ pub async fn register_commands(
     global: bool,
 ) -> Result<(), Error> {
     if let Err(err) = poise::builtins::register_application_commands(ctx, global).await {
-        ctx.say(format!("Failed to register application commands, {err}"))
+        let locale = user_locale(ctx).await;
+        ctx.say(tr_with(
+            &locale,
+            "register.commands_failed",
+            &[("error", &err)],
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn autocomplete_language(
+    _ctx: Context<'_>,
+    _partial: String,
+) -> impl Stream<Item = poise::AutocompleteChoice<String>> {
+    futures::stream::iter(LOCALES.map(|(locale, name)| poise::AutocompleteChoice {
+        name: name.to_string(),
+        value: locale.to_string(),
+    }))
+}
+
+/// Set the language of the lobbies and the messages of the bot
+#[poise::command(
+    slash_command,
+    category = "Guild",
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    check = "is_guild_init"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn language(
+    ctx: Context<'_>,
+    #[description = "Language of the server"]
+    #[autocomplete = "autocomplete_language"]
+    language: String,
+) -> Result<(), Error> {
+    let selected = LOCALES.iter().find(|(locale, name)| {
+        locale.eq_ignore_ascii_case(&language) || name.eq_ignore_ascii_case(&language)
+    });
+    let (locale, name) = match selected {
+        Some(selected) => selected,
+        None => {
+            let languages: Vec<_> = LOCALES.iter().map(|(_, name)| *name).collect();
+            ctx.say(tr_with(
+                &user_locale(ctx).await,
+                "language.unsupported",
+                &[
+                    ("language", &language),
+                    ("languages", &languages.join(", ")),
+                ],
+            ))
             .await?;
+            return Ok(());
+        }
+    };
+
+    ctx.data()
+        .db
+        .update_server_locale(guild_id(ctx)?, locale)
+        .await?;
+    ctx.say(tr_with(locale, "language.set", &[("language", name)]))
+        .await?;
+
+    Ok(())
+}
+
+/// Show or change how players can join the lobbies of the server
+#[poise::command(
+    slash_command,
+    category = "Guild",
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    check = "is_guild_init"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn settings(
+    ctx: Context<'_>,
+    #[description = "Players can join a lobby with only one of their characters"]
+    single_character: Option<bool>,
+    #[description = "Minutes between the lobbies of a character, 0 to allow any"]
+    #[min = 0]
+    #[max = 1440]
+    conflict_minutes: Option<i32>,
+) -> Result<(), Error> {
+    let db = ctx.data().db;
+    let guild_id = guild_id(ctx)?;
+    let locale = user_locale(ctx).await;
+
+    let current = LobbySettings::from(&db.get_server(guild_id).await?);
+    let settings = LobbySettings {
+        single_character: single_character.unwrap_or(current.single_character),
+        conflict_minutes: conflict_minutes.unwrap_or(current.conflict_minutes),
+    };
+    if settings != current {
+        db.update_server_settings(guild_id, &settings).await?;
+    }
+
+    let mut lines = vec![tr(&locale, "settings.title")];
+    lines.push(if settings.single_character {
+        tr(&locale, "settings.single_character")
+    } else {
+        tr(&locale, "settings.multiple_characters")
+    });
+    lines.push(if settings.conflict_minutes > 0 {
+        tr_with(
+            &locale,
+            "settings.conflict_minutes",
+            &[("minutes", &settings.conflict_minutes)],
+        )
+    } else {
+        tr(&locale, "settings.no_conflicts")
+    });
+    if settings != current {
+        lines.push(tr(&locale, "settings.updated"));
     }
+    ctx.say(lines.join("\n")).await?;
 
     Ok(())
 }
@@ -0,0 +1,121 @@
+use crate::{
+    check::{guild_id, is_guild_init},
+    locale::{tr, tr_with, user_locale},
+    webhook::generate_secret,
+};
+use sea_orm::DbErr;
+
+use super::*;
+
+#[poise::command(
+    slash_command,
+    category = "Guild",
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    check = "is_guild_init"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn add_webhook(
+    ctx: Context<'_>,
+    #[description = "URL that will receive the lobby events"] url: String,
+) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    let db = ctx.data().db;
+    let locale = user_locale(ctx).await;
+
+    match reqwest::Url::parse(&url) {
+        Ok(parsed) if parsed.scheme() == "https" || parsed.scheme() == "http" => {}
+        _ => {
+            ctx.send(|m| {
+                m.content(tr(&locale, "webhook.invalid_url"))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let secret = generate_secret();
+    db.insert_webhook(guild_id, &url, &secret).await?;
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr(&locale, "webhook.added_title"))
+                .description(tr_with(&locale, "webhook.added", &[("url", &url)]))
+                .field(
+                    tr(&locale, "webhook.secret"),
+                    format!("```{secret}```"),
+                    false,
+                )
+                .field("\0", tr(&locale, "webhook.signature"), false)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    category = "Guild",
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    check = "is_guild_init"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn remove_webhook(
+    ctx: Context<'_>,
+    #[description = "URL of the webhook"] url: String,
+) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    let locale = user_locale(ctx).await;
+
+    match ctx.data().db.remove_webhook(guild_id, &url).await {
+        Ok(()) => {
+            ctx.send(|m| m.content(tr(&locale, "webhook.removed")).ephemeral(true))
+                .await?;
+        }
+        Err(DbErr::RecordNotFound(_)) => {
+            ctx.send(|m| m.content(tr(&locale, "webhook.not_found")).ephemeral(true))
+                .await?;
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    category = "Guild",
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    check = "is_guild_init"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), user_id = %ctx.author().id))]
+pub async fn list_webhooks(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?.to_string();
+    let webhooks = ctx.data().db.get_webhooks(&guild_id).await?;
+    let locale = user_locale(ctx).await;
+
+    let webhook_list = if webhooks.is_empty() {
+        tr(&locale, "webhook.none")
+    } else {
+        webhooks
+            .iter()
+            .map(|webhook| format!("{}\n", webhook.url))
+            .collect()
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr(&locale, "webhook.list_title"))
+                .description(webhook_list)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
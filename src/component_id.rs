@@ -0,0 +1,132 @@
+//! Custom ids of the message components.
+//!
+//! Every component the bot creates has a custom id in `<namespace>:v<version>:<entity>:<action>[:<arg>]`
+//! format, e.g. `lobby:v1:<lobby id>:lobby-join`. Entity is the id of the thing the component
+//! belongs to (a lobby id, a command id) and action tells what the component does. Argument can
+//! contain `:` since it is the last part.
+//!
+//! Discord only allows custom ids up to 100 characters so keep the actions and arguments short.
+use std::{fmt, str::FromStr};
+
+use parse_display::Display;
+
+pub const VERSION: u32 = 1;
+
+/// Components of the lobby messages. Entity is the lobby id.
+pub const LOBBY: &str = "lobby";
+/// Components of the lobby setup of `/create_lobby`. Entity is the lobby id.
+pub const WIZARD: &str = "wizard";
+/// Components of the character commands. Entity is the command id.
+pub const CHARACTER: &str = "character";
+/// Confirmation buttons of the commands. Entity is the command id.
+pub const CONFIRM: &str = "confirm";
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentIdError {
+    #[display("Malformed component id")]
+    Malformed,
+    #[display("Unsupported component id version: {0}")]
+    UnsupportedVersion(u32),
+}
+
+impl std::error::Error for ComponentIdError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentId {
+    pub namespace: String,
+    pub entity: String,
+    pub action: String,
+    pub arg: Option<String>,
+}
+
+impl ComponentId {
+    pub fn new(namespace: &str, entity: impl ToString, action: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            entity: entity.to_string(),
+            action: action.to_string(),
+            arg: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_arg(mut self, arg: impl ToString) -> Self {
+        self.arg = Some(arg.to_string());
+        self
+    }
+
+    pub fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn decode(custom_id: &str) -> Result<Self, ComponentIdError> {
+        custom_id.parse()
+    }
+
+    /// Returns true if the id belongs to given namespace and entity.
+    pub fn is_for(&self, namespace: &str, entity: &str) -> bool {
+        self.namespace == namespace && self.entity == entity
+    }
+
+    /// Lobby buttons posted before the versioned format were the lobby id followed by the action.
+    fn decode_legacy(custom_id: &str) -> Result<Self, ComponentIdError> {
+        // UUIDv4 length is 36 characters
+        match (custom_id.get(..36), custom_id.get(36..)) {
+            (Some(lobby_id), Some(action))
+                if !action.is_empty() && uuid::Uuid::parse_str(lobby_id).is_ok() =>
+            {
+                Ok(Self::new(LOBBY, lobby_id, action))
+            }
+            _ => Err(ComponentIdError::Malformed),
+        }
+    }
+}
+
+impl fmt::Display for ComponentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:v{}:{}:{}",
+            self.namespace, VERSION, self.entity, self.action
+        )?;
+        if let Some(arg) = &self.arg {
+            write!(f, ":{arg}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ComponentId {
+    type Err = ComponentIdError;
+
+    fn from_str(custom_id: &str) -> Result<Self, Self::Err> {
+        let mut parts = custom_id.splitn(5, ':');
+
+        let (namespace, version, entity, action) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(namespace), Some(version), Some(entity), Some(action)) => {
+                    (namespace, version, entity, action)
+                }
+                _ => return Self::decode_legacy(custom_id),
+            };
+
+        let version = version
+            .strip_prefix('v')
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or(ComponentIdError::Malformed)?;
+        if version != VERSION {
+            return Err(ComponentIdError::UnsupportedVersion(version));
+        }
+
+        if namespace.is_empty() || entity.is_empty() || action.is_empty() {
+            return Err(ComponentIdError::Malformed);
+        }
+
+        Ok(Self {
+            namespace: namespace.to_string(),
+            entity: entity.to_string(),
+            action: action.to_string(),
+            arg: parts.next().map(str::to_string),
+        })
+    }
+}
@@ -0,0 +1,277 @@
+//! Configuration of the bot.
+//!
+//! Settings are read from a TOML file given with `--config <path>` or `ARK_CONFIG`, or from
+//! `config.toml` in the working directory if it exists. Environment variables override the
+//! file, so a `.env` file with only `DISCORD_TOKEN` and `DATABASE_URL` keeps working.
+use std::{
+    fmt,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+
+use crate::logging::DEFAULT_LOG_LEVEL;
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// Setting is missing or has an invalid value.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "Couldn't read {}: {err}", path.display()),
+            ConfigError::Parse(path, err) => write!(f, "Couldn't parse {}: {err}", path.display()),
+            ConfigError::Invalid(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub discord: DiscordConfig,
+    pub database: DatabaseConfig,
+    pub content: ContentConfig,
+    pub log: LogConfig,
+    pub features: FeatureConfig,
+    pub defaults: ServerDefaults,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    pub token: String,
+    pub prefix: String,
+    /// Users allowed to run the owner only commands.
+    pub owners: Vec<u64>,
+}
+
+impl Default for DiscordConfig {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            prefix: "!".to_string(),
+            owners: vec![],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            max_connections: 10,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContentConfig {
+    /// Directory of the content TOML files.
+    pub directory: PathBuf,
+    /// Reloads the contents when the files change.
+    pub watch: bool,
+}
+
+impl Default for ContentConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("./contents"),
+            watch: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: String,
+    pub json: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LOG_LEVEL.to_string(),
+            json: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureConfig {
+    /// Address of the HTTP API. Needs the `api` feature.
+    pub api_addr: Option<SocketAddr>,
+    /// Address of the Prometheus endpoint. Needs the `metrics` feature.
+    pub metrics_addr: Option<SocketAddr>,
+    /// Adds a restart note to the lobby messages while the bot is shutting down.
+    pub lobby_restart_notice: bool,
+}
+
+/// Settings of the servers that didn't set their own.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerDefaults {
+    pub timezone: i32,
+}
+
+impl Config {
+    /// Loads the config file, applies the environment overrides and validates the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match config_path() {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Read(path.to_path_buf(), err))?;
+        toml::from_str(&content).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        if let Some(token) = env("DISCORD_TOKEN") {
+            self.discord.token = token;
+        }
+        if let Some(prefix) = env("COMMAND_PREFIX") {
+            self.discord.prefix = prefix;
+        }
+        if let Some(owners) = env("OWNER_IDS") {
+            self.discord.owners = owners
+                .split(',')
+                .map(|owner| parse_env("OWNER_IDS", owner.trim()))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(url) = env("DATABASE_URL") {
+            self.database.url = url;
+        }
+        if let Some(max_connections) = env("DATABASE_MAX_CONNECTIONS") {
+            self.database.max_connections =
+                parse_env("DATABASE_MAX_CONNECTIONS", &max_connections)?;
+        }
+        if let Some(directory) = env("CONTENT_DIR") {
+            self.content.directory = PathBuf::from(directory);
+        }
+        if env("CONTENT_WATCH").is_some() {
+            self.content.watch = true;
+        }
+        if let Some(level) = env("LOG_LEVEL") {
+            self.log.level = level;
+        }
+        if let Some(format) = env("LOG_FORMAT") {
+            self.log.json = format == "json";
+        }
+        if let Some(api_addr) = env("API_ADDR") {
+            self.features.api_addr = Some(parse_env("API_ADDR", &api_addr)?);
+        }
+        if let Some(metrics_addr) = env("METRICS_ADDR") {
+            self.features.metrics_addr = Some(parse_env("METRICS_ADDR", &metrics_addr)?);
+        }
+        if env("LOBBY_RESTART_NOTICE").is_some() {
+            self.features.lobby_restart_notice = true;
+        }
+        if let Some(timezone) = env("DEFAULT_TIMEZONE") {
+            self.defaults.timezone = parse_env("DEFAULT_TIMEZONE", &timezone)?;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.discord.token.is_empty() {
+            return invalid("Discord token is not set (`discord.token` or `DISCORD_TOKEN`).");
+        }
+        if self.discord.prefix.is_empty() {
+            return invalid("Command prefix can't be empty.");
+        }
+        if self.database.url.is_empty() {
+            return invalid("Database URL is not set (`database.url` or `DATABASE_URL`).");
+        }
+        if self.database.max_connections == 0 {
+            return invalid("Database pool needs at least one connection.");
+        }
+        if !self.content.directory.is_dir() {
+            return invalid(format!(
+                "Content directory {} doesn't exist.",
+                self.content.directory.display()
+            ));
+        }
+        if !(-23..=23).contains(&self.defaults.timezone) {
+            return invalid("Default timezone must be between -23 and 23.");
+        }
+        Ok(())
+    }
+
+    /// Database URL to connect. SQLite database files are created if they don't exist yet.
+    pub fn database_url(&self) -> String {
+        let url = &self.database.url;
+        if url.starts_with("sqlite:") && !url.contains('?') {
+            format!("{url}?mode=rwc")
+        } else {
+            url.clone()
+        }
+    }
+}
+
+/// Path given with `--config` or `ARK_CONFIG`, otherwise `config.toml` if it exists.
+fn config_path() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    if let Some(path) = env("ARK_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    let default_path = PathBuf::from(DEFAULT_CONFIG_PATH);
+    if default_path.exists() {
+        Some(default_path)
+    } else {
+        None
+    }
+}
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn parse_env<T: FromStr>(key: &str, value: &str) -> Result<T, ConfigError>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|err| ConfigError::Invalid(format!("Invalid value for {key} ({value}): {err}")))
+}
+
+fn invalid(msg: impl Into<String>) -> Result<(), ConfigError> {
+    Err(ConfigError::Invalid(msg.into()))
+}
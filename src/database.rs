@@ -1,14 +1,16 @@
-use std::str::FromStr;
-
-use entity::{characters, guildmates, lobby_player, servers};
+use entity::{api_tokens, characters, custom_content, guildmates, lobby_player, servers, webhooks};
 use entity::{lobby, prelude::*};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, ModelTrait,
-    QueryFilter, QueryOrder, Set,
+    sea_query::{Expr, Query},
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
 };
 use uuid::Uuid;
 
-use crate::commands::lobby::context::LobbyContext;
+use crate::commands::lobby::{context::LobbyContext, settings::LobbySettings};
+use crate::locale::DEFAULT_LOCALE;
+use crate::storage::duplicate;
+use crate::webhook::{self, PlayerPayload, WebhookEvent};
 
 use super::*;
 
@@ -18,10 +20,14 @@ pub async fn insert_server(
     server_timezone: i32,
     db: &DatabaseConnection,
 ) -> Result<(), DbErr> {
+    let defaults = LobbySettings::default();
     let new_server = servers::ActiveModel {
         id: Set(server_id.to_string()),
         guild_name: Set(server_name.to_string()),
         timezone: Set(server_timezone),
+        locale: Set(DEFAULT_LOCALE.to_string()),
+        single_character: Set(defaults.single_character),
+        conflict_minutes: Set(defaults.conflict_minutes),
     };
 
     new_server.insert(db).await?;
@@ -78,6 +84,40 @@ pub async fn get_server(server_id: u64, db: &DatabaseConnection) -> Result<serve
         .ok_or_else(|| DbErr::RecordNotFound("Couldn't find server.".to_string()))
 }
 
+/// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+pub async fn update_server_locale(
+    server_id: u64,
+    locale: &str,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let mut server: servers::ActiveModel = get_server(server_id, db).await?.into();
+    server.locale = Set(locale.to_string());
+    server.update(db).await?;
+
+    Ok(())
+}
+
+pub async fn update_server_settings(
+    server_id: u64,
+    settings: &LobbySettings,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let mut server: servers::ActiveModel = get_server(server_id, db).await?.into();
+    server.single_character = Set(settings.single_character);
+    server.conflict_minutes = Set(settings.conflict_minutes);
+    server.update(db).await?;
+
+    Ok(())
+}
+
+/// This might return an empty vec
+pub async fn get_servers(db: &DatabaseConnection) -> Result<Vec<servers::Model>, DbErr> {
+    Servers::find()
+        .order_by_asc(servers::Column::GuildName)
+        .all(db)
+        .await
+}
+
 /// Gets the guildmate from database.
 ///
 /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
@@ -176,6 +216,29 @@ pub async fn get_guildmates_by_min_ilvl(
     }
 }
 
+/// This might return an empty vec
+pub async fn get_guild_characters(
+    guild_id: u64,
+    db: &DatabaseConnection,
+) -> Result<Vec<characters::Model>, DbErr> {
+    Characters::find()
+        .filter(characters::Column::GuildId.eq(guild_id.to_string()))
+        .order_by_desc(characters::Column::ItemLevel)
+        .all(db)
+        .await
+}
+
+/// This might return an empty vec
+pub async fn get_guildmates(
+    guild_id: u64,
+    db: &DatabaseConnection,
+) -> Result<Vec<guildmates::Model>, DbErr> {
+    Guildmates::find()
+        .filter(guildmates::Column::ServerId.eq(guild_id.to_string()))
+        .all(db)
+        .await
+}
+
 /// Adds filter for each player id in Vec
 pub async fn get_guildmates_by_min_ilvl_filter_out(
     guild_id: u64,
@@ -288,31 +351,61 @@ pub async fn insert_lobby(
     lobby_context: &LobbyContext,
     db: &DatabaseConnection,
 ) -> Result<(), DbErr> {
-    let mut content_name_retained = lobby_context.content_info().name.clone();
-
-    // EnumString trait defined as to accept only conflated lowercase &str because
-    // Display names, .toml file names and database names are slightly different
-    // Don't ask why its just bad design.
-    // I did not think this project would grow this much
-    content_name_retained.retain(|c| !c.is_whitespace());
-    let content_name_retained = content_name_retained.to_lowercase();
-
     let lobby = entity::lobby::ActiveModel {
         lobby_id: Set(lobby_context.id),
         guild_id: Set(lobby_context.guild_id.to_string()),
         channel_id: Set(lobby_context.channel_id.to_string()),
         message_id: Set(lobby_context.message_id.to_string()),
         lobby_master: Set(lobby_context.lobby_master.to_string()),
-        content: Set(Content::from_str(content_name_retained.as_str()).unwrap()),
+        content: Set(lobby_context.content_info().key.clone()),
         created: Set(chrono::Utc::now()),
         scheduled: Set(lobby_context.lobby_time),
         active: Set(true),
+        difficulty: Set(lobby_context.options.difficulty.clone()),
+        gate_start: Set(lobby_context.options.gate_start),
+        gate_end: Set(lobby_context.options.gate_end),
+        note: Set(lobby_context.note.clone()),
+        min_clears: Set(lobby_context
+            .requirements
+            .min_clears
+            .map(|clears| clears as i32)),
+        engravings: Set(lobby_context.requirements.engravings_text()),
+        language: Set(lobby_context.requirements.language.clone()),
+        voice: Set(lobby_context.requirements.voice),
+        gold_run: Set(lobby_context.requirements.gold_run),
     };
 
-    lobby.insert(db).await?;
+    let lobby = lobby.insert(db).await?;
+
+    for (player, slot) in lobby_context.active_players.iter().zip(0..) {
+        lobby_player_model(lobby_context, player, slot)
+            .insert(db)
+            .await?;
+    }
 
-    for player in &lobby_context.active_players {
-        insert_lobby_player(lobby_context, player, db).await?;
+    let guild_id = lobby.guild_id.clone();
+    webhook::emit(
+        &guild_id,
+        WebhookEvent::LobbyCreated {
+            players: lobby_context
+                .active_players
+                .iter()
+                .map(PlayerPayload::from)
+                .collect(),
+            lobby,
+        },
+        db,
+    );
+
+    // Lobby master may fill the lobby before posting it
+    if lobby_context.active_players.len() == lobby_context.content_info().content_size {
+        webhook::emit(
+            &guild_id,
+            WebhookEvent::LobbyFull {
+                lobby_id: lobby_context.id,
+            },
+            db,
+        );
     }
 
     Ok(())
@@ -333,6 +426,31 @@ pub async fn get_active_lobbies(db: &DatabaseConnection) -> Result<Vec<lobby::Mo
         .await
 }
 
+/// This might return an empty vec
+pub async fn get_active_guild_lobbies(
+    guild_id: u64,
+    db: &DatabaseConnection,
+) -> Result<Vec<lobby::Model>, DbErr> {
+    Lobby::find()
+        .filter(lobby::Column::GuildId.eq(guild_id.to_string()))
+        .filter(lobby::Column::Active.eq(true))
+        .order_by_asc(lobby::Column::Scheduled)
+        .all(db)
+        .await
+}
+
+/// Returns finished lobbies too. This might return an empty vec
+pub async fn get_guild_lobbies(
+    guild_id: u64,
+    db: &DatabaseConnection,
+) -> Result<Vec<lobby::Model>, DbErr> {
+    Lobby::find()
+        .filter(lobby::Column::GuildId.eq(guild_id.to_string()))
+        .order_by_asc(lobby::Column::Created)
+        .all(db)
+        .await
+}
+
 pub async fn disable_lobby(lobby: &lobby::Model, db: &DatabaseConnection) -> Result<(), DbErr> {
     let mut lobby_a_model: lobby::ActiveModel = lobby.clone().into();
     lobby_a_model.active = Set(false);
@@ -345,23 +463,126 @@ pub async fn disable_lobby(lobby: &lobby::Model, db: &DatabaseConnection) -> Res
         player.update(db).await?;
     }
 
+    webhook::emit(
+        &lobby.guild_id,
+        WebhookEvent::LobbyFinished {
+            lobby_id: lobby.lobby_id,
+        },
+        db,
+    );
+
     Ok(())
 }
 
-pub async fn insert_lobby_player(
-    lobby_context: &LobbyContext,
-    player: &characters::Model,
+pub async fn update_lobby_schedule(
+    lobby_id: Uuid,
+    scheduled: Option<chrono::DateTime<chrono::Utc>>,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let mut lobby: lobby::ActiveModel = get_lobby(lobby_id, db).await?.into();
+    lobby.scheduled = Set(scheduled);
+    let lobby = lobby.update(db).await?;
+
+    webhook::emit(
+        &lobby.guild_id,
+        WebhookEvent::LobbyRescheduled {
+            lobby_id,
+            scheduled,
+        },
+        db,
+    );
+
+    Ok(())
+}
+
+/// Lobby master must be a guildmate of the lobby's guild.
+pub async fn update_lobby_master(
+    lobby_id: Uuid,
+    lobby_master: u64,
     db: &DatabaseConnection,
 ) -> Result<(), DbErr> {
-    let lobby_player = lobby_player::ActiveModel {
+    let lobby = get_lobby(lobby_id, db).await?;
+    Guildmates::find_by_id((lobby_master.to_string(), lobby.guild_id.clone()))
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Couldn't find guildmate.".to_string()))?;
+
+    let mut lobby: lobby::ActiveModel = lobby.into();
+    lobby.lobby_master = Set(lobby_master.to_string());
+    lobby.update(db).await?;
+
+    Ok(())
+}
+
+fn lobby_player_model(
+    lobby_context: &LobbyContext,
+    player: &characters::Model,
+    slot: i32,
+) -> lobby_player::ActiveModel {
+    lobby_player::ActiveModel {
         lobby_id: Set(lobby_context.id),
         guild_id: Set(lobby_context.guild_id.to_string()),
         player_id: Set(player.id.to_string()),
         character_name: Set(player.name.clone()),
         active: Set(true),
-    };
+        slot: Set(slot),
+    }
+}
 
-    lobby_player.insert(db).await?;
+/// Slot after the last player of the lobby. Slots of the players who left are not reused.
+/// First `count` slots not in `taken`, slots of the players who left are filled first.
+pub fn free_slots(taken: &[i32], count: usize) -> Vec<i32> {
+    (0..)
+        .filter(|slot| !taken.contains(slot))
+        .take(count)
+        .collect()
+}
+
+async fn free_lobby_slots(
+    lobby_id: Uuid,
+    count: usize,
+    db: &DatabaseConnection,
+) -> Result<Vec<i32>, DbErr> {
+    let taken: Vec<i32> = LobbyPlayer::find()
+        .filter(lobby_player::Column::LobbyId.eq(lobby_id))
+        .all(db)
+        .await?
+        .iter()
+        .map(|player| player.slot)
+        .collect();
+    Ok(free_slots(&taken, count))
+}
+
+pub async fn insert_lobby_player(
+    lobby_context: &LobbyContext,
+    player: &characters::Model,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let slot = free_lobby_slots(lobby_context.id, 1, db).await?[0];
+    lobby_player_model(lobby_context, player, slot)
+        .insert(db)
+        .await?;
+
+    let guild_id = lobby_context.guild_id.to_string();
+    webhook::emit(
+        &guild_id,
+        WebhookEvent::PlayerJoined {
+            lobby_id: lobby_context.id,
+            player: player.into(),
+        },
+        db,
+    );
+
+    // The player is added to the context after it is inserted
+    if lobby_context.active_players.len() + 1 == lobby_context.content_info().content_size {
+        webhook::emit(
+            &guild_id,
+            WebhookEvent::LobbyFull {
+                lobby_id: lobby_context.id,
+            },
+            db,
+        );
+    }
 
     Ok(())
 }
@@ -371,15 +592,11 @@ pub async fn insert_lobby_players(
     player_list: Vec<characters::Model>,
     db: &DatabaseConnection,
 ) -> Result<(), DbErr> {
+    let slots = free_lobby_slots(lobby_context.id, player_list.len(), db).await?;
     let models: Vec<lobby_player::ActiveModel> = player_list
         .iter()
-        .map(|m| lobby_player::ActiveModel {
-            lobby_id: Set(lobby_context.id),
-            guild_id: Set(lobby_context.guild_id.to_string()),
-            player_id: Set(m.id.to_string()),
-            character_name: Set(m.name.clone()),
-            active: Set(true),
-        })
+        .zip(slots)
+        .map(|(m, slot)| lobby_player_model(lobby_context, m, slot))
         .collect();
 
     LobbyPlayer::insert_many(models).exec(db).await?;
@@ -404,6 +621,7 @@ pub async fn get_lobby_players(
 ) -> Result<Vec<lobby_player::Model>, DbErr> {
     LobbyPlayer::find()
         .filter(lobby_player::Column::LobbyId.eq(lobby_id))
+        .order_by_asc(lobby_player::Column::Slot)
         .all(db)
         .await
 }
@@ -413,23 +631,294 @@ pub async fn remove_lobby_player(
     player_name: &str,
     db: &DatabaseConnection,
 ) -> Result<(), DbErr> {
-    let _ = get_lobby_player(lobby_id, player_name, db)
-        .await?
-        .delete(db)
+    let lobby_player = get_lobby_player(lobby_id, player_name, db).await?;
+    let guild_id = lobby_player.guild_id.clone();
+    let player = PlayerPayload {
+        user_id: lobby_player.player_id.clone(),
+        character_name: lobby_player.character_name.clone(),
+    };
+
+    let slot = lobby_player.slot;
+
+    let _ = lobby_player.delete(db).await?;
+
+    // Players after the removed one move up like they do in the lobby message
+    LobbyPlayer::update_many()
+        .col_expr(
+            lobby_player::Column::Slot,
+            Expr::col(lobby_player::Column::Slot).sub(1),
+        )
+        .filter(lobby_player::Column::LobbyId.eq(lobby_id))
+        .filter(lobby_player::Column::Slot.gt(slot))
+        .exec(db)
+        .await?;
+
+    webhook::emit(&guild_id, WebhookEvent::PlayerLeft { lobby_id, player }, db);
+
+    Ok(())
+}
+
+pub async fn swap_lobby_player(
+    lobby_context: &LobbyContext,
+    player_name: &str,
+    player: &characters::Model,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let lobby_player = get_lobby_player(lobby_context.id, player_name, db).await?;
+    let left = PlayerPayload {
+        user_id: lobby_player.player_id.clone(),
+        character_name: lobby_player.character_name.clone(),
+    };
+
+    // Character name is a part of the primary key, check it instead of relying on the database
+    match get_lobby_player(lobby_context.id, &player.name, db).await {
+        Ok(_) => return Err(duplicate("lobby_player")),
+        Err(DbErr::RecordNotFound(_)) => {}
+        Err(err) => return Err(err),
+    }
+
+    // Row is updated in place so the player keeps the slot
+    LobbyPlayer::update_many()
+        .col_expr(
+            lobby_player::Column::CharacterName,
+            Expr::value(player.name.clone()),
+        )
+        .filter(lobby_player::Column::LobbyId.eq(lobby_context.id))
+        .filter(lobby_player::Column::CharacterName.eq(player_name))
+        .exec(db)
         .await?;
+
+    webhook::emit(
+        &lobby_context.guild_id.to_string(),
+        WebhookEvent::PlayerSwapped {
+            lobby_id: lobby_context.id,
+            left,
+            joined: player.into(),
+        },
+        db,
+    );
+
     Ok(())
 }
 
+/// This might return an empty vec
+pub async fn get_guild_lobby_players(
+    guild_id: u64,
+    db: &DatabaseConnection,
+) -> Result<Vec<lobby_player::Model>, DbErr> {
+    LobbyPlayer::find()
+        .filter(lobby_player::Column::GuildId.eq(guild_id.to_string()))
+        .all(db)
+        .await
+}
+
+/// Deactivates the players left active in finished lobbies, e.g. when the bot stopped while
+/// disabling a lobby. Returns the number of fixed rows.
+pub async fn deactivate_orphaned_lobby_players(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let finished_lobbies = Query::select()
+        .column(lobby::Column::LobbyId)
+        .from(Lobby)
+        .and_where(lobby::Column::Active.eq(false))
+        .to_owned();
+
+    let result = LobbyPlayer::update_many()
+        .col_expr(lobby_player::Column::Active, Expr::value(false))
+        .filter(lobby_player::Column::Active.eq(true))
+        .filter(lobby_player::Column::LobbyId.in_subquery(finished_lobbies))
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected)
+}
+
+/// Number of the finished lobbies of the content the character was in.
+pub async fn count_clears(
+    guild_id: u64,
+    character_name: &str,
+    content: &str,
+    db: &DatabaseConnection,
+) -> Result<usize, DbErr> {
+    let finished_lobbies = Query::select()
+        .column(lobby::Column::LobbyId)
+        .from(Lobby)
+        .and_where(lobby::Column::GuildId.eq(guild_id.to_string()))
+        .and_where(lobby::Column::Content.eq(content))
+        .and_where(lobby::Column::Active.eq(false))
+        .to_owned();
+
+    LobbyPlayer::find()
+        .filter(lobby_player::Column::GuildId.eq(guild_id.to_string()))
+        .filter(lobby_player::Column::CharacterName.eq(character_name))
+        .filter(lobby_player::Column::LobbyId.in_subquery(finished_lobbies))
+        .count(db)
+        .await
+}
+
 pub async fn get_active_characters_joined(
     lobby_id: Uuid,
     db: &DatabaseConnection,
 ) -> Result<Vec<characters::Model>, DbErr> {
-    let chars = Lobby::find()
-        .filter(lobby::Column::LobbyId.eq(lobby_id))
-        .find_also_linked(Characters)
+    let chars = LobbyPlayer::find()
+        .filter(lobby_player::Column::LobbyId.eq(lobby_id))
+        .order_by_asc(lobby_player::Column::Slot)
+        .find_also_related(Characters)
         .all(db)
         .await?
-        .into_iter().flat_map(|m| m.1).collect();
+        .into_iter()
+        .flat_map(|m| m.1)
+        .collect();
 
     Ok(chars)
 }
+
+/// Stores the token hash of the guild, replacing the old one if it exists.
+pub async fn upsert_api_token(
+    guild_id: u64,
+    token_hash: &str,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    match ApiTokens::find_by_id(guild_id.to_string()).one(db).await? {
+        Some(existing_token) => {
+            let mut existing_token: api_tokens::ActiveModel = existing_token.into();
+            existing_token.token_hash = Set(token_hash.to_string());
+            existing_token.created = Set(chrono::Utc::now());
+
+            existing_token.update(db).await?;
+        }
+        None => {
+            let new_token = api_tokens::ActiveModel {
+                guild_id: Set(guild_id.to_string()),
+                token_hash: Set(token_hash.to_string()),
+                created: Set(chrono::Utc::now()),
+            };
+
+            new_token.insert(db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the token record matching the hash.
+///
+/// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+pub async fn get_api_token_by_hash(
+    token_hash: &str,
+    db: &DatabaseConnection,
+) -> Result<api_tokens::Model, DbErr> {
+    ApiTokens::find()
+        .filter(api_tokens::Column::TokenHash.eq(token_hash))
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Couldn't find api token.".to_string()))
+}
+
+/// This might return an empty vec
+pub async fn get_webhooks(
+    guild_id: &str,
+    db: &DatabaseConnection,
+) -> Result<Vec<webhooks::Model>, DbErr> {
+    Webhooks::find()
+        .filter(webhooks::Column::GuildId.eq(guild_id))
+        .order_by_asc(webhooks::Column::Created)
+        .all(db)
+        .await
+}
+
+pub async fn insert_webhook(
+    guild_id: u64,
+    url: &str,
+    secret: &str,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let new_webhook = webhooks::ActiveModel {
+        webhook_id: Set(Uuid::new_v4()),
+        guild_id: Set(guild_id.to_string()),
+        url: Set(url.to_string()),
+        secret: Set(secret.to_string()),
+        created: Set(chrono::Utc::now()),
+    };
+
+    new_webhook.insert(db).await?;
+
+    Ok(())
+}
+
+/// Removes every webhook of the guild with the given url.
+///
+/// Returns [`DbErr::RecordNotFound`] if there was none.
+pub async fn remove_webhook(
+    guild_id: u64,
+    url: &str,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let deleted = Webhooks::delete_many()
+        .filter(webhooks::Column::GuildId.eq(guild_id.to_string()))
+        .filter(webhooks::Column::Url.eq(url))
+        .exec(db)
+        .await?;
+
+    if deleted.rows_affected == 0 {
+        Err(DbErr::RecordNotFound("Couldn't find webhook.".to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+/// This might return an empty vec
+pub async fn get_custom_contents(
+    guild_id: u64,
+    db: &DatabaseConnection,
+) -> Result<Vec<custom_content::Model>, DbErr> {
+    CustomContent::find()
+        .filter(custom_content::Column::GuildId.eq(guild_id.to_string()))
+        .order_by_asc(custom_content::Column::Name)
+        .all(db)
+        .await
+}
+
+/// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+pub async fn get_custom_content(
+    content_id: Uuid,
+    db: &DatabaseConnection,
+) -> Result<custom_content::Model, DbErr> {
+    CustomContent::find_by_id(content_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Couldn't find custom content.".to_string()))
+}
+
+pub async fn insert_custom_content(
+    custom_content: custom_content::Model,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    custom_content.into_active_model().insert(db).await?;
+
+    Ok(())
+}
+
+/// Updates everything except the guild and creation time of the content.
+pub async fn update_custom_content(
+    custom_content: custom_content::Model,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let mut existing: custom_content::ActiveModel =
+        get_custom_content(custom_content.content_id, db)
+            .await?
+            .into();
+    existing.name = Set(custom_content.name);
+    existing.content_size = Set(custom_content.content_size);
+    existing.ilvl_req = Set(custom_content.ilvl_req);
+    existing.description = Set(custom_content.description);
+    existing.image = Set(custom_content.image);
+
+    existing.update(db).await?;
+
+    Ok(())
+}
+
+pub async fn remove_custom_content(content_id: Uuid, db: &DatabaseConnection) -> Result<(), DbErr> {
+    let _ = get_custom_content(content_id, db).await?.delete(db).await?;
+
+    Ok(())
+}
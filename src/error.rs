@@ -0,0 +1,149 @@
+//! Error type of the bot and the handlers reporting them to the users.
+//!
+//! Every reported error gets a short correlation id. The id is shown to the user and printed
+//! together with the full error so admins can find what went wrong from a screenshot.
+use std::fmt;
+
+use poise::serenity_prelude as serenity;
+use sea_orm::DbErr;
+
+use crate::{
+    component_id::ComponentIdError,
+    locale::{interaction_locale, tr, tr_with, user_locale, DEFAULT_LOCALE},
+    metrics,
+    router::RoutedInteraction,
+    Data,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Database(DbErr),
+    Discord(serenity::Error),
+    /// Input from a command or a component couldn't be parsed.
+    Parse(String),
+    /// User is not allowed to do the action. Holds the key of the message shown to the user.
+    Permission(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Database(err) => write!(f, "Database error: {err}"),
+            Error::Discord(err) => write!(f, "Discord error: {err}"),
+            Error::Parse(msg) => write!(f, "Parse error: {msg}"),
+            Error::Permission(msg) => write!(f, "Permission error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Database(err) => Some(err),
+            Error::Discord(err) => Some(err),
+            Error::Parse(_) | Error::Permission(_) => None,
+        }
+    }
+}
+
+impl From<DbErr> for Error {
+    fn from(err: DbErr) -> Self {
+        Self::Database(err)
+    }
+}
+
+impl From<serenity::Error> for Error {
+    fn from(err: serenity::Error) -> Self {
+        Self::Discord(err)
+    }
+}
+
+impl From<ComponentIdError> for Error {
+    fn from(err: ComponentIdError) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(err: std::num::ParseIntError) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
+
+impl Error {
+    pub fn parse(msg: impl Into<String>) -> Self {
+        Self::Parse(msg.into())
+    }
+
+    pub fn permission(key: impl Into<String>) -> Self {
+        Self::Permission(key.into())
+    }
+
+    /// Label of the error in metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Database(_) => "database",
+            Error::Discord(_) => "discord",
+            Error::Parse(_) => "parse",
+            Error::Permission(_) => "permission",
+        }
+    }
+
+    /// Message shown to the user instead of the error itself.
+    pub fn user_message(&self, locale: &str, correlation_id: &str) -> String {
+        let key = match self {
+            Error::Permission(key) => return tr(locale, key),
+            Error::Database(_) => "error.database",
+            Error::Discord(_) => "error.discord",
+            Error::Parse(_) => "error.parse",
+        };
+
+        format!(
+            "{}\n{}",
+            tr(locale, key),
+            tr_with(locale, "error.report", &[("id", &correlation_id)])
+        )
+    }
+
+    /// Prints the error and returns the correlation id for it.
+    fn log(&self, origin: &str) -> String {
+        let correlation_id = uuid::Uuid::new_v4().to_simple().to_string()[..8].to_string();
+        tracing::error!(%correlation_id, origin, error = ?self, "{self}");
+        metrics::error_reported(self.kind());
+        correlation_id
+    }
+
+    /// Reports the error to the user of the interaction.
+    pub async fn report_interaction(
+        &self,
+        interaction: RoutedInteraction<'_>,
+        http: &serenity::http::client::Http,
+    ) {
+        let origin = format!("interaction ({})", interaction.custom_id());
+        let locale = interaction_locale(interaction.locale(), DEFAULT_LOCALE);
+        let message = self.user_message(&locale, &self.log(&origin));
+
+        if let Err(err) = interaction.respond_ephemeral(http, &message).await {
+            tracing::warn!("Couldn't report the error to the user: {err}");
+        }
+    }
+}
+
+/// Error handler of the framework. Command errors are shown to the author as an ephemeral message.
+pub async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    match error {
+        poise::FrameworkError::Command { error, ctx } => {
+            let correlation_id = error.log("command");
+            let message = error.user_message(&user_locale(ctx).await, &correlation_id);
+
+            if let Err(err) = ctx.send(|m| m.content(message).ephemeral(true)).await {
+                tracing::warn!("Couldn't report the error to the user: {err}");
+            }
+        }
+        error => {
+            if let Err(err) = poise::builtins::on_error(error).await {
+                tracing::error!("Error while handling error: {err}");
+            }
+        }
+    }
+}
@@ -1,68 +1,561 @@
-use enum_iterator::IntoEnumIterator;
+use entity::custom_content;
 use hashbrown::HashMap;
-use once_cell::sync::Lazy;
-use serde::Deserialize;
-use std::io::Read;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
 
 use super::*;
+use crate::locale::{self, DEFAULT_LOCALE, LOCALES};
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ContentInfo {
+    /// Stored in the lobbies, must not change once lobbies are created for the content.
+    pub key: String,
     pub name: String,
-    #[serde(rename(deserialize = "type"))]
+    #[serde(rename = "type")]
     pub content_type: String,
     pub tier: u8,
     pub ilvl_req: i32,
     pub content_size: usize,
+    #[serde(default)]
     pub image: String,
+    #[serde(default)]
     pub banner: String,
+    #[serde(default)]
     pub guide: String,
+    #[serde(default)]
     pub gameplay_video: String,
     pub introduction: String,
+    /// Translations of the introduction by locale.
+    #[serde(default)]
+    pub introductions: BTreeMap<String, String>,
+    /// Parts of the content that can be done separately, in order.
+    #[serde(default)]
+    pub gates: Vec<Gate>,
+    /// First one is selected by default.
+    #[serde(default)]
+    pub difficulties: Vec<Difficulty>,
 }
 
-// I love iterators
-pub static GUARDIAN_RAIDS: Lazy<Vec<Content>> = Lazy::new(|| {
-    Content::into_enum_iter()
-        .take_while(|x| *x != Content::DemonBeastCanyon)
-        .collect()
-});
-
-pub static ABYSS_DUNGEONS: Lazy<Vec<Content>> = Lazy::new(|| {
-    Content::into_enum_iter()
-        .skip_while(|x| *x != Content::DemonBeastCanyon)
-        .take_while(|x| *x != Content::Argos1)
-        .collect()
-});
-
-pub static ABYSS_RAIDS: Lazy<Vec<Content>> = Lazy::new(|| {
-    Content::into_enum_iter()
-        .skip_while(|x| *x != Content::Argos1)
-        .collect()
-});
-
-pub static CONTENT_TOML: Lazy<Vec<String>> = Lazy::new(|| {
-    let mut contents = vec![];
-    for content in Content::into_enum_iter() {
-        let mut file = std::fs::File::open(format!("./contents/{}.toml", content)).unwrap();
-        let mut content_toml = String::new();
-        file.read_to_string(&mut content_toml).unwrap();
-        contents.push(content_toml);
-    }
-    contents
-});
-
-pub static CONTENT_DATA: Lazy<HashMap<String, ContentInfo>> = Lazy::new(|| {
-    let mut content_map = HashMap::new();
-    for (index, content) in Content::into_enum_iter().enumerate() {
-        let content_info: ContentInfo = toml::from_str(CONTENT_TOML[index].as_str()).unwrap();
-        content_map.insert(content.to_string(), content_info);
-    }
-    content_map
-});
-
-impl From<entity::sea_orm_active_enums::Content> for &ContentInfo {
-    fn from(content: entity::sea_orm_active_enums::Content) -> Self {
-        CONTENT_DATA.get(&content.to_string()).unwrap()
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Gate {
+    pub name: String,
+    /// Needed on top of the item level of the content.
+    #[serde(default)]
+    pub ilvl_req: i32,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Difficulty {
+    pub name: String,
+    pub ilvl_req: i32,
+}
+
+/// Difficulty and gates a lobby is created for. Gates are numbered from 1.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct ContentOptions {
+    pub difficulty: Option<String>,
+    pub gate_start: Option<i32>,
+    pub gate_end: Option<i32>,
+}
+
+impl ContentInfo {
+    /// Content info of a content defined by a guild with `/content add`.
+    pub fn from_custom(custom_content: &custom_content::Model) -> Self {
+        let image = custom_content.image.clone().unwrap_or_default();
+        Self {
+            key: format!("{CUSTOM_CONTENT_PREFIX}{}", custom_content.content_id),
+            name: custom_content.name.clone(),
+            content_type: CUSTOM_CONTENT_TYPE.to_string(),
+            tier: 0,
+            ilvl_req: custom_content.ilvl_req,
+            content_size: custom_content.content_size as usize,
+            image: image.clone(),
+            banner: image,
+            guide: String::new(),
+            gameplay_video: String::new(),
+            introduction: custom_content.description.clone(),
+            introductions: BTreeMap::new(),
+            gates: vec![],
+            difficulties: vec![],
+        }
+    }
+
+    pub fn is_custom(&self) -> bool {
+        self.content_type == CUSTOM_CONTENT_TYPE
+    }
+
+    /// Introduction in the locale, the English one if it is not translated.
+    pub fn introduction_for(&self, locale: &str) -> &str {
+        self.introductions.get(locale).unwrap_or(&self.introduction)
+    }
+
+    /// Returns true if the lobby master has to choose a difficulty or gates.
+    pub fn has_options(&self) -> bool {
+        self.difficulties.len() > 1 || self.gates.len() > 1
+    }
+
+    /// First difficulty and every gate.
+    pub fn default_options(&self) -> ContentOptions {
+        let (gate_start, gate_end) = if self.gates.is_empty() {
+            (None, None)
+        } else {
+            (Some(1), Some(self.gates.len() as i32))
+        };
+        ContentOptions {
+            difficulty: self.difficulties.first().map(|d| d.name.clone()),
+            gate_start,
+            gate_end,
+        }
+    }
+
+    /// Returns the message key of the reason if the options can't be used with this content.
+    pub fn check_options(&self, options: &ContentOptions) -> Result<(), &'static str> {
+        match &options.difficulty {
+            Some(name) if self.difficulty(name).is_none() => {
+                return Err("lobby.unknown_difficulty")
+            }
+            None if !self.difficulties.is_empty() => return Err("lobby.select_difficulty"),
+            _ => {}
+        }
+
+        match (options.gate_start, options.gate_end) {
+            (None, None) if self.gates.is_empty() => Ok(()),
+            (Some(start), Some(end))
+                if 1 <= start && start <= end && end <= self.gates.len() as i32 =>
+            {
+                Ok(())
+            }
+            _ => Err("lobby.invalid_gates"),
+        }
+    }
+
+    pub fn difficulty(&self, name: &str) -> Option<&Difficulty> {
+        self.difficulties.iter().find(|d| d.name == name)
+    }
+
+    /// Gates in the range of the options.
+    pub fn selected_gates(&self, options: &ContentOptions) -> &[Gate] {
+        match (options.gate_start, options.gate_end) {
+            (Some(start), Some(end)) => self
+                .gates
+                .get((start - 1) as usize..end as usize)
+                .unwrap_or_default(),
+            _ => &[],
+        }
+    }
+
+    /// Highest item level needed by the content, the difficulty and the gates.
+    pub fn ilvl_req_for(&self, options: &ContentOptions) -> i32 {
+        let difficulty = options
+            .difficulty
+            .as_deref()
+            .and_then(|name| self.difficulty(name))
+            .map_or(0, |d| d.ilvl_req);
+        self.selected_gates(options)
+            .iter()
+            .map(|gate| gate.ilvl_req)
+            .chain([self.ilvl_req, difficulty])
+            .max()
+            .unwrap_or(self.ilvl_req)
+    }
+
+    /// Difficulty and gates shown next to the content name, like `Hard, Gate 1 - Gate 2`.
+    pub fn describe_options(&self, options: &ContentOptions) -> Option<String> {
+        let gates = match self.selected_gates(options) {
+            [] => None,
+            [gate] => Some(gate.name.clone()),
+            [first, .., last] => Some(format!("{} - {}", first.name, last.name)),
+        };
+
+        match (options.difficulty.clone(), gates) {
+            (Some(difficulty), Some(gates)) => Some(format!("{difficulty}, {gates}")),
+            (difficulty, gates) => difficulty.or(gates),
+        }
+    }
+}
+
+/// Category of the contents with the same `type`.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Category {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub name: String,
+    /// Translations of the name by locale.
+    #[serde(default)]
+    pub names: BTreeMap<String, String>,
+    /// Style of the category button, one of [`BUTTON_STYLES`].
+    #[serde(default = "default_button")]
+    pub button: String,
+    /// Embed colour of the lobbies as `#rrggbb`.
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CategoryFile {
+    categories: Vec<Category>,
+}
+
+/// Button styles of Discord that can be used for the categories.
+pub const BUTTON_STYLES: [&str; 4] = ["primary", "secondary", "success", "danger"];
+
+fn default_button() -> String {
+    "primary".to_string()
+}
+
+impl Category {
+    /// Category of the contents defined by the guilds.
+    pub fn custom() -> Self {
+        Self {
+            content_type: CUSTOM_CONTENT_TYPE.to_string(),
+            name: locale::tr(DEFAULT_LOCALE, "content.custom_category"),
+            names: LOCALES
+                .iter()
+                .map(|(code, _)| {
+                    (
+                        code.to_string(),
+                        locale::tr(code, "content.custom_category"),
+                    )
+                })
+                .collect(),
+            button: "secondary".to_string(),
+            color: None,
+        }
+    }
+
+    /// Name in the locale, the English one if it is not translated.
+    pub fn name_for(&self, locale: &str) -> &str {
+        self.names.get(locale).unwrap_or(&self.name)
+    }
+
+    pub fn colour(&self) -> Option<u32> {
+        self.color
+            .as_deref()
+            .and_then(|color| color.strip_prefix('#'))
+            .filter(|hex| hex.len() == 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+    }
+}
+
+/// File of the categories in the content directory, listed in the order of their buttons.
+pub const CATEGORY_FILE: &str = "categories.toml";
+
+/// Categories are buttons in four action rows of five in `/create_lobby`, the last row is
+/// kept for the navigation and one button for the custom contents.
+pub const MAX_CATEGORIES: usize = 19;
+
+/// Type of the contents defined by the guilds. Content files can't use it.
+pub const CUSTOM_CONTENT_TYPE: &str = "custom";
+
+/// Custom contents are stored in the lobbies as this prefix followed by their id.
+pub const CUSTOM_CONTENT_PREFIX: &str = "custom:";
+
+/// Returns the id of the custom content if the key belongs to one.
+pub fn custom_content_id(key: &str) -> Option<Uuid> {
+    key.strip_prefix(CUSTOM_CONTENT_PREFIX)
+        .and_then(|id| Uuid::parse_str(id).ok())
+}
+
+/// Directory of the content files. Set from the config before the contents are loaded.
+pub static CONTENT_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+static CONTENTS: OnceCell<RwLock<Arc<ContentRegistry>>> = OnceCell::new();
+
+/// Every problem found in the content files, one per line.
+#[derive(Debug)]
+pub struct ContentError {
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.errors.join("\n"))
+    }
+}
+
+impl std::error::Error for ContentError {}
+
+/// Content infos by their keys and the categories of their types.
+pub struct ContentRegistry {
+    contents: HashMap<String, Arc<ContentInfo>>,
+    categories: Vec<Arc<Category>>,
+}
+
+impl ContentRegistry {
+    /// Reads and validates the category file and every other TOML file in the directory.
+    pub fn load(directory: &Path) -> Result<Self, ContentError> {
+        let mut contents: HashMap<String, Arc<ContentInfo>> = HashMap::new();
+        let category_path = directory.join(CATEGORY_FILE);
+        let categories = load_category_file(&category_path).map_err(|err| ContentError {
+            errors: vec![format!("{}: {err}", category_path.display())],
+        })?;
+        let mut errors = vec![];
+
+        let mut paths = match std::fs::read_dir(directory) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().map_or(false, |ext| ext == "toml"))
+                .filter(|path| *path != category_path)
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                return Err(ContentError {
+                    errors: vec![format!("{}: {err}", directory.display())],
+                })
+            }
+        };
+        paths.sort();
+
+        for path in paths {
+            match load_content_file(&path, &categories) {
+                Ok(content_info) => {
+                    if let Some(other) = contents.get(&content_info.key) {
+                        errors.push(format!(
+                            "{}: key `{}` is already used by {}",
+                            path.display(),
+                            content_info.key,
+                            other.name
+                        ));
+                    } else {
+                        contents.insert(content_info.key.clone(), Arc::new(content_info));
+                    }
+                }
+                Err(err) => errors.push(format!("{}: {err}", path.display())),
+            }
+        }
+
+        if contents.is_empty() && errors.is_empty() {
+            errors.push(format!("{}: no content files", directory.display()));
+        }
+
+        if errors.is_empty() {
+            Ok(Self {
+                contents,
+                categories: categories.into_iter().map(Arc::new).collect(),
+            })
+        } else {
+            Err(ContentError { errors })
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arc<ContentInfo>> {
+        self.contents.get(key).cloned()
+    }
+
+    /// Contents of the type ordered by tier and item level.
+    pub fn of_type(&self, content_type: &str) -> Vec<Arc<ContentInfo>> {
+        let mut contents: Vec<_> = self
+            .contents
+            .values()
+            .filter(|content_info| content_info.content_type == content_type)
+            .cloned()
+            .collect();
+        contents.sort_by(|a, b| (a.tier, a.ilvl_req, &a.name).cmp(&(b.tier, b.ilvl_req, &b.name)));
+        contents
+    }
+
+    pub fn category(&self, content_type: &str) -> Option<Arc<Category>> {
+        self.categories
+            .iter()
+            .find(|category| category.content_type == content_type)
+            .cloned()
+    }
+
+    /// Categories that have contents, in the order of the category file.
+    pub fn categories(&self) -> Vec<Arc<Category>> {
+        self.categories
+            .iter()
+            .filter(|category| {
+                self.contents
+                    .values()
+                    .any(|content_info| content_info.content_type == category.content_type)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Arc<ContentInfo>> {
+        self.contents.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+}
+
+fn load_category_file(path: &Path) -> Result<Vec<Category>, String> {
+    let category_toml = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let categories = toml::from_str::<CategoryFile>(&category_toml)
+        .map_err(|err| err.to_string())?
+        .categories;
+
+    if categories.len() > MAX_CATEGORIES {
+        return Err(format!(
+            "has {} categories, at most {MAX_CATEGORIES} are supported",
+            categories.len()
+        ));
     }
+    for (i, category) in categories.iter().enumerate() {
+        if category.content_type.is_empty() || category.content_type == CUSTOM_CONTENT_TYPE {
+            return Err(format!(
+                "type of {} can't be empty or `{CUSTOM_CONTENT_TYPE}`",
+                category.name
+            ));
+        }
+        if categories[..i]
+            .iter()
+            .any(|other| other.content_type == category.content_type)
+        {
+            return Err(format!("type `{}` is repeated", category.content_type));
+        }
+        if !BUTTON_STYLES.contains(&category.button.as_str()) {
+            return Err(format!(
+                "unknown button `{}` of {}, expected one of {}",
+                category.button,
+                category.name,
+                BUTTON_STYLES.join(", ")
+            ));
+        }
+        if category.color.is_some() && category.colour().is_none() {
+            return Err(format!("color of {} must be like `#c0392b`", category.name));
+        }
+        if let Some(locale) = unsupported_locale(category.names.keys()) {
+            return Err(format!(
+                "unsupported locale `{locale}` in names of {}",
+                category.name
+            ));
+        }
+    }
+
+    Ok(categories)
+}
+
+fn load_content_file(path: &Path, categories: &[Category]) -> Result<ContentInfo, String> {
+    let content_toml = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let content_info: ContentInfo = toml::from_str(&content_toml).map_err(|err| err.to_string())?;
+
+    if content_info.key.is_empty() {
+        return Err("key can't be empty".to_string());
+    }
+    if !categories
+        .iter()
+        .any(|category| category.content_type == content_info.content_type)
+    {
+        return Err(format!(
+            "unknown type `{}`, expected one of the types in {CATEGORY_FILE}",
+            content_info.content_type
+        ));
+    }
+    if content_info.content_size == 0 {
+        return Err("content_size must be at least 1".to_string());
+    }
+    if let Some(locale) = unsupported_locale(content_info.introductions.keys()) {
+        return Err(format!("unsupported locale `{locale}` in introductions"));
+    }
+    if content_info.gates.iter().any(|gate| gate.name.is_empty()) {
+        return Err("gate names can't be empty".to_string());
+    }
+    for (i, difficulty) in content_info.difficulties.iter().enumerate() {
+        if difficulty.name.is_empty() {
+            return Err("difficulty names can't be empty".to_string());
+        }
+        if content_info.difficulties[..i]
+            .iter()
+            .any(|other| other.name == difficulty.name)
+        {
+            return Err(format!("difficulty `{}` is repeated", difficulty.name));
+        }
+    }
+    for (field, url) in [
+        ("image", &content_info.image),
+        ("banner", &content_info.banner),
+        ("guide", &content_info.guide),
+        ("gameplay_video", &content_info.gameplay_video),
+    ] {
+        if url.is_empty() {
+            continue;
+        }
+        match reqwest::Url::parse(url) {
+            Ok(parsed) if parsed.scheme() == "https" || parsed.scheme() == "http" => {}
+            _ => return Err(format!("{field} is not a valid http(s) url: `{url}`")),
+        }
+    }
+
+    Ok(content_info)
+}
+
+fn unsupported_locale<'a>(mut locales: impl Iterator<Item = &'a String>) -> Option<&'a String> {
+    locales.find(|locale| !locale::is_supported(locale))
+}
+
+fn content_dir() -> &'static Path {
+    CONTENT_DIR.get_or_init(|| PathBuf::from("./contents"))
+}
+
+/// Current content catalog. Lobbies keep the infos they were created with after a reload.
+pub fn contents() -> Arc<ContentRegistry> {
+    CONTENTS
+        .get_or_init(|| {
+            let registry = ContentRegistry::load(content_dir())
+                .unwrap_or_else(|err| panic!("Invalid content files:\n{err}"));
+            RwLock::new(Arc::new(registry))
+        })
+        .read()
+        .clone()
+}
+
+/// Loads the content files again and swaps the catalog. Current catalog is kept if any file
+/// is invalid. Returns the number of loaded contents.
+pub fn reload_contents() -> Result<usize, ContentError> {
+    let registry = Arc::new(ContentRegistry::load(content_dir())?);
+    let len = registry.len();
+
+    if CONTENTS.set(RwLock::new(registry.clone())).is_err() {
+        *CONTENTS.get().unwrap().write() = registry;
+    }
+
+    Ok(len)
+}
+
+/// Reloads the contents when a file in the content directory changes.
+pub fn watch_contents() -> notify::Result<()> {
+    let (sender, reciever) = std::sync::mpsc::channel();
+    let mut watcher = notify::watcher(sender, Duration::from_secs(2))?;
+    watcher.watch(content_dir(), RecursiveMode::NonRecursive)?;
+
+    std::thread::spawn(move || {
+        // Watcher stops when it is dropped
+        let _watcher = watcher;
+        for event in reciever {
+            if let DebouncedEvent::Error(err, _) = event {
+                tracing::warn!("Content watcher error: {err}");
+                continue;
+            }
+
+            match reload_contents() {
+                Ok(len) => tracing::info!(len, "Reloaded contents"),
+                Err(err) => {
+                    tracing::error!("Couldn't reload contents, keeping the old ones:\n{err}")
+                }
+            }
+        }
+    });
+
+    Ok(())
 }
@@ -1,24 +1,41 @@
+#[cfg(feature = "api")]
+pub mod api;
+pub mod backup;
 pub mod commands;
+pub mod component_id;
+pub mod config;
 pub mod database;
+pub mod error;
 pub mod info;
 pub mod listener;
+pub mod locale;
+pub mod logging;
+pub mod metrics;
+pub mod router;
+pub mod shutdown;
+pub mod storage;
 pub mod check;
+pub mod webhook;
 use commands::lobby::helper::{EventParseError, LobbyEvent};
+use commands::lobby::supervisor::LobbySupervisor;
+use config::Config;
 pub use entity::sea_orm_active_enums::*;
-use hashbrown::HashMap;
-use parking_lot::RwLock;
+pub use error::Error;
 use poise::serenity_prelude as serenity;
 use poise::serenity_prelude::MessageComponentInteraction;
-use sea_orm::DatabaseConnection;
+use router::Router;
+use shutdown::Shutdown;
 use std::sync::Arc;
-use tokio::sync::mpsc::UnboundedSender;
+use storage::Storage;
 
 pub struct Data {
-    pub db: &'static DatabaseConnection,
-    // Hashmap to store lobby ids with their task's channel handle
-    pub active_lobbies: RwLock<HashMap<String, UnboundedSender<EventComponent>>>,
+    pub config: Arc<Config>,
+    pub db: &'static dyn Storage,
+    pub lobby_supervisor: Arc<LobbySupervisor>,
+    pub router: Router,
+    pub shutdown: Arc<Shutdown>,
 }
-pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
 pub type Context<'a> = poise::Context<'a, Data, Error>;
 
 pub struct EventComponent {
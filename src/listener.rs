@@ -1,6 +1,6 @@
 use crate::{
     commands::{Data, Error},
-    EventComponent,
+    router::RoutedInteraction,
 };
 use poise::serenity_prelude as serenity;
 
@@ -11,35 +11,22 @@ pub async fn listener(
     user_data: &Data,
 ) -> Result<(), Error> {
     match event {
-        poise::Event::Ready { data_about_bot: _ } => println!("Ready to do stuff."),
-        poise::Event::InteractionCreate { interaction } => {
-            match interaction {
-                serenity::Interaction::MessageComponent(mci) => {
-                    // UUIDv4 length is 36 characters
-                    let (lobby_id_str, event_str) = mci.data.custom_id.split_at(36);
-
-                    let event_c = EventComponent::new(mci.clone(), ctx.http.clone(), event_str);
-
-                    if event_c.is_err() {
-                        println!("Event is not tracked");
-                        return Ok(());
-                    }
-                    println!("Lobby id: ({lobby_id_str})");
-
-                    let res = user_data
-                        .active_lobbies
-                        .read()
-                        .get(lobby_id_str)
-                        .expect("No active lobby found with given id")
-                        .send(event_c.unwrap());
-
-                    if let Err(err) = res {
-                        println!("Error sending event component to task {err}");
-                    }
-                }
-                _ => {}
+        poise::Event::Ready { data_about_bot: _ } => tracing::info!("Ready to do stuff."),
+        poise::Event::InteractionCreate { interaction } => match interaction {
+            serenity::Interaction::MessageComponent(mci) => {
+                user_data
+                    .router
+                    .dispatch(ctx, user_data, RoutedInteraction::Component(mci))
+                    .await?
+            }
+            serenity::Interaction::ModalSubmit(msi) => {
+                user_data
+                    .router
+                    .dispatch(ctx, user_data, RoutedInteraction::Modal(msi))
+                    .await?
             }
-        }
+            _ => {}
+        },
         _ => {}
     }
     Ok(())
@@ -0,0 +1,146 @@
+//! Translations of the bot messages.
+//!
+//! Messages are looked up by key in the catalogs of `locales/`, which are compiled into the bot.
+//! Keys are the dotted paths of the TOML tables, like `lobby.join`, and `{name}` placeholders are
+//! filled by [`tr_with`]. Messages missing in a catalog fall back to English.
+//!
+//! Posted lobbies use the locale of the server, replies only seen by a user prefer the Discord
+//! locale of the user.
+use std::fmt;
+
+use hashbrown::HashMap;
+use once_cell::sync::Lazy;
+
+use crate::{check::guild_id, Context};
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Locales with a catalog and their names shown to the users.
+pub const LOCALES: [(&str, &str); 2] = [("en", "English"), ("tr", "Türkçe")];
+
+const CATALOG_FILES: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.toml")),
+    ("tr", include_str!("../locales/tr.toml")),
+];
+
+type Catalog = HashMap<String, String>;
+
+static CATALOGS: Lazy<HashMap<&'static str, Catalog>> = Lazy::new(|| {
+    CATALOG_FILES
+        .iter()
+        .map(|(locale, file)| {
+            let value: toml::Value = toml::from_str(file)
+                .unwrap_or_else(|err| panic!("Invalid catalog of locale {locale}: {err}"));
+            let mut catalog = Catalog::new();
+            flatten("", value, &mut catalog);
+            (*locale, catalog)
+        })
+        .collect()
+});
+
+fn flatten(prefix: &str, value: toml::Value, catalog: &mut Catalog) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, catalog);
+            }
+        }
+        toml::Value::String(message) => {
+            catalog.insert(prefix.to_string(), message);
+        }
+        other => panic!("Message {prefix} must be a string, got {other}"),
+    }
+}
+
+pub fn is_supported(locale: &str) -> bool {
+    LOCALES.iter().any(|(supported, _)| *supported == locale)
+}
+
+/// Supported locale of a Discord locale like `en-US` or `tr`.
+pub fn resolve(discord_locale: &str) -> Option<&'static str> {
+    let language = discord_locale.split('-').next().unwrap_or_default();
+    LOCALES
+        .iter()
+        .map(|(locale, _)| *locale)
+        .find(|locale| *locale == language)
+}
+
+/// Keys of the English catalog, every other catalog should have the same keys.
+pub fn keys() -> Vec<&'static str> {
+    let mut keys: Vec<_> = CATALOGS[DEFAULT_LOCALE]
+        .keys()
+        .map(String::as_str)
+        .collect();
+    keys.sort_unstable();
+    keys
+}
+
+/// Keys of the English catalog missing in the catalog of the locale.
+pub fn missing_keys(locale: &str) -> Vec<&'static str> {
+    let catalog = CATALOGS.get(locale);
+    keys()
+        .into_iter()
+        .filter(|key| !catalog.map_or(false, |catalog| catalog.contains_key(*key)))
+        .collect()
+}
+
+/// Message of the key in the locale, the key itself if there is no such message.
+pub fn tr(locale: &str, key: &str) -> String {
+    CATALOGS
+        .get(locale)
+        .and_then(|catalog| catalog.get(key))
+        .or_else(|| CATALOGS[DEFAULT_LOCALE].get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Message of the key with its `{name}` placeholders replaced by the arguments.
+pub fn tr_with(locale: &str, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    args.iter().fold(tr(locale, key), |message, (name, value)| {
+        message.replace(&format!("{{{name}}}"), &value.to_string())
+    })
+}
+
+/// Locale of the server, the default locale if the server is not registered.
+pub async fn server_locale(ctx: Context<'_>) -> String {
+    match guild_id(ctx) {
+        Ok(guild_id) => ctx
+            .data()
+            .db
+            .get_server(guild_id)
+            .await
+            .map(|server| server.locale)
+            .unwrap_or_else(|_| DEFAULT_LOCALE.to_string()),
+        Err(_) => DEFAULT_LOCALE.to_string(),
+    }
+}
+
+/// Discord locale of the user if it is supported, otherwise the locale of the server.
+pub async fn user_locale(ctx: Context<'_>) -> String {
+    let discord_locale = match ctx {
+        poise::Context::Application(ctx) => Some(match ctx.interaction {
+            poise::ApplicationCommandOrAutocompleteInteraction::ApplicationCommand(interaction) => {
+                interaction.locale.as_str()
+            }
+            poise::ApplicationCommandOrAutocompleteInteraction::Autocomplete(interaction) => {
+                interaction.locale.as_str()
+            }
+        }),
+        poise::Context::Prefix(_) => None,
+    };
+
+    match discord_locale.and_then(resolve) {
+        Some(locale) => locale.to_string(),
+        None => server_locale(ctx).await,
+    }
+}
+
+/// Discord locale of the user if it is supported, otherwise the given locale.
+pub fn interaction_locale(discord_locale: &str, fallback: &str) -> String {
+    resolve(discord_locale).unwrap_or(fallback).to_string()
+}
@@ -0,0 +1,26 @@
+//! Log output of the bot.
+//!
+//! Level is a `tracing` filter, either a plain level like `debug` or directives like
+//! `ark_guild_bot=debug,serenity=warn`. Database query timings are logged at `debug` level.
+use tracing_subscriber::EnvFilter;
+
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Installs the global subscriber. JSON output prints one object per line with the span fields.
+pub fn init(level: &str, json: bool) {
+    let filter = EnvFilter::try_new(level).unwrap_or_else(|err| {
+        eprintln!("Invalid log level ({level}), using {DEFAULT_LOG_LEVEL}: {err}");
+        EnvFilter::new(DEFAULT_LOG_LEVEL)
+    });
+
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    if json {
+        subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init();
+    } else {
+        subscriber.init();
+    }
+}
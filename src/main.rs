@@ -1,60 +1,163 @@
 use ark_guild_bot::{
     commands::{
+        backup::*,
         characters::*,
-        lobby::{command::*, context::LobbyContext, helper::process_lobby_event},
+        content::*,
+        lobby::{command::*, handler::LobbyHandler, supervisor::LobbySupervisor},
         register::*,
+        webhook::*,
         Data,
     },
-    database::{disable_lobby, get_active_characters_joined, get_active_lobbies},
-    info::ContentInfo,
+    component_id::{CHARACTER, CONFIRM, LOBBY, WIZARD},
+    config::Config,
+    error::on_error,
+    info::{reload_contents, watch_contents, CONTENT_DIR},
     listener::listener,
-    Error, EventComponent,
+    locale::{tr, user_locale},
+    logging, metrics,
+    router::Router,
+    shutdown::{self, set_lobby_notice, Shutdown},
+    Error,
 };
-use chrono::Utc;
 use dotenv::dotenv;
-use hashbrown::HashMap;
+use migration::{Migrator, MigratorTrait};
 use once_cell::sync::OnceCell;
-use parking_lot::RwLock;
 use poise::serenity_prelude::{self as serenity, GatewayIntents};
-use sea_orm::{Database, DatabaseConnection, DbErr};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 use std::sync::Arc;
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 
 pub static DB: OnceCell<DatabaseConnection> = OnceCell::new();
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     dotenv().ok();
-    DB.set(
-        Database::connect(std::env::var("DATABASE_URL").expect("DATABASE_URL must be set")).await?,
-    )
-    .unwrap();
-    poise::Framework::build()
-        .token(std::env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN must be set"))
-        .user_data_setup(move |_ctx, _ready, _framework| {
-            Box::pin(async move {
-                Ok(Data {
-                    db: DB.get().unwrap(),
-                    // active_lobbies: todo!("Init this with database query"),
-                    active_lobbies: init_active_lobbies(DB.get().unwrap()).await?,
+    let config = match Config::load() {
+        Ok(config) => Arc::new(config),
+        Err(err) => {
+            eprintln!("Invalid configuration: {err}");
+            std::process::exit(1);
+        }
+    };
+    logging::init(&config.log.level, config.log.json);
+    CONTENT_DIR.set(config.content.directory.clone()).unwrap();
+    if let Err(err) = reload_contents() {
+        eprintln!("Invalid content files:\n{err}");
+        std::process::exit(1);
+    }
+    if config.content.watch {
+        if let Err(err) = watch_contents() {
+            tracing::error!("Couldn't watch the content directory: {err}");
+        }
+    }
+
+    let mut connect_options = ConnectOptions::new(config.database_url());
+    connect_options.max_connections(config.database.max_connections);
+    let db = Database::connect(connect_options).await?;
+    Migrator::up(&db, None).await?;
+    DB.set(db).unwrap();
+
+    #[cfg(feature = "api")]
+    if let Some(api_addr) = config.features.api_addr {
+        tokio::spawn(async move {
+            if let Err(err) = ark_guild_bot::api::serve(api_addr, DB.get().unwrap()).await {
+                tracing::error!("API server stopped: {err}");
+            }
+        });
+    }
+
+    let restart_notice = config.features.lobby_restart_notice;
+    let lobby_supervisor = LobbySupervisor::start(DB.get().unwrap()).await?;
+    let shutdown = Arc::new(Shutdown::default());
+
+    #[cfg(feature = "metrics")]
+    if let Some(metrics_addr) = config.features.metrics_addr {
+        let lobby_supervisor = lobby_supervisor.clone();
+        tokio::spawn(async move {
+            if let Err(err) = metrics::serve(metrics_addr, lobby_supervisor).await {
+                tracing::error!("Metrics server stopped: {err}");
+            }
+        });
+    }
+
+    let framework = poise::Framework::build()
+        .token(&config.discord.token)
+        .user_data_setup({
+            let config = config.clone();
+            let lobby_supervisor = lobby_supervisor.clone();
+            let shutdown = shutdown.clone();
+            move |ctx, _ready, _framework| {
+                Box::pin(async move {
+                    // Remove the notes left by the last shutdown
+                    if restart_notice {
+                        set_lobby_notice(&ctx.http, &lobby_supervisor.lobby_messages(), None).await;
+                    }
+
+                    Ok(Data {
+                        config,
+                        db: DB.get().unwrap(),
+                        lobby_supervisor,
+                        router: Router::new()
+                            .register(LOBBY, LobbyHandler)
+                            .collected(CHARACTER)
+                            .collected(CONFIRM)
+                            .collected(WIZARD),
+                        shutdown,
+                    })
                 })
-            })
+            }
         })
         .options(poise::FrameworkOptions {
             commands: vec![
                 register_guild(),
+                language(),
+                settings(),
                 register_commands(),
+                reload_content(),
+                content(),
+                content_info(),
                 character(),
                 list_characters(),
                 delete_character(),
                 edit_character_ilvl(),
                 create_lobby(),
+                reschedule_lobby(),
+                add_webhook(),
+                remove_webhook(),
+                list_webhooks(),
+                backup(),
+                restore(),
+                #[cfg(feature = "api")]
+                ark_guild_bot::commands::api::api_token(),
             ],
+            owners: config
+                .discord
+                .owners
+                .iter()
+                .map(|&owner| serenity::UserId(owner))
+                .collect(),
+            on_error: |error| Box::pin(on_error(error)),
+            pre_command: |ctx| {
+                Box::pin(async move {
+                    if let Some(command) = ctx.command() {
+                        metrics::command_executed(command.name());
+                    }
+                })
+            },
+            command_check: Some(|ctx| {
+                Box::pin(async move {
+                    if ctx.data().shutdown.is_started() {
+                        ctx.say(tr(&user_locale(ctx).await, "common.restarting"))
+                            .await?;
+                        return Ok(false);
+                    }
+                    Ok(true)
+                })
+            }),
             listener: |ctx, event, framework, user_data| {
                 Box::pin(listener(ctx, event, framework, user_data))
             },
             prefix_options: poise::PrefixFrameworkOptions {
-                prefix: Some("!".into()),
+                prefix: Some(config.discord.prefix.clone()),
                 ..Default::default()
             },
             ..Default::default()
@@ -63,74 +166,20 @@ async fn main() -> Result<(), Error> {
             client_builder.intents(GatewayIntents::privileged() | GatewayIntents::non_privileged())
         })
         .intents(serenity::GatewayIntents::all())
-        .run()
-        .await
-        .unwrap();
-    Ok(())
-}
+        .build()
+        .await?;
 
-async fn init_active_lobbies(
-    db: &'static DatabaseConnection,
-) -> Result<RwLock<HashMap<String, UnboundedSender<EventComponent>>>, DbErr> {
-    let mut lobby_map = HashMap::new();
-    let active_lobbies = get_active_lobbies(db).await?;
-    for lobby in active_lobbies {
-        if let Some(time) = lobby.scheduled {
-            if time <= Utc::now() {
-                disable_lobby(&lobby, db).await?;
-                continue;
-            } else if time <= Utc::now() + chrono::Duration::minutes(10) {
-                todo!("Notify users")
-            }
+    tokio::spawn({
+        let shard_manager = framework.shard_manager();
+        async move {
+            shutdown::signal().await;
+            let http = serenity::http::client::Http::new(&config.discord.token);
+            shutdown
+                .run(&lobby_supervisor, &http, shard_manager, restart_notice)
+                .await;
         }
+    });
 
-        let (sender, mut reciever) = unbounded_channel();
-        lobby_map.insert(lobby.lobby_id.to_hyphenated().to_string(), sender);
-
-        let active_players = get_active_characters_joined(lobby.lobby_id, db).await?;
-        tokio::spawn({
-            let content_info: &ContentInfo = lobby.content.into();
-            let lobby_context_locked = Arc::new(RwLock::new(LobbyContext {
-                id: lobby.lobby_id,
-                id_as_string: lobby.lobby_id.to_hyphenated().to_string(),
-                guild_id: lobby.guild_id.parse().unwrap(),
-                channel_id: lobby.channel_id.parse().unwrap(),
-                message_id: lobby.message_id.parse().unwrap(),
-                lobby_master: lobby.lobby_master.parse().unwrap(),
-                state: State::Generated,
-                content: Some(content_info.content_type.as_str().into()),
-                content_info: Some(content_info),
-                lobby_time: lobby.scheduled,
-                players: vec![],
-                active_players: vec![],
-                player_list: vec![],
-            }));
-
-            {
-                let lobby_context = lobby_context_locked.clone();
-                let mut lobby_context = lobby_context.write();
-                lobby_context.player_list = vec![
-                    "\n*This slot is empty*".to_string();
-                    lobby_context.content_info().content_size as usize
-                ];
-                for char_model in active_players {
-                    lobby_context.add_active_player_by_model(char_model);
-                }
-            }
-            println!("Started listening lobby: ({})", lobby.lobby_id);
-
-            async move {
-                while let Some(event_c) = reciever.recv().await {
-                    match process_lobby_event(event_c, lobby_context_locked.clone(), db).await {
-                        Ok(_) => {}
-                        Err(err) => {
-                            println!("Error processing event: {err}")
-                        }
-                    }
-                }
-            }
-        });
-    }
-
-    Ok(RwLock::from(lobby_map))
+    framework.start().await?;
+    Ok(())
 }
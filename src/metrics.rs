@@ -0,0 +1,163 @@
+//! Prometheus metrics.
+//!
+//! Metrics are only collected when the bot is built with the `metrics` feature, the recording
+//! functions do nothing otherwise. Gauges of the lobbies are read from the supervisor when the
+//! `/metrics` endpoint is scraped.
+use std::{future::Future, time::Instant};
+
+#[cfg(feature = "metrics")]
+pub use exporter::serve;
+
+/// Counts an executed command.
+pub fn command_executed(command: &str) {
+    #[cfg(feature = "metrics")]
+    exporter::COMMANDS.with_label_values(&[command]).inc();
+    #[cfg(not(feature = "metrics"))]
+    let _ = command;
+}
+
+/// Counts a processed lobby event.
+pub fn lobby_event_processed(event: &str) {
+    #[cfg(feature = "metrics")]
+    exporter::LOBBY_EVENTS.with_label_values(&[event]).inc();
+    #[cfg(not(feature = "metrics"))]
+    let _ = event;
+}
+
+/// Counts a reported error.
+pub fn error_reported(kind: &str) {
+    #[cfg(feature = "metrics")]
+    exporter::ERRORS.with_label_values(&[kind]).inc();
+    #[cfg(not(feature = "metrics"))]
+    let _ = kind;
+}
+
+/// Records the latency of a database query.
+pub fn observe_db_query(query: &str, seconds: f64) {
+    #[cfg(feature = "metrics")]
+    exporter::DB_LATENCY
+        .with_label_values(&[query])
+        .observe(seconds);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (query, seconds);
+}
+
+/// Awaits a Discord request and records its latency.
+pub async fn discord_request<T>(request: &'static str, future: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let result = future.await;
+
+    #[cfg(feature = "metrics")]
+    exporter::DISCORD_LATENCY
+        .with_label_values(&[request])
+        .observe(start.elapsed().as_secs_f64());
+    #[cfg(not(feature = "metrics"))]
+    let _ = (request, start);
+
+    result
+}
+
+#[cfg(feature = "metrics")]
+mod exporter {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use axum::{
+        extract::Extension,
+        http::{header::CONTENT_TYPE, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use once_cell::sync::Lazy;
+    use prometheus::{
+        register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder,
+        HistogramVec, IntCounterVec, IntGauge, TextEncoder,
+    };
+
+    use crate::commands::lobby::supervisor::LobbySupervisor;
+
+    pub static COMMANDS: Lazy<IntCounterVec> = Lazy::new(|| {
+        register_int_counter_vec!(
+            "ark_commands_total",
+            "Number of executed commands.",
+            &["command"]
+        )
+        .unwrap()
+    });
+
+    pub static LOBBY_EVENTS: Lazy<IntCounterVec> = Lazy::new(|| {
+        register_int_counter_vec!(
+            "ark_lobby_events_total",
+            "Number of processed lobby events.",
+            &["event"]
+        )
+        .unwrap()
+    });
+
+    pub static ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+        register_int_counter_vec!(
+            "ark_errors_total",
+            "Number of errors reported to the users.",
+            &["kind"]
+        )
+        .unwrap()
+    });
+
+    pub static DB_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+        register_histogram_vec!(
+            "ark_db_query_duration_seconds",
+            "Latency of the database queries.",
+            &["query"]
+        )
+        .unwrap()
+    });
+
+    pub static DISCORD_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+        register_histogram_vec!(
+            "ark_discord_request_duration_seconds",
+            "Latency of the Discord API requests.",
+            &["request"]
+        )
+        .unwrap()
+    });
+
+    static ACTIVE_LOBBIES: Lazy<IntGauge> = Lazy::new(|| {
+        register_int_gauge!("ark_active_lobbies", "Number of posted lobbies.").unwrap()
+    });
+
+    static LOBBY_QUEUE_DEPTH: Lazy<IntGauge> = Lazy::new(|| {
+        register_int_gauge!(
+            "ark_lobby_queue_depth",
+            "Number of events waiting for the lobby tasks."
+        )
+        .unwrap()
+    });
+
+    async fn metrics(Extension(supervisor): Extension<Arc<LobbySupervisor>>) -> impl IntoResponse {
+        ACTIVE_LOBBIES.set(supervisor.active_lobbies() as i64);
+        LOBBY_QUEUE_DEPTH.set(supervisor.queue_depth() as i64);
+
+        let encoder = TextEncoder::new();
+        let mut buffer = vec![];
+        if let Err(err) = encoder.encode(&prometheus::gather(), &mut buffer) {
+            tracing::error!("Couldn't encode metrics: {err}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, vec![]).into_response();
+        }
+
+        ([(CONTENT_TYPE, encoder.format_type().to_string())], buffer).into_response()
+    }
+
+    pub async fn serve(
+        addr: SocketAddr,
+        supervisor: Arc<LobbySupervisor>,
+    ) -> Result<(), hyper::Error> {
+        let app = Router::new()
+            .route("/metrics", get(metrics))
+            .layer(Extension(supervisor));
+
+        tracing::info!("Metrics listening on {addr}");
+        axum::Server::bind(&addr)
+            .serve(app.into_make_service())
+            .await
+    }
+}
@@ -0,0 +1,224 @@
+//! Routes component and modal submit interactions to the handler registered for the namespace
+//! of their [ComponentId].
+//!
+//! Features register their handlers in `main` instead of adding branches to the listener.
+//! Interactions with ids that can't be decoded or that have no handler get an ephemeral
+//! "expired" response.
+use async_trait::async_trait;
+use hashbrown::{HashMap, HashSet};
+use poise::serenity_prelude::{
+    self as serenity, MessageComponentInteraction, ModalSubmitInteraction,
+};
+
+use crate::{
+    component_id::ComponentId,
+    locale::{interaction_locale, tr, DEFAULT_LOCALE},
+    metrics, Data, Error,
+};
+
+/// Interaction given to the handlers.
+#[derive(Clone, Copy)]
+pub enum RoutedInteraction<'a> {
+    Component(&'a MessageComponentInteraction),
+    Modal(&'a ModalSubmitInteraction),
+}
+
+impl RoutedInteraction<'_> {
+    pub fn custom_id(&self) -> &str {
+        match self {
+            RoutedInteraction::Component(mci) => &mci.data.custom_id,
+            RoutedInteraction::Modal(msi) => &msi.data.custom_id,
+        }
+    }
+
+    pub fn user(&self) -> &serenity::User {
+        match self {
+            RoutedInteraction::Component(mci) => &mci.user,
+            RoutedInteraction::Modal(msi) => &msi.user,
+        }
+    }
+
+    /// Discord locale of the user like `en-US`.
+    pub fn locale(&self) -> &str {
+        match self {
+            RoutedInteraction::Component(mci) => &mci.locale,
+            RoutedInteraction::Modal(msi) => &msi.locale,
+        }
+    }
+
+    pub fn guild_id(&self) -> Option<serenity::GuildId> {
+        match self {
+            RoutedInteraction::Component(mci) => mci.guild_id,
+            RoutedInteraction::Modal(msi) => msi.guild_id,
+        }
+    }
+
+    /// Sends an ephemeral message. Uses a followup message if the interaction is already responded.
+    pub async fn respond_ephemeral(
+        &self,
+        http: &serenity::http::client::Http,
+        content: &str,
+    ) -> Result<(), serenity::Error> {
+        let response = metrics::discord_request("respond_ephemeral", async {
+            match self {
+                RoutedInteraction::Component(mci) => {
+                    mci.create_interaction_response(http, |r| {
+                        r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|d| d.content(content).ephemeral(true))
+                    })
+                    .await
+                }
+                RoutedInteraction::Modal(msi) => {
+                    msi.create_interaction_response(http, |r| {
+                        r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|d| d.content(content).ephemeral(true))
+                    })
+                    .await
+                }
+            }
+        })
+        .await;
+
+        if response.is_ok() {
+            return Ok(());
+        }
+
+        match self {
+            RoutedInteraction::Component(mci) => {
+                mci.create_followup_message(http, |f| f.content(content).ephemeral(true))
+                    .await?;
+            }
+            RoutedInteraction::Modal(msi) => {
+                msi.create_followup_message(http, |f| f.content(content).ephemeral(true))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub enum Outcome {
+    /// Handler responded to the interaction or left it to a running collector.
+    Handled,
+    /// Thing the component belongs to doesn't exist anymore.
+    Expired,
+}
+
+#[async_trait]
+pub trait InteractionHandler: Send + Sync {
+    /// Runs before [InteractionHandler::handle]. Returned error is reported to the user,
+    /// [Error::Permission] messages are shown in the locale of the user.
+    async fn check(
+        &self,
+        _ctx: &serenity::Context,
+        _data: &Data,
+        _component_id: &ComponentId,
+        _interaction: RoutedInteraction<'_>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        ctx: &serenity::Context,
+        data: &Data,
+        component_id: ComponentId,
+        interaction: RoutedInteraction<'_>,
+    ) -> Result<Outcome, Error>;
+}
+
+#[derive(Default)]
+pub struct Router {
+    handlers: HashMap<&'static str, Box<dyn InteractionHandler>>,
+    collected: HashSet<&'static str>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler for the interactions in the namespace.
+    #[must_use]
+    pub fn register(
+        mut self,
+        namespace: &'static str,
+        handler: impl InteractionHandler + 'static,
+    ) -> Self {
+        self.handlers.insert(namespace, Box::new(handler));
+        self
+    }
+
+    /// Marks the namespace as handled by the collectors of the commands so the router
+    /// doesn't respond to them.
+    #[must_use]
+    pub fn collected(mut self, namespace: &'static str) -> Self {
+        self.collected.insert(namespace);
+        self
+    }
+
+    #[tracing::instrument(
+        skip_all,
+        fields(custom_id = interaction.custom_id(), user_id = %interaction.user().id)
+    )]
+    pub async fn dispatch(
+        &self,
+        ctx: &serenity::Context,
+        data: &Data,
+        interaction: RoutedInteraction<'_>,
+    ) -> Result<(), Error> {
+        let locale = interaction_locale(interaction.locale(), DEFAULT_LOCALE);
+        if data.shutdown.is_started() {
+            interaction
+                .respond_ephemeral(&ctx.http, &tr(&locale, "common.restarting"))
+                .await?;
+            return Ok(());
+        }
+
+        let component_id = match ComponentId::decode(interaction.custom_id()) {
+            Ok(component_id) => component_id,
+            Err(err) => {
+                tracing::warn!(
+                    custom_id = interaction.custom_id(),
+                    "Unknown component id: {err}"
+                );
+                interaction
+                    .respond_ephemeral(&ctx.http, &tr(&locale, "common.expired"))
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        if self.collected.contains(component_id.namespace.as_str()) {
+            return Ok(());
+        }
+
+        let handler = match self.handlers.get(component_id.namespace.as_str()) {
+            Some(handler) => handler,
+            None => {
+                interaction
+                    .respond_ephemeral(&ctx.http, &tr(&locale, "common.expired"))
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        let result = match handler.check(ctx, data, &component_id, interaction).await {
+            Ok(()) => handler.handle(ctx, data, component_id, interaction).await,
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(Outcome::Handled) => {}
+            Ok(Outcome::Expired) => {
+                interaction
+                    .respond_ephemeral(&ctx.http, &tr(&locale, "common.expired"))
+                    .await?
+            }
+            Err(err) => err.report_interaction(interaction, &ctx.http).await,
+        }
+
+        Ok(())
+    }
+}
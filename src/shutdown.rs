@@ -0,0 +1,97 @@
+//! Graceful shutdown on SIGINT and SIGTERM.
+//!
+//! After a signal the bot stops accepting commands and interactions, lets every lobby task finish
+//! its queued events so database and lobby messages stay consistent, optionally adds a restart
+//! note to the lobby messages and closes the gateway connection.
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    commands::lobby::supervisor::{LobbyMessage, LobbySupervisor},
+    locale::tr,
+    metrics,
+};
+
+/// Maximum time to wait for the lobby tasks to finish their events.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+pub struct Shutdown {
+    started: AtomicBool,
+}
+
+impl Shutdown {
+    pub fn is_started(&self) -> bool {
+        self.started.load(Ordering::SeqCst)
+    }
+
+    /// Runs the shutdown sequence. Lobby messages get the restart notice if `notice` is set.
+    pub async fn run(
+        &self,
+        supervisor: &LobbySupervisor,
+        http: &serenity::http::client::Http,
+        shard_manager: Arc<tokio::sync::Mutex<serenity::client::bridge::gateway::ShardManager>>,
+        notice: bool,
+    ) {
+        self.started.store(true, Ordering::SeqCst);
+        tracing::info!("Shutting down, draining lobbies.");
+
+        let lobby_messages = supervisor.lobby_messages();
+        supervisor.drain(DRAIN_TIMEOUT).await;
+
+        if notice {
+            set_lobby_notice(http, &lobby_messages, Some("lobby.restart_notice")).await;
+        }
+
+        shard_manager.lock().await.shutdown_all().await;
+        tracing::info!("Shutdown complete.");
+    }
+}
+
+/// Sets the content of the lobby messages to the message of the key in the lobby locale.
+/// `None` removes the old notice.
+pub async fn set_lobby_notice(
+    http: &serenity::http::client::Http,
+    lobby_messages: &[LobbyMessage],
+    notice: Option<&str>,
+) {
+    for LobbyMessage {
+        channel_id,
+        message_id,
+        locale,
+    } in lobby_messages
+    {
+        let notice = notice.map(|key| tr(locale, key)).unwrap_or_default();
+        let edit =
+            serenity::ChannelId(*channel_id).edit_message(http, *message_id, |m| m.content(notice));
+        if let Err(err) = metrics::discord_request("edit_lobby_message", edit).await {
+            tracing::warn!(message_id, "Couldn't edit lobby message: {err}");
+        }
+    }
+}
+
+/// Waits until the process recieves SIGINT or SIGTERM.
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Couldn't listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
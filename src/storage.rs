@@ -0,0 +1,1556 @@
+//! Storage abstraction over the database functions.
+//!
+//! Commands and lobby tasks only talk to a [`Storage`] so they can run against PostgreSQL
+//! through [`DatabaseConnection`] or against [`MemoryStorage`] in tests.
+use std::{future::Future, time::Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use entity::{
+    api_tokens, characters, custom_content, guildmates, lobby, lobby_player, servers, webhooks,
+};
+use parking_lot::Mutex;
+use sea_orm::{DatabaseConnection, DbErr};
+use uuid::Uuid;
+
+use crate::{
+    backup::{self, GuildArchive, ARCHIVE_VERSION},
+    commands::lobby::{context::LobbyContext, settings::LobbySettings},
+    database,
+    locale::DEFAULT_LOCALE,
+    metrics, Class, Role,
+};
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn insert_server(
+        &self,
+        server_id: u64,
+        server_name: &str,
+        server_timezone: i32,
+    ) -> Result<(), DbErr>;
+
+    async fn insert_guildmate(
+        &self,
+        server_id: u64,
+        guildmate_id: u64,
+        guildmate_role: Role,
+    ) -> Result<(), DbErr>;
+
+    async fn insert_character(
+        &self,
+        character_id: u64,
+        character_guild: u64,
+        character_name: &str,
+        character_class: Class,
+        character_item_level: i32,
+    ) -> Result<(), DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn get_server(&self, server_id: u64) -> Result<servers::Model, DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn update_server_locale(&self, server_id: u64, locale: &str) -> Result<(), DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn update_server_settings(
+        &self,
+        server_id: u64,
+        settings: &LobbySettings,
+    ) -> Result<(), DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn get_guildmate(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+    ) -> Result<guildmates::Model, DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if the guildmate has no characters.
+    async fn get_all_characters(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+    ) -> Result<Vec<characters::Model>, DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if the guildmate has no characters above the item level.
+    async fn get_all_character_by_ilvl(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+        item_level: i32,
+    ) -> Result<Vec<characters::Model>, DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn get_single_character(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+    ) -> Result<characters::Model, DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if there are no characters above the item level.
+    async fn get_guildmates_by_min_ilvl(
+        &self,
+        guild_id: u64,
+        item_level: i32,
+    ) -> Result<Vec<characters::Model>, DbErr>;
+
+    /// This might return an empty vec
+    async fn get_guild_characters(&self, guild_id: u64) -> Result<Vec<characters::Model>, DbErr>;
+
+    /// Filters out every character of the guildmates in `filtered_out`.
+    async fn get_guildmates_by_min_ilvl_filter_out(
+        &self,
+        guild_id: u64,
+        item_level: i32,
+        filtered_out: &Vec<characters::Model>,
+    ) -> Result<Vec<characters::Model>, DbErr>;
+
+    async fn update_character(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+        character_class: Class,
+        character_item_level: i32,
+    ) -> Result<(), DbErr>;
+
+    async fn update_ilvl(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+        character_item_level: i32,
+    ) -> Result<(), DbErr>;
+
+    /// This also deletes everything associated with the server
+    async fn remove_server(&self, server_id: u64) -> Result<(), DbErr>;
+
+    /// This also deletes all characters associated with the guildmate
+    async fn remove_guildmate(&self, guildmate_id: u64, guild_id: u64) -> Result<(), DbErr>;
+
+    async fn remove_character(&self, character_name: &str, guild_id: u64) -> Result<(), DbErr>;
+
+    async fn insert_lobby(&self, lobby_context: &LobbyContext) -> Result<(), DbErr>;
+
+    async fn get_lobby(&self, lobby_id: Uuid) -> Result<lobby::Model, DbErr>;
+
+    /// This might return an empty vec
+    async fn get_active_lobbies(&self) -> Result<Vec<lobby::Model>, DbErr>;
+
+    /// This might return an empty vec
+    async fn get_active_guild_lobbies(&self, guild_id: u64) -> Result<Vec<lobby::Model>, DbErr>;
+
+    async fn disable_lobby(&self, lobby: &lobby::Model) -> Result<(), DbErr>;
+
+    async fn update_lobby_schedule(
+        &self,
+        lobby_id: Uuid,
+        scheduled: Option<DateTime<Utc>>,
+    ) -> Result<(), DbErr>;
+
+    async fn insert_lobby_player(
+        &self,
+        lobby_context: &LobbyContext,
+        player: &characters::Model,
+    ) -> Result<(), DbErr>;
+
+    async fn insert_lobby_players(
+        &self,
+        lobby_context: &LobbyContext,
+        player_list: Vec<characters::Model>,
+    ) -> Result<(), DbErr>;
+
+    async fn get_lobby_player(
+        &self,
+        lobby_id: Uuid,
+        player_name: &str,
+    ) -> Result<lobby_player::Model, DbErr>;
+
+    async fn get_lobby_players(&self, lobby_id: Uuid) -> Result<Vec<lobby_player::Model>, DbErr>;
+
+    /// Players after the removed one move up a slot.
+    async fn remove_lobby_player(&self, lobby_id: Uuid, player_name: &str) -> Result<(), DbErr>;
+
+    /// Replaces a character in the lobby with `player`.
+    /// Returns [`DbErr::RecordNotFound`] if the character isn't in the lobby and
+    /// [`DbErr::Exec`] if `player` is already in it.
+    async fn swap_lobby_player(
+        &self,
+        lobby_context: &LobbyContext,
+        player_name: &str,
+        player: &characters::Model,
+    ) -> Result<(), DbErr>;
+
+    async fn get_active_characters_joined(
+        &self,
+        lobby_id: Uuid,
+    ) -> Result<Vec<characters::Model>, DbErr>;
+
+    /// Number of the finished lobbies of the content the character was in.
+    async fn count_clears(
+        &self,
+        guild_id: u64,
+        character_name: &str,
+        content: &str,
+    ) -> Result<usize, DbErr>;
+
+    async fn upsert_api_token(&self, guild_id: u64, token_hash: &str) -> Result<(), DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<api_tokens::Model, DbErr>;
+
+    /// This might return an empty vec
+    async fn get_webhooks(&self, guild_id: &str) -> Result<Vec<webhooks::Model>, DbErr>;
+
+    async fn insert_webhook(&self, guild_id: u64, url: &str, secret: &str) -> Result<(), DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if there was none.
+    async fn remove_webhook(&self, guild_id: u64, url: &str) -> Result<(), DbErr>;
+
+    /// This might return an empty vec
+    async fn get_custom_contents(&self, guild_id: u64)
+        -> Result<Vec<custom_content::Model>, DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn get_custom_content(&self, content_id: Uuid) -> Result<custom_content::Model, DbErr>;
+
+    async fn insert_custom_content(
+        &self,
+        custom_content: custom_content::Model,
+    ) -> Result<(), DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn update_custom_content(
+        &self,
+        custom_content: custom_content::Model,
+    ) -> Result<(), DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if record doesn't exists.
+    async fn remove_custom_content(&self, content_id: Uuid) -> Result<(), DbErr>;
+
+    /// Returns [`DbErr::RecordNotFound`] if the guild is not registered.
+    async fn export_guild(&self, guild_id: u64) -> Result<GuildArchive, DbErr>;
+
+    /// Replaces the data of the archive's guild. Returns [`DbErr::Custom`] if the archive is
+    /// not valid.
+    async fn import_guild(&self, archive: GuildArchive) -> Result<(), DbErr>;
+}
+
+/// Awaits the query and records how long it took.
+async fn timed<T>(query: &'static str, future: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let result = future.await;
+    let elapsed = start.elapsed().as_secs_f64();
+    tracing::debug!(query, elapsed_ms = elapsed * 1000.0, "database query");
+    metrics::observe_db_query(query, elapsed);
+    result
+}
+
+#[async_trait]
+impl Storage for DatabaseConnection {
+    async fn insert_server(
+        &self,
+        server_id: u64,
+        server_name: &str,
+        server_timezone: i32,
+    ) -> Result<(), DbErr> {
+        timed(
+            "insert_server",
+            database::insert_server(server_id, server_name, server_timezone, self),
+        )
+        .await
+    }
+
+    async fn insert_guildmate(
+        &self,
+        server_id: u64,
+        guildmate_id: u64,
+        guildmate_role: Role,
+    ) -> Result<(), DbErr> {
+        timed(
+            "insert_guildmate",
+            database::insert_guildmate(server_id, guildmate_id, guildmate_role, self),
+        )
+        .await
+    }
+
+    async fn insert_character(
+        &self,
+        character_id: u64,
+        character_guild: u64,
+        character_name: &str,
+        character_class: Class,
+        character_item_level: i32,
+    ) -> Result<(), DbErr> {
+        timed(
+            "insert_character",
+            database::insert_character(
+                character_id,
+                character_guild,
+                character_name,
+                character_class,
+                character_item_level,
+                self,
+            ),
+        )
+        .await
+    }
+
+    async fn get_server(&self, server_id: u64) -> Result<servers::Model, DbErr> {
+        timed("get_server", database::get_server(server_id, self)).await
+    }
+
+    async fn update_server_locale(&self, server_id: u64, locale: &str) -> Result<(), DbErr> {
+        timed(
+            "update_server_locale",
+            database::update_server_locale(server_id, locale, self),
+        )
+        .await
+    }
+
+    async fn update_server_settings(
+        &self,
+        server_id: u64,
+        settings: &LobbySettings,
+    ) -> Result<(), DbErr> {
+        timed(
+            "update_server_settings",
+            database::update_server_settings(server_id, settings, self),
+        )
+        .await
+    }
+
+    async fn get_guildmate(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+    ) -> Result<guildmates::Model, DbErr> {
+        timed(
+            "get_guildmate",
+            database::get_guildmate(guildmate_id, guild_id, self),
+        )
+        .await
+    }
+
+    async fn get_all_characters(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        timed(
+            "get_all_characters",
+            database::get_all_characters(guildmate_id, guild_id, self),
+        )
+        .await
+    }
+
+    async fn get_all_character_by_ilvl(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+        item_level: i32,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        timed(
+            "get_all_character_by_ilvl",
+            database::get_all_character_by_ilvl(guildmate_id, guild_id, item_level, self),
+        )
+        .await
+    }
+
+    async fn get_single_character(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+    ) -> Result<characters::Model, DbErr> {
+        timed(
+            "get_single_character",
+            database::get_single_character(character_name, character_guild, self),
+        )
+        .await
+    }
+
+    async fn get_guildmates_by_min_ilvl(
+        &self,
+        guild_id: u64,
+        item_level: i32,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        timed(
+            "get_guildmates_by_min_ilvl",
+            database::get_guildmates_by_min_ilvl(guild_id, item_level, self),
+        )
+        .await
+    }
+
+    async fn get_guild_characters(&self, guild_id: u64) -> Result<Vec<characters::Model>, DbErr> {
+        timed(
+            "get_guild_characters",
+            database::get_guild_characters(guild_id, self),
+        )
+        .await
+    }
+
+    async fn get_guildmates_by_min_ilvl_filter_out(
+        &self,
+        guild_id: u64,
+        item_level: i32,
+        filtered_out: &Vec<characters::Model>,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        timed(
+            "get_guildmates_by_min_ilvl_filter_out",
+            database::get_guildmates_by_min_ilvl_filter_out(
+                guild_id,
+                item_level,
+                filtered_out,
+                self,
+            ),
+        )
+        .await
+    }
+
+    async fn update_character(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+        character_class: Class,
+        character_item_level: i32,
+    ) -> Result<(), DbErr> {
+        timed(
+            "update_character",
+            database::update_character(
+                character_name,
+                character_guild,
+                character_class,
+                character_item_level,
+                self,
+            ),
+        )
+        .await
+    }
+
+    async fn update_ilvl(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+        character_item_level: i32,
+    ) -> Result<(), DbErr> {
+        timed(
+            "update_ilvl",
+            database::update_ilvl(character_name, character_guild, character_item_level, self),
+        )
+        .await
+    }
+
+    async fn remove_server(&self, server_id: u64) -> Result<(), DbErr> {
+        timed("remove_server", database::remove_server(server_id, self)).await
+    }
+
+    async fn remove_guildmate(&self, guildmate_id: u64, guild_id: u64) -> Result<(), DbErr> {
+        timed(
+            "remove_guildmate",
+            database::remove_guildmate(guildmate_id, guild_id, self),
+        )
+        .await
+    }
+
+    async fn remove_character(&self, character_name: &str, guild_id: u64) -> Result<(), DbErr> {
+        timed(
+            "remove_character",
+            database::remove_character(character_name, guild_id, self),
+        )
+        .await
+    }
+
+    async fn insert_lobby(&self, lobby_context: &LobbyContext) -> Result<(), DbErr> {
+        timed("insert_lobby", database::insert_lobby(lobby_context, self)).await
+    }
+
+    async fn get_lobby(&self, lobby_id: Uuid) -> Result<lobby::Model, DbErr> {
+        timed("get_lobby", database::get_lobby(lobby_id, self)).await
+    }
+
+    async fn get_active_lobbies(&self) -> Result<Vec<lobby::Model>, DbErr> {
+        timed("get_active_lobbies", database::get_active_lobbies(self)).await
+    }
+
+    async fn get_active_guild_lobbies(&self, guild_id: u64) -> Result<Vec<lobby::Model>, DbErr> {
+        timed(
+            "get_active_guild_lobbies",
+            database::get_active_guild_lobbies(guild_id, self),
+        )
+        .await
+    }
+
+    async fn disable_lobby(&self, lobby: &lobby::Model) -> Result<(), DbErr> {
+        timed("disable_lobby", database::disable_lobby(lobby, self)).await
+    }
+
+    async fn update_lobby_schedule(
+        &self,
+        lobby_id: Uuid,
+        scheduled: Option<DateTime<Utc>>,
+    ) -> Result<(), DbErr> {
+        timed(
+            "update_lobby_schedule",
+            database::update_lobby_schedule(lobby_id, scheduled, self),
+        )
+        .await
+    }
+
+    async fn insert_lobby_player(
+        &self,
+        lobby_context: &LobbyContext,
+        player: &characters::Model,
+    ) -> Result<(), DbErr> {
+        timed(
+            "insert_lobby_player",
+            database::insert_lobby_player(lobby_context, player, self),
+        )
+        .await
+    }
+
+    async fn insert_lobby_players(
+        &self,
+        lobby_context: &LobbyContext,
+        player_list: Vec<characters::Model>,
+    ) -> Result<(), DbErr> {
+        timed(
+            "insert_lobby_players",
+            database::insert_lobby_players(lobby_context, player_list, self),
+        )
+        .await
+    }
+
+    async fn get_lobby_player(
+        &self,
+        lobby_id: Uuid,
+        player_name: &str,
+    ) -> Result<lobby_player::Model, DbErr> {
+        timed(
+            "get_lobby_player",
+            database::get_lobby_player(lobby_id, player_name, self),
+        )
+        .await
+    }
+
+    async fn get_lobby_players(&self, lobby_id: Uuid) -> Result<Vec<lobby_player::Model>, DbErr> {
+        timed(
+            "get_lobby_players",
+            database::get_lobby_players(lobby_id, self),
+        )
+        .await
+    }
+
+    async fn remove_lobby_player(&self, lobby_id: Uuid, player_name: &str) -> Result<(), DbErr> {
+        timed(
+            "remove_lobby_player",
+            database::remove_lobby_player(lobby_id, player_name, self),
+        )
+        .await
+    }
+
+    async fn swap_lobby_player(
+        &self,
+        lobby_context: &LobbyContext,
+        player_name: &str,
+        player: &characters::Model,
+    ) -> Result<(), DbErr> {
+        timed(
+            "swap_lobby_player",
+            database::swap_lobby_player(lobby_context, player_name, player, self),
+        )
+        .await
+    }
+
+    async fn get_active_characters_joined(
+        &self,
+        lobby_id: Uuid,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        timed(
+            "get_active_characters_joined",
+            database::get_active_characters_joined(lobby_id, self),
+        )
+        .await
+    }
+
+    async fn count_clears(
+        &self,
+        guild_id: u64,
+        character_name: &str,
+        content: &str,
+    ) -> Result<usize, DbErr> {
+        timed(
+            "count_clears",
+            database::count_clears(guild_id, character_name, content, self),
+        )
+        .await
+    }
+
+    async fn upsert_api_token(&self, guild_id: u64, token_hash: &str) -> Result<(), DbErr> {
+        timed(
+            "upsert_api_token",
+            database::upsert_api_token(guild_id, token_hash, self),
+        )
+        .await
+    }
+
+    async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<api_tokens::Model, DbErr> {
+        timed(
+            "get_api_token_by_hash",
+            database::get_api_token_by_hash(token_hash, self),
+        )
+        .await
+    }
+
+    async fn get_webhooks(&self, guild_id: &str) -> Result<Vec<webhooks::Model>, DbErr> {
+        timed("get_webhooks", database::get_webhooks(guild_id, self)).await
+    }
+
+    async fn insert_webhook(&self, guild_id: u64, url: &str, secret: &str) -> Result<(), DbErr> {
+        timed(
+            "insert_webhook",
+            database::insert_webhook(guild_id, url, secret, self),
+        )
+        .await
+    }
+
+    async fn remove_webhook(&self, guild_id: u64, url: &str) -> Result<(), DbErr> {
+        timed(
+            "remove_webhook",
+            database::remove_webhook(guild_id, url, self),
+        )
+        .await
+    }
+
+    async fn get_custom_contents(
+        &self,
+        guild_id: u64,
+    ) -> Result<Vec<custom_content::Model>, DbErr> {
+        timed(
+            "get_custom_contents",
+            database::get_custom_contents(guild_id, self),
+        )
+        .await
+    }
+
+    async fn get_custom_content(&self, content_id: Uuid) -> Result<custom_content::Model, DbErr> {
+        timed(
+            "get_custom_content",
+            database::get_custom_content(content_id, self),
+        )
+        .await
+    }
+
+    async fn insert_custom_content(
+        &self,
+        custom_content: custom_content::Model,
+    ) -> Result<(), DbErr> {
+        timed(
+            "insert_custom_content",
+            database::insert_custom_content(custom_content, self),
+        )
+        .await
+    }
+
+    async fn update_custom_content(
+        &self,
+        custom_content: custom_content::Model,
+    ) -> Result<(), DbErr> {
+        timed(
+            "update_custom_content",
+            database::update_custom_content(custom_content, self),
+        )
+        .await
+    }
+
+    async fn remove_custom_content(&self, content_id: Uuid) -> Result<(), DbErr> {
+        timed(
+            "remove_custom_content",
+            database::remove_custom_content(content_id, self),
+        )
+        .await
+    }
+
+    async fn export_guild(&self, guild_id: u64) -> Result<GuildArchive, DbErr> {
+        timed("export_guild", backup::export_guild(guild_id, self)).await
+    }
+
+    async fn import_guild(&self, archive: GuildArchive) -> Result<(), DbErr> {
+        timed("import_guild", backup::import_guild(archive, self)).await
+    }
+}
+
+#[derive(Default)]
+struct Tables {
+    servers: Vec<servers::Model>,
+    guildmates: Vec<guildmates::Model>,
+    characters: Vec<characters::Model>,
+    lobbies: Vec<lobby::Model>,
+    lobby_players: Vec<lobby_player::Model>,
+    api_tokens: Vec<api_tokens::Model>,
+    webhooks: Vec<webhooks::Model>,
+    custom_contents: Vec<custom_content::Model>,
+}
+
+impl Tables {
+    fn character_position(&self, character_name: &str, guild_id: &str) -> Option<usize> {
+        self.characters
+            .iter()
+            .position(|c| c.name == character_name && c.guild_id == guild_id)
+    }
+
+    /// Same as the cascading foreign keys of the migrations.
+    fn cascade(&mut self) {
+        let Tables {
+            servers,
+            guildmates,
+            characters,
+            lobbies,
+            lobby_players,
+            api_tokens,
+            webhooks,
+            custom_contents,
+        } = self;
+
+        guildmates.retain(|g| servers.iter().any(|s| s.id == g.server_id));
+        characters.retain(|c| {
+            guildmates
+                .iter()
+                .any(|g| g.id == c.id && g.server_id == c.guild_id)
+        });
+        lobbies.retain(|l| {
+            guildmates
+                .iter()
+                .any(|g| g.id == l.lobby_master && g.server_id == l.guild_id)
+        });
+        lobby_players.retain(|p| {
+            lobbies.iter().any(|l| l.lobby_id == p.lobby_id)
+                && characters
+                    .iter()
+                    .any(|c| c.guild_id == p.guild_id && c.name == p.character_name)
+        });
+        api_tokens.retain(|t| servers.iter().any(|s| s.id == t.guild_id));
+        webhooks.retain(|w| servers.iter().any(|s| s.id == w.guild_id));
+        custom_contents.retain(|c| servers.iter().any(|s| s.id == c.guild_id));
+    }
+}
+
+fn not_found(what: &str) -> DbErr {
+    DbErr::RecordNotFound(format!("Couldn't find {what}."))
+}
+
+/// Error of the records that already exist, same for every backend.
+pub(crate) fn duplicate(what: &str) -> DbErr {
+    DbErr::Exec(format!("Duplicate key value for {what}."))
+}
+
+fn non_empty(characters: Vec<characters::Model>) -> Result<Vec<characters::Model>, DbErr> {
+    if characters.is_empty() {
+        Err(not_found("any character"))
+    } else {
+        Ok(characters)
+    }
+}
+
+fn sorted_by_ilvl(mut characters: Vec<characters::Model>) -> Vec<characters::Model> {
+    characters.sort_by(|a, b| b.item_level.cmp(&a.item_level));
+    characters
+}
+
+fn of_guild<T: Clone>(rows: &[T], guild_id_of: impl Fn(&T) -> &String, guild_id: &str) -> Vec<T> {
+    rows.iter()
+        .filter(|row| guild_id_of(row) == guild_id)
+        .cloned()
+        .collect()
+}
+
+fn lobby_player_model(
+    lobby_context: &LobbyContext,
+    player: &characters::Model,
+    slot: i32,
+) -> lobby_player::Model {
+    lobby_player::Model {
+        lobby_id: lobby_context.id,
+        guild_id: lobby_context.guild_id.to_string(),
+        player_id: player.id.clone(),
+        character_name: player.name.clone(),
+        active: true,
+        slot,
+    }
+}
+
+/// In-memory [`Storage`] for tests. Behaves like the database including the cascades,
+/// but doesn't emit webhook events.
+#[derive(Default)]
+pub struct MemoryStorage {
+    tables: Mutex<Tables>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn insert_server(
+        &self,
+        server_id: u64,
+        server_name: &str,
+        server_timezone: i32,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let id = server_id.to_string();
+        if tables.servers.iter().any(|s| s.id == id) {
+            return Err(duplicate("servers"));
+        }
+
+        let defaults = LobbySettings::default();
+        tables.servers.push(servers::Model {
+            id,
+            guild_name: server_name.to_string(),
+            timezone: server_timezone,
+            locale: DEFAULT_LOCALE.to_string(),
+            single_character: defaults.single_character,
+            conflict_minutes: defaults.conflict_minutes,
+        });
+        Ok(())
+    }
+
+    async fn insert_guildmate(
+        &self,
+        server_id: u64,
+        guildmate_id: u64,
+        guildmate_role: Role,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let (id, server_id) = (guildmate_id.to_string(), server_id.to_string());
+        if !tables.servers.iter().any(|s| s.id == server_id) {
+            return Err(DbErr::Exec("Server doesn't exist.".to_string()));
+        }
+        if tables
+            .guildmates
+            .iter()
+            .any(|g| g.id == id && g.server_id == server_id)
+        {
+            return Err(duplicate("guildmates"));
+        }
+
+        tables.guildmates.push(guildmates::Model {
+            id,
+            server_id,
+            role: guildmate_role,
+        });
+        Ok(())
+    }
+
+    async fn insert_character(
+        &self,
+        character_id: u64,
+        character_guild: u64,
+        character_name: &str,
+        character_class: Class,
+        character_item_level: i32,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let (id, guild_id) = (character_id.to_string(), character_guild.to_string());
+        if !tables
+            .guildmates
+            .iter()
+            .any(|g| g.id == id && g.server_id == guild_id)
+        {
+            return Err(DbErr::Exec("Guildmate doesn't exist.".to_string()));
+        }
+        if tables
+            .character_position(character_name, &guild_id)
+            .is_some()
+        {
+            return Err(duplicate("characters"));
+        }
+
+        tables.characters.push(characters::Model {
+            id,
+            guild_id,
+            name: character_name.to_string(),
+            class: character_class,
+            item_level: character_item_level,
+            last_updated: Utc::now(),
+        });
+        Ok(())
+    }
+
+    async fn get_server(&self, server_id: u64) -> Result<servers::Model, DbErr> {
+        let id = server_id.to_string();
+        self.tables
+            .lock()
+            .servers
+            .iter()
+            .find(|s| s.id == id)
+            .cloned()
+            .ok_or_else(|| not_found("server"))
+    }
+
+    async fn update_server_locale(&self, server_id: u64, locale: &str) -> Result<(), DbErr> {
+        let id = server_id.to_string();
+        let mut tables = self.tables.lock();
+        let server = tables
+            .servers
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| not_found("server"))?;
+        server.locale = locale.to_string();
+        Ok(())
+    }
+
+    async fn update_server_settings(
+        &self,
+        server_id: u64,
+        settings: &LobbySettings,
+    ) -> Result<(), DbErr> {
+        let id = server_id.to_string();
+        let mut tables = self.tables.lock();
+        let server = tables
+            .servers
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| not_found("server"))?;
+        server.single_character = settings.single_character;
+        server.conflict_minutes = settings.conflict_minutes;
+        Ok(())
+    }
+
+    async fn get_guildmate(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+    ) -> Result<guildmates::Model, DbErr> {
+        let (id, guild_id) = (guildmate_id.to_string(), guild_id.to_string());
+        self.tables
+            .lock()
+            .guildmates
+            .iter()
+            .find(|g| g.id == id && g.server_id == guild_id)
+            .cloned()
+            .ok_or_else(|| not_found("guildmate"))
+    }
+
+    async fn get_all_characters(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        self.get_all_character_by_ilvl(guildmate_id, guild_id, i32::MIN)
+            .await
+    }
+
+    async fn get_all_character_by_ilvl(
+        &self,
+        guildmate_id: u64,
+        guild_id: u64,
+        item_level: i32,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        let (id, guild_id) = (guildmate_id.to_string(), guild_id.to_string());
+        let characters = self
+            .tables
+            .lock()
+            .characters
+            .iter()
+            .filter(|c| c.id == id && c.guild_id == guild_id && c.item_level >= item_level)
+            .cloned()
+            .collect();
+
+        non_empty(sorted_by_ilvl(characters))
+    }
+
+    async fn get_single_character(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+    ) -> Result<characters::Model, DbErr> {
+        let tables = self.tables.lock();
+        tables
+            .character_position(character_name, &character_guild.to_string())
+            .map(|idx| tables.characters[idx].clone())
+            .ok_or_else(|| {
+                DbErr::RecordNotFound(format!("Couldn't find character named {character_name}."))
+            })
+    }
+
+    async fn get_guildmates_by_min_ilvl(
+        &self,
+        guild_id: u64,
+        item_level: i32,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        self.get_guildmates_by_min_ilvl_filter_out(guild_id, item_level, &vec![])
+            .await
+    }
+
+    async fn get_guild_characters(&self, guild_id: u64) -> Result<Vec<characters::Model>, DbErr> {
+        let guild_id = guild_id.to_string();
+        let characters = self
+            .tables
+            .lock()
+            .characters
+            .iter()
+            .filter(|c| c.guild_id == guild_id)
+            .cloned()
+            .collect();
+
+        Ok(sorted_by_ilvl(characters))
+    }
+
+    async fn get_guildmates_by_min_ilvl_filter_out(
+        &self,
+        guild_id: u64,
+        item_level: i32,
+        filtered_out: &Vec<characters::Model>,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        let guild_id = guild_id.to_string();
+        let characters = self
+            .tables
+            .lock()
+            .characters
+            .iter()
+            .filter(|c| {
+                c.guild_id == guild_id
+                    && c.item_level >= item_level
+                    && !filtered_out.iter().any(|f| f.id == c.id)
+            })
+            .cloned()
+            .collect();
+
+        non_empty(sorted_by_ilvl(characters))
+    }
+
+    async fn update_character(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+        character_class: Class,
+        character_item_level: i32,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let idx = tables
+            .character_position(character_name, &character_guild.to_string())
+            .ok_or_else(|| not_found("character"))?;
+
+        let character = &mut tables.characters[idx];
+        character.class = character_class;
+        character.item_level = character_item_level;
+        character.last_updated = Utc::now();
+        Ok(())
+    }
+
+    async fn update_ilvl(
+        &self,
+        character_name: &str,
+        character_guild: u64,
+        character_item_level: i32,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let idx = tables
+            .character_position(character_name, &character_guild.to_string())
+            .ok_or_else(|| not_found("character"))?;
+
+        let character = &mut tables.characters[idx];
+        character.item_level = character_item_level;
+        character.last_updated = Utc::now();
+        Ok(())
+    }
+
+    async fn remove_server(&self, server_id: u64) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let id = server_id.to_string();
+        let idx = tables
+            .servers
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or_else(|| not_found("server"))?;
+
+        tables.servers.remove(idx);
+        tables.cascade();
+        Ok(())
+    }
+
+    async fn remove_guildmate(&self, guildmate_id: u64, guild_id: u64) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let (id, guild_id) = (guildmate_id.to_string(), guild_id.to_string());
+        let idx = tables
+            .guildmates
+            .iter()
+            .position(|g| g.id == id && g.server_id == guild_id)
+            .ok_or_else(|| not_found("guildmate"))?;
+
+        tables.guildmates.remove(idx);
+        tables.cascade();
+        Ok(())
+    }
+
+    async fn remove_character(&self, character_name: &str, guild_id: u64) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let idx = tables
+            .character_position(character_name, &guild_id.to_string())
+            .ok_or_else(|| not_found("character"))?;
+
+        tables.characters.remove(idx);
+        tables.cascade();
+        Ok(())
+    }
+
+    async fn insert_lobby(&self, lobby_context: &LobbyContext) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        if tables
+            .lobbies
+            .iter()
+            .any(|l| l.lobby_id == lobby_context.id)
+        {
+            return Err(duplicate("lobby"));
+        }
+
+        tables.lobbies.push(lobby::Model {
+            lobby_id: lobby_context.id,
+            guild_id: lobby_context.guild_id.to_string(),
+            channel_id: lobby_context.channel_id.to_string(),
+            message_id: lobby_context.message_id.to_string(),
+            lobby_master: lobby_context.lobby_master.to_string(),
+            content: lobby_context.content_info().key.clone(),
+            created: Utc::now(),
+            scheduled: lobby_context.lobby_time,
+            active: true,
+            difficulty: lobby_context.options.difficulty.clone(),
+            gate_start: lobby_context.options.gate_start,
+            gate_end: lobby_context.options.gate_end,
+            note: lobby_context.note.clone(),
+            min_clears: lobby_context
+                .requirements
+                .min_clears
+                .map(|clears| clears as i32),
+            engravings: lobby_context.requirements.engravings_text(),
+            language: lobby_context.requirements.language.clone(),
+            voice: lobby_context.requirements.voice,
+            gold_run: lobby_context.requirements.gold_run,
+        });
+
+        for (player, slot) in lobby_context.active_players.iter().zip(0..) {
+            tables
+                .lobby_players
+                .push(lobby_player_model(lobby_context, player, slot));
+        }
+        Ok(())
+    }
+
+    async fn get_lobby(&self, lobby_id: Uuid) -> Result<lobby::Model, DbErr> {
+        self.tables
+            .lock()
+            .lobbies
+            .iter()
+            .find(|l| l.lobby_id == lobby_id)
+            .cloned()
+            .ok_or_else(|| not_found("lobby"))
+    }
+
+    async fn get_active_lobbies(&self) -> Result<Vec<lobby::Model>, DbErr> {
+        Ok(self
+            .tables
+            .lock()
+            .lobbies
+            .iter()
+            .filter(|l| l.active)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_active_guild_lobbies(&self, guild_id: u64) -> Result<Vec<lobby::Model>, DbErr> {
+        let guild_id = guild_id.to_string();
+        let mut lobbies: Vec<lobby::Model> = self
+            .tables
+            .lock()
+            .lobbies
+            .iter()
+            .filter(|l| l.active && l.guild_id == guild_id)
+            .cloned()
+            .collect();
+
+        lobbies.sort_by_key(|l| l.scheduled);
+        Ok(lobbies)
+    }
+
+    async fn disable_lobby(&self, lobby: &lobby::Model) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let stored = tables
+            .lobbies
+            .iter_mut()
+            .find(|l| l.lobby_id == lobby.lobby_id)
+            .ok_or_else(|| not_found("lobby"))?;
+        stored.active = false;
+
+        for player in tables
+            .lobby_players
+            .iter_mut()
+            .filter(|p| p.lobby_id == lobby.lobby_id)
+        {
+            player.active = false;
+        }
+        Ok(())
+    }
+
+    async fn update_lobby_schedule(
+        &self,
+        lobby_id: Uuid,
+        scheduled: Option<DateTime<Utc>>,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let stored = tables
+            .lobbies
+            .iter_mut()
+            .find(|l| l.lobby_id == lobby_id)
+            .ok_or_else(|| not_found("lobby"))?;
+
+        stored.scheduled = scheduled;
+        Ok(())
+    }
+
+    async fn insert_lobby_player(
+        &self,
+        lobby_context: &LobbyContext,
+        player: &characters::Model,
+    ) -> Result<(), DbErr> {
+        self.insert_lobby_players(lobby_context, vec![player.clone()])
+            .await
+    }
+
+    async fn insert_lobby_players(
+        &self,
+        lobby_context: &LobbyContext,
+        player_list: Vec<characters::Model>,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        for player in &player_list {
+            if tables
+                .lobby_players
+                .iter()
+                .any(|p| p.lobby_id == lobby_context.id && p.character_name == player.name)
+            {
+                return Err(duplicate("lobby_player"));
+            }
+        }
+
+        let taken: Vec<i32> = tables
+            .lobby_players
+            .iter()
+            .filter(|p| p.lobby_id == lobby_context.id)
+            .map(|p| p.slot)
+            .collect();
+        let slots = database::free_slots(&taken, player_list.len());
+        for (player, slot) in player_list.iter().zip(slots) {
+            tables
+                .lobby_players
+                .push(lobby_player_model(lobby_context, player, slot));
+        }
+        Ok(())
+    }
+
+    async fn get_lobby_player(
+        &self,
+        lobby_id: Uuid,
+        player_name: &str,
+    ) -> Result<lobby_player::Model, DbErr> {
+        self.tables
+            .lock()
+            .lobby_players
+            .iter()
+            .find(|p| p.lobby_id == lobby_id && p.character_name == player_name)
+            .cloned()
+            .ok_or_else(|| not_found("player"))
+    }
+
+    async fn get_lobby_players(&self, lobby_id: Uuid) -> Result<Vec<lobby_player::Model>, DbErr> {
+        let mut players: Vec<_> = self
+            .tables
+            .lock()
+            .lobby_players
+            .iter()
+            .filter(|p| p.lobby_id == lobby_id)
+            .cloned()
+            .collect();
+        players.sort_by_key(|p| p.slot);
+        Ok(players)
+    }
+
+    async fn remove_lobby_player(&self, lobby_id: Uuid, player_name: &str) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let idx = tables
+            .lobby_players
+            .iter()
+            .position(|p| p.lobby_id == lobby_id && p.character_name == player_name)
+            .ok_or_else(|| not_found("player"))?;
+
+        let removed = tables.lobby_players.remove(idx);
+        for p in tables.lobby_players.iter_mut() {
+            if p.lobby_id == lobby_id && p.slot > removed.slot {
+                p.slot -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    async fn swap_lobby_player(
+        &self,
+        lobby_context: &LobbyContext,
+        player_name: &str,
+        player: &characters::Model,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let idx = tables
+            .lobby_players
+            .iter()
+            .position(|p| p.lobby_id == lobby_context.id && p.character_name == player_name)
+            .ok_or_else(|| not_found("player"))?;
+        if tables
+            .lobby_players
+            .iter()
+            .any(|p| p.lobby_id == lobby_context.id && p.character_name == player.name)
+        {
+            return Err(duplicate("lobby_player"));
+        }
+
+        // Rows have no order in the database either, only the slot is kept
+        let swapped = tables.lobby_players.remove(idx);
+        tables
+            .lobby_players
+            .push(lobby_player_model(lobby_context, player, swapped.slot));
+        Ok(())
+    }
+
+    async fn get_active_characters_joined(
+        &self,
+        lobby_id: Uuid,
+    ) -> Result<Vec<characters::Model>, DbErr> {
+        let tables = self.tables.lock();
+        let mut players: Vec<_> = tables
+            .lobby_players
+            .iter()
+            .filter(|p| p.lobby_id == lobby_id)
+            .collect();
+        players.sort_by_key(|p| p.slot);
+        Ok(players
+            .into_iter()
+            .filter_map(|p| {
+                tables
+                    .character_position(&p.character_name, &p.guild_id)
+                    .map(|idx| tables.characters[idx].clone())
+            })
+            .collect())
+    }
+
+    async fn count_clears(
+        &self,
+        guild_id: u64,
+        character_name: &str,
+        content: &str,
+    ) -> Result<usize, DbErr> {
+        let tables = self.tables.lock();
+        let guild_id = guild_id.to_string();
+        Ok(tables
+            .lobby_players
+            .iter()
+            .filter(|p| p.guild_id == guild_id && p.character_name == character_name)
+            .filter(|p| {
+                tables
+                    .lobbies
+                    .iter()
+                    .any(|l| l.lobby_id == p.lobby_id && !l.active && l.content == content)
+            })
+            .count())
+    }
+
+    async fn upsert_api_token(&self, guild_id: u64, token_hash: &str) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let guild_id = guild_id.to_string();
+        tables.api_tokens.retain(|t| t.guild_id != guild_id);
+        tables.api_tokens.push(api_tokens::Model {
+            guild_id,
+            token_hash: token_hash.to_string(),
+            created: Utc::now(),
+        });
+        Ok(())
+    }
+
+    async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<api_tokens::Model, DbErr> {
+        self.tables
+            .lock()
+            .api_tokens
+            .iter()
+            .find(|t| t.token_hash == token_hash)
+            .cloned()
+            .ok_or_else(|| not_found("api token"))
+    }
+
+    async fn get_webhooks(&self, guild_id: &str) -> Result<Vec<webhooks::Model>, DbErr> {
+        Ok(self
+            .tables
+            .lock()
+            .webhooks
+            .iter()
+            .filter(|w| w.guild_id == guild_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn insert_webhook(&self, guild_id: u64, url: &str, secret: &str) -> Result<(), DbErr> {
+        self.tables.lock().webhooks.push(webhooks::Model {
+            webhook_id: Uuid::new_v4(),
+            guild_id: guild_id.to_string(),
+            url: url.to_string(),
+            secret: secret.to_string(),
+            created: Utc::now(),
+        });
+        Ok(())
+    }
+
+    async fn remove_webhook(&self, guild_id: u64, url: &str) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let guild_id = guild_id.to_string();
+        let before = tables.webhooks.len();
+        tables
+            .webhooks
+            .retain(|w| !(w.guild_id == guild_id && w.url == url));
+
+        if tables.webhooks.len() == before {
+            Err(not_found("webhook"))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn get_custom_contents(
+        &self,
+        guild_id: u64,
+    ) -> Result<Vec<custom_content::Model>, DbErr> {
+        let mut custom_contents = of_guild(
+            &self.tables.lock().custom_contents,
+            |c| &c.guild_id,
+            &guild_id.to_string(),
+        );
+        custom_contents.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(custom_contents)
+    }
+
+    async fn get_custom_content(&self, content_id: Uuid) -> Result<custom_content::Model, DbErr> {
+        self.tables
+            .lock()
+            .custom_contents
+            .iter()
+            .find(|c| c.content_id == content_id)
+            .cloned()
+            .ok_or_else(|| not_found("custom content"))
+    }
+
+    async fn insert_custom_content(
+        &self,
+        custom_content: custom_content::Model,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        if !tables
+            .servers
+            .iter()
+            .any(|s| s.id == custom_content.guild_id)
+        {
+            return Err(not_found("server"));
+        }
+        if tables.custom_contents.iter().any(|c| {
+            c.content_id == custom_content.content_id
+                || (c.guild_id == custom_content.guild_id && c.name == custom_content.name)
+        }) {
+            return Err(duplicate("custom content"));
+        }
+
+        tables.custom_contents.push(custom_content);
+        Ok(())
+    }
+
+    async fn update_custom_content(
+        &self,
+        custom_content: custom_content::Model,
+    ) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        if tables.custom_contents.iter().any(|c| {
+            c.content_id != custom_content.content_id
+                && c.guild_id == custom_content.guild_id
+                && c.name == custom_content.name
+        }) {
+            return Err(duplicate("custom content"));
+        }
+
+        let existing = tables
+            .custom_contents
+            .iter_mut()
+            .find(|c| c.content_id == custom_content.content_id)
+            .ok_or_else(|| not_found("custom content"))?;
+        *existing = custom_content::Model {
+            guild_id: existing.guild_id.clone(),
+            created: existing.created,
+            ..custom_content
+        };
+        Ok(())
+    }
+
+    async fn remove_custom_content(&self, content_id: Uuid) -> Result<(), DbErr> {
+        let mut tables = self.tables.lock();
+        let before = tables.custom_contents.len();
+        tables
+            .custom_contents
+            .retain(|c| c.content_id != content_id);
+
+        if tables.custom_contents.len() == before {
+            Err(not_found("custom content"))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn export_guild(&self, guild_id: u64) -> Result<GuildArchive, DbErr> {
+        let tables = self.tables.lock();
+        let guild_id = guild_id.to_string();
+        let server = tables
+            .servers
+            .iter()
+            .find(|s| s.id == guild_id)
+            .cloned()
+            .ok_or_else(|| not_found("server"))?;
+
+        Ok(GuildArchive {
+            version: ARCHIVE_VERSION,
+            exported: Utc::now(),
+            server,
+            guildmates: of_guild(&tables.guildmates, |g| &g.server_id, &guild_id),
+            characters: of_guild(&tables.characters, |c| &c.guild_id, &guild_id),
+            lobbies: of_guild(&tables.lobbies, |l| &l.guild_id, &guild_id),
+            lobby_players: of_guild(&tables.lobby_players, |p| &p.guild_id, &guild_id),
+            webhooks: backup::without_secrets(of_guild(
+                &tables.webhooks,
+                |w| &w.guild_id,
+                &guild_id,
+            )),
+            custom_contents: of_guild(&tables.custom_contents, |c| &c.guild_id, &guild_id),
+        })
+    }
+
+    async fn import_guild(&self, mut archive: GuildArchive) -> Result<(), DbErr> {
+        archive.validate().map_err(DbErr::Custom)?;
+
+        let mut tables = self.tables.lock();
+        let guild_id = archive.server.id.clone();
+        archive.restore_secrets(&of_guild(&tables.webhooks, |w| &w.guild_id, &guild_id));
+        tables.servers.retain(|s| s.id != guild_id);
+        tables.cascade();
+
+        tables.servers.push(archive.server);
+        tables.guildmates.extend(archive.guildmates);
+        tables.characters.extend(archive.characters);
+        tables.lobbies.extend(archive.lobbies);
+        tables.lobby_players.extend(archive.lobby_players);
+        tables.webhooks.extend(archive.webhooks);
+        tables.custom_contents.extend(archive.custom_contents);
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+//! Outgoing webhooks for lobby lifecycle events.
+//!
+//! Guilds register URLs with `/add_webhook`. Each event is posted as JSON to every URL of the
+//! guild with the `X-Ark-Event` header set to the event name and `X-Ark-Signature` set to
+//! `sha256=<hex HMAC-SHA256 of the body>` using the secret of the webhook. Failed deliveries
+//! are retried with exponential backoff in a background task so the caller is never blocked.
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use entity::{characters, lobby};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::database::get_webhooks;
+
+pub const SIGNATURE_HEADER: &str = "X-Ark-Signature";
+pub const EVENT_HEADER: &str = "X-Ark-Event";
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("Couldn't build webhook client")
+});
+
+#[derive(Serialize, Clone)]
+pub struct PlayerPayload {
+    pub user_id: String,
+    pub character_name: String,
+}
+
+impl From<&characters::Model> for PlayerPayload {
+    fn from(character: &characters::Model) -> Self {
+        Self {
+            user_id: character.id.clone(),
+            character_name: character.name.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    LobbyCreated {
+        lobby: lobby::Model,
+        players: Vec<PlayerPayload>,
+    },
+    PlayerJoined {
+        lobby_id: uuid::Uuid,
+        player: PlayerPayload,
+    },
+    PlayerLeft {
+        lobby_id: uuid::Uuid,
+        player: PlayerPayload,
+    },
+    /// Player changed their character in the lobby without leaving.
+    PlayerSwapped {
+        lobby_id: uuid::Uuid,
+        left: PlayerPayload,
+        joined: PlayerPayload,
+    },
+    LobbyFull {
+        lobby_id: uuid::Uuid,
+    },
+    LobbyRescheduled {
+        lobby_id: uuid::Uuid,
+        scheduled: Option<DateTime<Utc>>,
+    },
+    LobbyFinished {
+        lobby_id: uuid::Uuid,
+    },
+}
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::LobbyCreated { .. } => "lobby_created",
+            WebhookEvent::PlayerJoined { .. } => "player_joined",
+            WebhookEvent::PlayerLeft { .. } => "player_left",
+            WebhookEvent::PlayerSwapped { .. } => "player_swapped",
+            WebhookEvent::LobbyFull { .. } => "lobby_full",
+            WebhookEvent::LobbyRescheduled { .. } => "lobby_rescheduled",
+            WebhookEvent::LobbyFinished { .. } => "lobby_finished",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    guild_id: &'a str,
+    timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry. Doubles every attempt starting from `base_delay`.
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.base_delay * 2u32.saturating_pow(retry)
+    }
+}
+
+/// Returns the value of the signature header for the body.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Generates a new secret for signing the payloads.
+pub fn generate_secret() -> String {
+    uuid::Uuid::new_v4().to_simple().to_string()
+}
+
+/// Serializes the event as it is sent to the receivers.
+pub fn payload(guild_id: &str, event: &WebhookEvent) -> Vec<u8> {
+    serde_json::to_vec(&Envelope {
+        guild_id,
+        timestamp: Utc::now(),
+        event,
+    })
+    .expect("Webhook payloads are always serializable")
+}
+
+/// Posts the payload to the url, retrying with backoff until it gets a success status.
+///
+/// Returns the number of attempts made on success and the last error otherwise.
+pub async fn deliver(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event_name: &str,
+    body: &[u8],
+    policy: RetryPolicy,
+) -> Result<u32, String> {
+    let signature = sign(secret, body);
+    let mut last_error = String::new();
+
+    for attempt in 0..policy.max_attempts {
+        if attempt > 0 {
+            tokio::time::sleep(policy.delay(attempt - 1)).await;
+        }
+
+        let response = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event_name)
+            .header(SIGNATURE_HEADER, &signature)
+            .body(body.to_vec())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => return Ok(attempt + 1),
+            Ok(response) => last_error = format!("receiver returned {}", response.status()),
+            Err(err) => last_error = err.to_string(),
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Sends the event to every webhook of the guild in the background.
+pub fn emit(guild_id: &str, event: WebhookEvent, db: &DatabaseConnection) {
+    let guild_id = guild_id.to_string();
+    let db = db.clone();
+
+    tokio::spawn(async move {
+        let webhooks = match get_webhooks(&guild_id, &db).await {
+            Ok(webhooks) => webhooks,
+            Err(err) => {
+                tracing::error!(%guild_id, "Couldn't get webhooks: {err}");
+                return;
+            }
+        };
+
+        if webhooks.is_empty() {
+            return;
+        }
+
+        let body = payload(&guild_id, &event);
+
+        for webhook in webhooks {
+            let body = body.clone();
+            let event_name = event.name();
+            tokio::spawn(async move {
+                if let Err(err) = deliver(
+                    &CLIENT,
+                    &webhook.url,
+                    &webhook.secret,
+                    event_name,
+                    &body,
+                    RetryPolicy::default(),
+                )
+                .await
+                {
+                    tracing::warn!(
+                        webhook_id = %webhook.webhook_id,
+                        "Couldn't deliver {event_name}: {err}"
+                    );
+                }
+            });
+        }
+    });
+}
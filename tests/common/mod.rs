@@ -0,0 +1,79 @@
+//! Fixtures shared by the integration tests.
+// Every test file uses a different part of the fixtures
+#![allow(dead_code)]
+
+use ark_guild_bot::{
+    commands::lobby::{command::State, context::LobbyContext},
+    info::contents,
+    locale::tr,
+    storage::{MemoryStorage, Storage},
+    Class, Role,
+};
+
+pub const GUILD: u64 = 1;
+pub const MASTER: u64 = 10;
+pub const PLAYER: u64 = 20;
+
+/// Guild with the master and a player with two characters.
+pub async fn seeded_storage() -> MemoryStorage {
+    let db = MemoryStorage::new();
+    db.insert_server(GUILD, "Guild", 3).await.unwrap();
+    db.insert_guildmate(GUILD, MASTER, Role::GuildMaster)
+        .await
+        .unwrap();
+    db.insert_guildmate(GUILD, PLAYER, Role::Guildmate)
+        .await
+        .unwrap();
+
+    db.insert_character(MASTER, GUILD, "Master", Class::Paladin, 1370)
+        .await
+        .unwrap();
+    db.insert_character(PLAYER, GUILD, "Main", Class::Sorceress, 1340)
+        .await
+        .unwrap();
+    db.insert_character(PLAYER, GUILD, "Alt", Class::Bard, 1100)
+        .await
+        .unwrap();
+    db
+}
+
+/// Lobby of the master at the start of the setup.
+pub fn lobby_context() -> LobbyContext {
+    LobbyContext {
+        id: uuid::Uuid::new_v4(),
+        id_as_string: String::new(),
+        guild_id: GUILD,
+        channel_id: 2,
+        message_id: 3,
+        lobby_master: MASTER,
+        locale: "en".to_string(),
+        state: State::ContentSelection,
+        content: None,
+        content_info: None,
+        options: Default::default(),
+        lobby_time: None,
+        note: None,
+        requirements: Default::default(),
+        settings: Default::default(),
+        players: vec![],
+        active_players: vec![],
+        player_list: vec![],
+    }
+}
+
+/// Line of an empty slot in the player list of an English lobby.
+pub fn empty_slot() -> String {
+    format!("\n{}", tr("en", "lobby.empty_slot"))
+}
+
+/// Lobby of the master collecting the players of the content.
+pub fn lobby_context_with(key: &str) -> LobbyContext {
+    let content_info = contents().get(key).unwrap();
+    LobbyContext {
+        state: State::CollectPlayers,
+        content: Some(content_info.content_type.as_str().try_into().unwrap()),
+        player_list: vec![empty_slot(); content_info.content_size],
+        content_info: Some(content_info),
+        ..lobby_context()
+    }
+}
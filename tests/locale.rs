@@ -0,0 +1,166 @@
+use std::{fs, path::Path};
+
+use ark_guild_bot::locale::{keys, missing_keys, resolve, tr, tr_with, LOCALES};
+
+/// Calls whose text is shown to the users.
+const REPLY_CALLS: [&str; 10] = [
+    ".say(",
+    ".content(",
+    ".description(",
+    ".title(",
+    ".label(",
+    ".placeholder(",
+    ".field(",
+    ".respond_ephemeral(",
+    "Error::permission(",
+    " reply(",
+];
+
+#[test]
+fn every_locale_has_every_message() {
+    for (locale, _) in LOCALES {
+        assert_eq!(missing_keys(locale), Vec::<&str>::new(), "{locale}");
+    }
+}
+
+#[test]
+fn discord_locales_are_resolved_by_language() {
+    assert_eq!(resolve("en-US"), Some("en"));
+    assert_eq!(resolve("tr"), Some("tr"));
+    assert_eq!(resolve("de"), None);
+}
+
+#[test]
+fn messages_fall_back_to_english() {
+    assert_eq!(tr("de", "lobby.join"), "Join Lobby!");
+    assert_eq!(tr("tr", "lobby.join"), "Lobiye Katıl!");
+    assert_eq!(tr("tr", "no.such.key"), "no.such.key");
+}
+
+#[test]
+fn placeholders_are_filled() {
+    assert_eq!(
+        tr_with("en", "lobby.next", &[("page", &2), ("pages", &3)]),
+        "Next (2/3)"
+    );
+}
+
+#[test]
+fn replies_are_in_the_catalogs() {
+    let keys = keys();
+    let mut literals = vec![];
+    collect_reply_literals(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+        &mut literals,
+    );
+
+    let untranslated: Vec<_> = literals
+        .into_iter()
+        .filter(|(_, literal)| {
+            !keys.iter().any(|key| key == &literal.as_str()) && has_words(literal)
+        })
+        .collect();
+    assert_eq!(untranslated, Vec::<(String, String)>::new());
+}
+
+fn collect_reply_literals(dir: &Path, literals: &mut Vec<(String, String)>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_reply_literals(&path, literals);
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            let source = fs::read_to_string(&path).unwrap();
+            for call in REPLY_CALLS {
+                for (start, _) in source.match_indices(call) {
+                    let line = source[..start].lines().count();
+                    for literal in call_literals(&source[start + call.len()..]) {
+                        literals.push((format!("{}:{line}", path.display()), literal));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// String literals in the arguments of the call, except the ones given to `tr` and `tr_with`.
+fn call_literals(args: &str) -> Vec<String> {
+    let mut literals = vec![];
+    scan_call(args, &mut Some(&mut literals));
+    literals
+}
+
+/// Returns the end of the call whose arguments start at `args`, collecting its literals if asked.
+fn scan_call(args: &str, literals: &mut Option<&mut Vec<String>>) -> usize {
+    let bytes = args.as_bytes();
+    let mut depth = 1;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let after_ident = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+        if let Some(call) = ["tr(", "tr_with("]
+            .iter()
+            .find(|call| !after_ident && bytes[i..].starts_with(call.as_bytes()))
+        {
+            i += call.len();
+            i += scan_call(&args[i..], &mut None);
+        } else {
+            match bytes[i] {
+                b'"' => {
+                    let end = literal_end(bytes, i);
+                    // Patterns of a match in the arguments are not shown
+                    if !args[end + 1..].trim_start().starts_with("=>") {
+                        if let Some(literals) = literals {
+                            literals.push(args[i + 1..end].to_string());
+                        }
+                    }
+                    i = end;
+                }
+                b'\'' if bytes.get(i + 1) == Some(&b'\\') || bytes.get(i + 2) == Some(&b'\'') => {
+                    i += bytes[i + 2..].iter().position(|&b| b == b'\'').unwrap() + 2;
+                }
+                b'(' => depth += 1,
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+
+    i
+}
+
+/// Index of the quote closing the literal starting at `start`.
+fn literal_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while bytes[i] != b'"' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    i
+}
+
+/// Whether the literal has any text apart from placeholders and escapes.
+fn has_words(literal: &str) -> bool {
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            '\\' => {
+                chars.next();
+            }
+            c if c.is_ascii_alphabetic() => return true,
+            _ => {}
+        }
+    }
+    false
+}
@@ -0,0 +1,389 @@
+mod common;
+
+use std::sync::Arc;
+
+use ark_guild_bot::{
+    commands::lobby::supervisor::{load_lobby_context, LobbySupervisor},
+    info::contents,
+    storage::Storage,
+    Class,
+};
+use chrono::{Duration, Utc};
+use common::{empty_slot, lobby_context_with, seeded_storage, GUILD, PLAYER};
+use sea_orm::DbErr;
+
+#[tokio::test]
+async fn characters_are_sorted_by_item_level() {
+    let db = seeded_storage().await;
+
+    let characters = db.get_all_characters(PLAYER, GUILD).await.unwrap();
+    let names: Vec<&str> = characters.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["Main", "Alt"]);
+
+    let characters = db
+        .get_all_character_by_ilvl(PLAYER, GUILD, 1200)
+        .await
+        .unwrap();
+    assert_eq!(characters.len(), 1);
+
+    assert!(matches!(
+        db.get_all_character_by_ilvl(PLAYER, GUILD, 1400).await,
+        Err(DbErr::RecordNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn duplicate_character_is_rejected() {
+    let db = seeded_storage().await;
+
+    assert!(db
+        .insert_character(PLAYER, GUILD, "Main", Class::Bard, 1)
+        .await
+        .is_err());
+
+    db.update_ilvl("Main", GUILD, 1370).await.unwrap();
+    assert_eq!(
+        db.get_single_character("Main", GUILD)
+            .await
+            .unwrap()
+            .item_level,
+        1370
+    );
+}
+
+#[tokio::test]
+async fn removing_server_cascades() {
+    let db = seeded_storage().await;
+    let lobby_context = lobby_context_with("Urnil");
+    db.insert_lobby(&lobby_context).await.unwrap();
+
+    db.remove_server(GUILD).await.unwrap();
+
+    assert!(matches!(
+        db.get_guildmate(PLAYER, GUILD).await,
+        Err(DbErr::RecordNotFound(_))
+    ));
+    assert!(db.get_guild_characters(GUILD).await.unwrap().is_empty());
+    assert!(db.get_active_lobbies().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn adding_player_filters_out_their_characters() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context_with("Urnil");
+    lobby_context.players = db
+        .get_guildmates_by_min_ilvl(GUILD, lobby_context.content_info().ilvl_req)
+        .await
+        .unwrap();
+    assert_eq!(lobby_context.players.len(), 3);
+
+    let idx = lobby_context
+        .players
+        .iter()
+        .position(|c| c.name == "Main")
+        .unwrap();
+    lobby_context.add_active_player(idx, &db).await;
+
+    assert!(lobby_context.is_active_player(PLAYER));
+    assert!(lobby_context.player_list[0].contains("Main"));
+    assert!(lobby_context
+        .players
+        .iter()
+        .all(|c| c.id != PLAYER.to_string()));
+
+    lobby_context.remove_active_player(0, &db).await;
+
+    assert!(!lobby_context.is_active_player(PLAYER));
+    assert_eq!(lobby_context.player_list[0], empty_slot());
+    assert_eq!(lobby_context.players.len(), 3);
+}
+
+#[tokio::test]
+async fn users_can_add_alts_without_single_character() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context_with("Urnil");
+    lobby_context.settings.single_character = false;
+    lobby_context.refresh_players(&db).await;
+
+    let idx = lobby_context
+        .players
+        .iter()
+        .position(|c| c.name == "Main")
+        .unwrap();
+    lobby_context.add_active_player(idx, &db).await;
+
+    let names: Vec<&str> = lobby_context
+        .players
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect();
+    assert!(names.contains(&"Alt"));
+    assert!(!names.contains(&"Main"));
+}
+
+#[tokio::test]
+async fn characters_in_lobbies_at_the_same_time_are_busy() {
+    let db = seeded_storage().await;
+    let time = Utc::now() + Duration::days(1);
+    let mut other = lobby_context_with("Urnil");
+    other.lobby_time = Some(time);
+    let main = db.get_single_character("Main", GUILD).await.unwrap();
+    other.add_active_player_by_model(main.clone());
+    db.insert_lobby(&other).await.unwrap();
+
+    let mut lobby_context = lobby_context_with("Urnil");
+    lobby_context.lobby_time = Some(time + Duration::minutes(30));
+    lobby_context.refresh_players(&db).await;
+    assert!(lobby_context.players.iter().all(|c| c.name != "Main"));
+    assert!(lobby_context.players.iter().any(|c| c.name == "Alt"));
+
+    lobby_context.add_active_player_by_model(main);
+    assert!(lobby_context.has_busy_players(&db).await.unwrap());
+
+    for (lobby_time, conflict_minutes) in [(time + Duration::minutes(90), 60), (time, 0)] {
+        lobby_context.lobby_time = Some(lobby_time);
+        lobby_context.settings.conflict_minutes = conflict_minutes;
+        assert!(!lobby_context.has_busy_players(&db).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn lobby_gates_decide_item_level_and_are_stored() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context_with("Urnil");
+    lobby_context.set_content_info(contents().get("Argos"));
+    assert_eq!(lobby_context.ilvl_req(), 1400);
+
+    lobby_context.options.gate_end = Some(1);
+    assert_eq!(lobby_context.ilvl_req(), 1370);
+    db.insert_lobby(&lobby_context).await.unwrap();
+
+    let lobby = db.get_lobby(lobby_context.id).await.unwrap();
+    assert_eq!(lobby.content, "Argos");
+    assert_eq!((lobby.gate_start, lobby.gate_end), (Some(1), Some(1)));
+}
+
+#[tokio::test]
+async fn lobby_players_follow_the_lobby() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context_with("Urnil");
+    let master = db.get_single_character("Master", GUILD).await.unwrap();
+    lobby_context.add_active_player_by_model(master);
+    db.insert_lobby(&lobby_context).await.unwrap();
+
+    let main = db.get_single_character("Main", GUILD).await.unwrap();
+    db.insert_lobby_player(&lobby_context, &main).await.unwrap();
+    assert!(db.insert_lobby_player(&lobby_context, &main).await.is_err());
+
+    let joined = db
+        .get_active_characters_joined(lobby_context.id)
+        .await
+        .unwrap();
+    assert_eq!(joined.len(), 2);
+
+    db.remove_lobby_player(lobby_context.id, "Main")
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_lobby_players(lobby_context.id).await.unwrap().len(),
+        1
+    );
+
+    let lobby = db.get_lobby(lobby_context.id).await.unwrap();
+    db.disable_lobby(&lobby).await.unwrap();
+    assert!(db.get_active_lobbies().await.unwrap().is_empty());
+    assert!(db
+        .get_lobby_players(lobby_context.id)
+        .await
+        .unwrap()
+        .iter()
+        .all(|p| !p.active));
+}
+
+#[tokio::test]
+async fn swapped_character_keeps_the_slot() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context_with("Urnil");
+    for name in ["Main", "Master"] {
+        let character = db.get_single_character(name, GUILD).await.unwrap();
+        lobby_context.add_active_player_by_model(character);
+    }
+    db.insert_lobby(&lobby_context).await.unwrap();
+
+    let alt = db.get_single_character("Alt", GUILD).await.unwrap();
+    db.swap_lobby_player(&lobby_context, "Main", &alt)
+        .await
+        .unwrap();
+    assert!(lobby_context.swap_active_player("Main", alt.clone()));
+    assert!(!lobby_context.swap_active_player("Main", alt.clone()));
+
+    assert!(lobby_context.player_list[0].contains("Alt"));
+
+    // Slots are kept when the lobby is loaded again after a restart
+    let lobby = db.get_lobby(lobby_context.id).await.unwrap();
+    let reloaded = load_lobby_context(&lobby, &db).await.unwrap();
+    let names: Vec<&str> = reloaded
+        .active_players
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect();
+    assert_eq!(names, ["Alt", "Master"]);
+    assert!(reloaded.player_list[0].contains("Alt"));
+
+    let master = db.get_single_character("Master", GUILD).await.unwrap();
+    assert!(matches!(
+        db.swap_lobby_player(&lobby_context, "Alt", &master).await,
+        Err(DbErr::Exec(_))
+    ));
+    assert!(matches!(
+        db.swap_lobby_player(&lobby_context, "Main", &alt).await,
+        Err(DbErr::RecordNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn slots_follow_the_players_who_leave_and_join() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context_with("Urnil");
+    for name in ["Main", "Master"] {
+        let character = db.get_single_character(name, GUILD).await.unwrap();
+        lobby_context.add_active_player_by_model(character);
+    }
+    db.insert_lobby(&lobby_context).await.unwrap();
+
+    db.remove_lobby_player(lobby_context.id, "Main")
+        .await
+        .unwrap();
+    lobby_context.remove_active_player_without_filter(0);
+    let alt = db.get_single_character("Alt", GUILD).await.unwrap();
+    db.insert_lobby_player(&lobby_context, &alt).await.unwrap();
+    lobby_context.add_active_player_by_model(alt);
+
+    let slots: Vec<(String, i32)> = db
+        .get_lobby_players(lobby_context.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| (p.character_name, p.slot))
+        .collect();
+    assert_eq!(slots, [("Master".to_string(), 0), ("Alt".to_string(), 1)]);
+
+    let lobby = db.get_lobby(lobby_context.id).await.unwrap();
+    let reloaded = load_lobby_context(&lobby, &db).await.unwrap();
+    assert_eq!(reloaded.player_list, lobby_context.player_list);
+}
+
+#[tokio::test]
+async fn lobbies_of_removed_contents_are_disabled_at_start() {
+    let db: &'static _ = Box::leak(Box::new(seeded_storage().await));
+    let lobby_context = lobby_context_with("Urnil");
+    db.insert_lobby(&lobby_context).await.unwrap();
+
+    let mut removed = lobby_context_with("Urnil");
+    let mut content_info = removed.content_info().clone();
+    content_info.key = "Removed".to_string();
+    removed.set_content_info(Some(Arc::new(content_info)));
+    db.insert_lobby(&removed).await.unwrap();
+
+    let supervisor = LobbySupervisor::start(db).await.unwrap();
+
+    assert_eq!(supervisor.active_lobbies(), 1);
+    assert!(supervisor
+        .context(&lobby_context.id.to_hyphenated().to_string())
+        .is_some());
+    assert!(!db.get_lobby(removed.id).await.unwrap().active);
+}
+
+#[tokio::test]
+async fn clears_are_finished_lobbies_of_the_content() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context_with("Urnil");
+    lobby_context.requirements.min_clears = Some(1);
+    let main = db.get_single_character("Main", GUILD).await.unwrap();
+    lobby_context.add_active_player_by_model(main.clone());
+    db.insert_lobby(&lobby_context).await.unwrap();
+
+    let eligible = lobby_context
+        .requirements
+        .with_enough_clears(vec![main.clone()], GUILD, "Urnil", &db)
+        .await
+        .unwrap();
+    assert!(eligible.is_empty());
+
+    let lobby = db.get_lobby(lobby_context.id).await.unwrap();
+    assert_eq!(lobby.min_clears, Some(1));
+    db.disable_lobby(&lobby).await.unwrap();
+
+    assert_eq!(db.count_clears(GUILD, "Main", "Urnil").await.unwrap(), 1);
+    assert_eq!(db.count_clears(GUILD, "Main", "Argos").await.unwrap(), 0);
+    let eligible = lobby_context
+        .requirements
+        .with_enough_clears(vec![main], GUILD, "Urnil", &db)
+        .await
+        .unwrap();
+    assert_eq!(eligible.len(), 1);
+}
+
+#[tokio::test]
+async fn restoring_backup_replaces_guild_data() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context_with("Urnil");
+    let master = db.get_single_character("Master", GUILD).await.unwrap();
+    lobby_context.add_active_player_by_model(master);
+    db.insert_lobby(&lobby_context).await.unwrap();
+
+    let archive = db.export_guild(GUILD).await.unwrap();
+    assert!(archive.validate().is_ok());
+    assert_eq!(archive.characters.len(), 3);
+    assert_eq!(archive.lobby_players.len(), 1);
+
+    db.remove_server(GUILD).await.unwrap();
+    db.insert_server(GUILD, "New Guild", 0).await.unwrap();
+
+    db.import_guild(archive).await.unwrap();
+
+    assert_eq!(db.get_server(GUILD).await.unwrap().guild_name, "Guild");
+    assert_eq!(db.get_guild_characters(GUILD).await.unwrap().len(), 3);
+    assert_eq!(db.get_active_lobbies().await.unwrap().len(), 1);
+    assert_eq!(
+        db.get_lobby_players(lobby_context.id).await.unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn backup_leaves_out_webhook_secrets() {
+    let db = seeded_storage().await;
+    db.insert_webhook(GUILD, "https://example.com/hook", "secret")
+        .await
+        .unwrap();
+
+    let archive = db.export_guild(GUILD).await.unwrap();
+    assert!(archive.webhooks[0].secret.is_empty());
+
+    // Existing webhooks keep their secrets
+    db.import_guild(archive).await.unwrap();
+    let webhooks = db.get_webhooks(&GUILD.to_string()).await.unwrap();
+    assert_eq!(webhooks[0].secret, "secret");
+
+    let archive = db.export_guild(GUILD).await.unwrap();
+    db.remove_server(GUILD).await.unwrap();
+    db.import_guild(archive).await.unwrap();
+    let webhooks = db.get_webhooks(&GUILD.to_string()).await.unwrap();
+    assert!(!webhooks[0].secret.is_empty());
+    assert_ne!(webhooks[0].secret, "secret");
+}
+
+#[tokio::test]
+async fn inconsistent_backup_is_rejected() {
+    let db = seeded_storage().await;
+    let mut archive = db.export_guild(GUILD).await.unwrap();
+    archive.characters[0].guild_id = "2".to_string();
+
+    assert!(archive.validate().is_err());
+    assert!(matches!(
+        db.import_guild(archive).await,
+        Err(DbErr::Custom(_))
+    ));
+    assert_eq!(db.get_guild_characters(GUILD).await.unwrap().len(), 3);
+}
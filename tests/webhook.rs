@@ -0,0 +1,196 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use ark_guild_bot::webhook::{
+    deliver, payload, sign, RetryPolicy, WebhookEvent, EVENT_HEADER, SIGNATURE_HEADER,
+};
+use hmac::{Hmac, Mac};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use parking_lot::Mutex;
+use sha2::Sha256;
+
+struct Recieved {
+    event: String,
+    signature: String,
+    body: Vec<u8>,
+}
+
+struct Receiver {
+    addr: SocketAddr,
+    attempts: Arc<AtomicU32>,
+    recieved: Arc<Mutex<Vec<Recieved>>>,
+}
+
+/// Starts a local stand-in receiver that fails the first `failures` requests.
+fn start_receiver(failures: u32) -> Receiver {
+    let attempts = Arc::new(AtomicU32::new(0));
+    let recieved = Arc::new(Mutex::new(vec![]));
+
+    let make_service = make_service_fn({
+        let attempts = attempts.clone();
+        let recieved = recieved.clone();
+        move |_| {
+            let attempts = attempts.clone();
+            let recieved = recieved.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let attempts = attempts.clone();
+                    let recieved = recieved.clone();
+                    async move {
+                        let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+                        let header = |name| {
+                            req.headers()
+                                .get(name)
+                                .map(|v| v.to_str().unwrap().to_string())
+                                .unwrap_or_default()
+                        };
+                        let event = header(EVENT_HEADER);
+                        let signature = header(SIGNATURE_HEADER);
+                        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+
+                        let status = if attempt < failures {
+                            StatusCode::INTERNAL_SERVER_ERROR
+                        } else {
+                            recieved.lock().push(Recieved {
+                                event,
+                                signature,
+                                body: body.to_vec(),
+                            });
+                            StatusCode::OK
+                        };
+
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::empty())
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        }
+    });
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+
+    Receiver {
+        addr,
+        attempts,
+        recieved,
+    }
+}
+
+fn fast_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(10),
+    }
+}
+
+#[tokio::test]
+async fn delivers_signed_payload() {
+    let receiver = start_receiver(0);
+    let event = WebhookEvent::LobbyFull {
+        lobby_id: uuid::Uuid::new_v4(),
+    };
+    let body = payload("1234", &event);
+
+    let attempts = deliver(
+        &reqwest::Client::new(),
+        &format!("http://{}/hook", receiver.addr),
+        "secret",
+        event.name(),
+        &body,
+        fast_policy(3),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(attempts, 1);
+
+    let recieved = receiver.recieved.lock();
+    assert_eq!(recieved.len(), 1);
+    assert_eq!(recieved[0].event, "lobby_full");
+    assert_eq!(recieved[0].body, body);
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+    mac.update(&body);
+    let expected = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+    assert_eq!(recieved[0].signature, expected);
+    assert_eq!(sign("secret", &body), expected);
+
+    let json: serde_json::Value = serde_json::from_slice(&recieved[0].body).unwrap();
+    assert_eq!(json["event"], "lobby_full");
+    assert_eq!(json["guild_id"], "1234");
+}
+
+#[tokio::test]
+async fn retries_until_success() {
+    let receiver = start_receiver(2);
+    let event = WebhookEvent::LobbyFinished {
+        lobby_id: uuid::Uuid::new_v4(),
+    };
+    let body = payload("1234", &event);
+
+    let attempts = deliver(
+        &reqwest::Client::new(),
+        &format!("http://{}/hook", receiver.addr),
+        "secret",
+        event.name(),
+        &body,
+        fast_policy(5),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(attempts, 3);
+    assert_eq!(receiver.attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(receiver.recieved.lock().len(), 1);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_attempts() {
+    let receiver = start_receiver(u32::MAX);
+    let event = WebhookEvent::LobbyFinished {
+        lobby_id: uuid::Uuid::new_v4(),
+    };
+    let body = payload("1234", &event);
+
+    let result = deliver(
+        &reqwest::Client::new(),
+        &format!("http://{}/hook", receiver.addr),
+        "secret",
+        event.name(),
+        &body,
+        fast_policy(3),
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(receiver.attempts.load(Ordering::SeqCst), 3);
+    assert!(receiver.recieved.lock().is_empty());
+}
+
+#[test]
+fn backoff_doubles() {
+    let policy = RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_secs(1),
+    };
+
+    assert_eq!(policy.delay(0), Duration::from_secs(1));
+    assert_eq!(policy.delay(1), Duration::from_secs(2));
+    assert_eq!(policy.delay(3), Duration::from_secs(8));
+}
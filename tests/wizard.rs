@@ -0,0 +1,208 @@
+mod common;
+
+use ark_guild_bot::{
+    commands::lobby::{
+        command::State,
+        context::LobbyContext,
+        requirements::VOICE_RULE,
+        wizard::{apply, DetailsInput, Transition, WizardEvent},
+    },
+    component_id::{ComponentId, WIZARD},
+    storage::MemoryStorage,
+};
+use chrono::{Duration, Utc};
+use common::{lobby_context, seeded_storage};
+
+async fn select_content(
+    lobby_context: &mut LobbyContext,
+    content_type: &str,
+    key: &str,
+    db: &MemoryStorage,
+) {
+    for event in [
+        WizardEvent::Category(content_type.to_string()),
+        WizardEvent::Content(key.to_string()),
+    ] {
+        let transition = apply(lobby_context, event, db).await.unwrap();
+        assert_eq!(transition, Transition::Render { page: 0 });
+    }
+}
+
+#[tokio::test]
+async fn contents_without_options_go_to_the_players() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context();
+
+    select_content(&mut lobby_context, "guardian-raid", "Urnil", &db).await;
+
+    assert!(matches!(lobby_context.state, State::CollectPlayers));
+    assert_eq!(lobby_context.players.len(), 3);
+    assert_eq!(lobby_context.player_list.len(), 4);
+}
+
+#[tokio::test]
+async fn back_returns_to_the_previous_steps() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context();
+
+    select_content(&mut lobby_context, "abyss-raid", "Argos", &db).await;
+    assert!(matches!(lobby_context.state, State::OptionSelection));
+
+    for event in [
+        WizardEvent::GateEnd(1),
+        WizardEvent::Continue,
+        WizardEvent::AddPlayer(0),
+    ] {
+        apply(&mut lobby_context, event, &db).await.unwrap();
+    }
+    assert!(matches!(lobby_context.state, State::CollectPlayers));
+    assert_eq!(lobby_context.active_players.len(), 1);
+
+    apply(&mut lobby_context, WizardEvent::Back, &db)
+        .await
+        .unwrap();
+    assert!(matches!(lobby_context.state, State::OptionSelection));
+    assert!(lobby_context.active_players.is_empty());
+    assert_eq!(lobby_context.options.gate_end, Some(1));
+
+    apply(&mut lobby_context, WizardEvent::Back, &db)
+        .await
+        .unwrap();
+    assert!(matches!(lobby_context.state, State::LobbyFirstPrompt));
+    assert!(lobby_context.content_info.is_none());
+
+    apply(&mut lobby_context, WizardEvent::Back, &db)
+        .await
+        .unwrap();
+    assert!(matches!(lobby_context.state, State::ContentSelection));
+    assert!(lobby_context.content.is_none());
+}
+
+#[tokio::test]
+async fn events_of_other_steps_show_the_current_step() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context();
+
+    let transition = apply(&mut lobby_context, WizardEvent::Post, &db)
+        .await
+        .unwrap();
+
+    assert_eq!(transition, Transition::Render { page: 0 });
+    assert!(matches!(lobby_context.state, State::ContentSelection));
+}
+
+#[tokio::test]
+async fn lobby_is_posted_or_cancelled() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context();
+
+    select_content(&mut lobby_context, "guardian-raid", "Urnil", &db).await;
+
+    let transition = apply(&mut lobby_context, WizardEvent::Cancel, &db)
+        .await
+        .unwrap();
+    assert_eq!(transition, Transition::Cancel);
+
+    let transition = apply(&mut lobby_context, WizardEvent::Open, &db)
+        .await
+        .unwrap();
+    assert_eq!(transition, Transition::Post);
+    assert!(matches!(lobby_context.state, State::PublicLobby));
+}
+
+#[tokio::test]
+async fn details_set_the_time_the_note_and_the_requirements() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context();
+
+    let details = DetailsInput {
+        time: (Utc::now() + Duration::days(1)).to_rfc3339(),
+        note: "Bring grenades".to_string(),
+        min_clears: "2".to_string(),
+        engravings: "Grudge, , Keen Blunt Weapon".to_string(),
+        language: "English".to_string(),
+    };
+    let transition = apply(&mut lobby_context, WizardEvent::Details(details), &db)
+        .await
+        .unwrap();
+    assert_eq!(transition, Transition::Render { page: 0 });
+    assert!(lobby_context.lobby_time.is_some());
+    assert_eq!(lobby_context.note.as_deref(), Some("Bring grenades"));
+    assert_eq!(lobby_context.requirements.min_clears, Some(2));
+    assert_eq!(
+        lobby_context.requirements.engravings,
+        ["Grudge", "Keen Blunt Weapon"]
+    );
+    assert_eq!(
+        lobby_context.requirements.language.as_deref(),
+        Some("English")
+    );
+
+    for (details, reason) in [
+        (
+            DetailsInput {
+                time: "not a time".to_string(),
+                ..Default::default()
+            },
+            "lobby.time_parse_error",
+        ),
+        (
+            DetailsInput {
+                time: (Utc::now() + Duration::minutes(5)).to_rfc3339(),
+                ..Default::default()
+            },
+            "lobby.time_too_soon",
+        ),
+        (
+            DetailsInput {
+                min_clears: "many".to_string(),
+                ..Default::default()
+            },
+            "lobby.invalid_min_clears",
+        ),
+    ] {
+        let transition = apply(&mut lobby_context, WizardEvent::Details(details), &db)
+            .await
+            .unwrap();
+        assert_eq!(transition, Transition::Rejected(reason));
+        assert_eq!(lobby_context.note.as_deref(), Some("Bring grenades"));
+    }
+
+    let details = WizardEvent::Details(DetailsInput::default());
+    apply(&mut lobby_context, details, &db).await.unwrap();
+    assert!(lobby_context.lobby_time.is_none());
+    assert!(lobby_context.note.is_none());
+    assert!(lobby_context.requirements.is_empty());
+}
+
+#[tokio::test]
+async fn rules_are_selected_with_the_players() {
+    let db = seeded_storage().await;
+    let mut lobby_context = lobby_context();
+
+    select_content(&mut lobby_context, "guardian-raid", "Urnil", &db).await;
+    let rules = WizardEvent::Rules(vec![VOICE_RULE.to_string()]);
+    apply(&mut lobby_context, rules, &db).await.unwrap();
+
+    assert!(lobby_context.requirements.voice);
+    assert!(!lobby_context.requirements.gold_run);
+}
+
+#[test]
+fn components_are_parsed_to_events() {
+    let gate_end = ComponentId::new(WIZARD, "lobby", "gate-end");
+    assert_eq!(
+        WizardEvent::parse(&gate_end, &["2".to_string()]).unwrap(),
+        WizardEvent::GateEnd(2)
+    );
+    assert!(WizardEvent::parse(&gate_end, &[]).is_err());
+
+    let category = ComponentId::new(WIZARD, "lobby", "category").with_arg("abyss-raid");
+    assert_eq!(
+        WizardEvent::parse(&category, &[]).unwrap(),
+        WizardEvent::Category("abyss-raid".to_string())
+    );
+
+    let unknown = ComponentId::new(WIZARD, "lobby", "unknown");
+    assert!(WizardEvent::parse(&unknown, &[]).is_err());
+}